@@ -5,9 +5,11 @@ import android.content.pm.PackageManager
 import android.util.Log
 import id.xms.xtrakernelmanager.data.model.AppBatteryStats
 import id.xms.xtrakernelmanager.data.model.BatteryUsageType
+import id.xms.xtrakernelmanager.domain.native.NativeLib
 import id.xms.xtrakernelmanager.domain.root.RootManager
 import kotlinx.coroutines.Dispatchers
 import kotlinx.coroutines.withContext
+import org.json.JSONObject
 
 object AppBatteryRepository {
   private const val TAG = "AppBatteryRepository"
@@ -15,9 +17,8 @@ object AppBatteryRepository {
   suspend fun getAppBatteryUsage(context: Context): List<AppBatteryStats> =
       withContext(Dispatchers.IO) {
         val result = RootManager.executeCommand("dumpsys batterystats --charged")
-        if (result.isFailure) return@withContext emptyList()
-
-        val output = result.getOrNull() ?: return@withContext emptyList()
+        val output = result.getOrNull()
+        if (result.isFailure || output == null) return@withContext getCpuEnergyUsage(context)
         val statsList = mutableListOf<AppBatteryStats>()
         val packageManager = context.packageManager
 
@@ -89,9 +90,48 @@ object AppBatteryRepository {
           }
         }
 
+        if (statsList.isEmpty()) return@withContext getCpuEnergyUsage(context)
         statsList.sortedByDescending { it.percent }
       }
 
+  /**
+   * CPU energy share per app over the last [windowSecs], from the kernel's per-UID accounting. The
+   * first sample only has totals since boot, which are not a window, so it yields an empty list.
+   */
+  suspend fun getCpuEnergyUsage(context: Context, windowSecs: Int = 3600): List<AppBatteryStats> =
+      withContext(Dispatchers.IO) {
+        val json = NativeLib.sampleUidCpu(windowSecs) ?: return@withContext emptyList()
+        val packageManager = context.packageManager
+
+        try {
+          val report = JSONObject(json)
+          if (report.optBoolean("since_boot", false)) {
+            Log.d(TAG, "UID CPU sampler has no baseline yet, skipping since-boot totals")
+            return@withContext emptyList()
+          }
+          val uids = report.optJSONArray("uids") ?: return@withContext emptyList()
+          (0 until uids.length())
+              .map { uids.getJSONObject(it) }
+              .filter { it.optDouble("energy_share", 0.0) > 0.0 }
+              .map { entry ->
+                val uid = entry.getInt("uid")
+                val (appName, pkgName, icon, type) = resolveUid(packageManager, uid)
+                AppBatteryStats(
+                    uid = uid,
+                    packageName = pkgName,
+                    appName = appName,
+                    icon = icon,
+                    percent = entry.getDouble("energy_share") * 100.0,
+                    usageType = type,
+                )
+              }
+              .sortedByDescending { it.percent }
+        } catch (e: Exception) {
+          Log.e(TAG, "Failed to parse native UID CPU report", e)
+          emptyList()
+        }
+      }
+
   private fun parseUid(uidStr: String): Int {
     return try {
       if (uidStr.startsWith("u0a")) {
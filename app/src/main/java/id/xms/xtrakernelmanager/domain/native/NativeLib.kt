@@ -2,7 +2,11 @@ package id.xms.xtrakernelmanager.domain.native
 
 import android.util.Log
 import id.xms.xtrakernelmanager.data.model.ClusterInfo
+import id.xms.xtrakernelmanager.data.model.CpuClusterLockConfig
+import id.xms.xtrakernelmanager.data.model.ThermalAction
+import id.xms.xtrakernelmanager.data.model.ThermalPolicy
 import org.json.JSONArray
+import org.json.JSONObject
 
 /**
  * Native library wrapper for Rust-based system operations. Provides faster sysfs/procfs reading
@@ -683,4 +687,1080 @@ object NativeLib {
       null
     }
   }
+
+  // ============== CPU Governor Tunables ==============
+
+  /**
+   * Read the tunables of the governor running on a cpufreq policy (numbered after its first CPU).
+   * Returns JSON with governor, path and a list of {name, value, kind, writable}.
+   */
+  fun getGovernorTunables(policy: Int): String? {
+    if (!isLoaded) return null
+    return try {
+      val json = getGovernorTunablesNative(policy)
+      if (json != "{}") json else null
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getGovernorTunables failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Validate, write and read back a governor tunable. Returns WriteResult JSON or {"error"} */
+  fun setGovernorTunable(policy: Int, name: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      setGovernorTunableNative(policy, name, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setGovernorTunable failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Remember the current governor's tunables so they can be restored later */
+  fun snapshotGovernorTunables(policy: Int): String? {
+    if (!isLoaded) return null
+    return try {
+      val json = snapshotGovernorTunablesNative(policy)
+      if (json != "{}") json else null
+    } catch (e: Exception) {
+      Log.e(TAG, "Native snapshotGovernorTunables failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Re-apply the last snapshot taken for the current governor */
+  fun restoreGovernorTunables(policy: Int): String? {
+    if (!isLoaded) return null
+    return try {
+      restoreGovernorTunablesNative(policy)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native restoreGovernorTunables failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Switch governor, snapshotting the old governor's tunables and restoring the new one's */
+  fun switchCpuGovernor(policy: Int, governor: String): String? {
+    if (!isLoaded) return null
+    return try {
+      val json = switchCpuGovernorNative(policy, governor)
+      if (json != "{}") json else null
+    } catch (e: Exception) {
+      Log.e(TAG, "Native switchCpuGovernor failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getGovernorTunablesNative(policy: Int): String
+
+  private external fun setGovernorTunableNative(policy: Int, name: String, value: String): String
+
+  private external fun snapshotGovernorTunablesNative(policy: Int): String
+
+  private external fun restoreGovernorTunablesNative(policy: Int): String
+
+  private external fun switchCpuGovernorNative(policy: Int, governor: String): String
+
+  // ============== CPU Idle States ==============
+
+  /** Read every core's cpuidle states (name, latency, usage, time, disable, above/below) as JSON */
+  fun readCpuIdleStates(): String? {
+    if (!isLoaded) return null
+    return try {
+      readCpuIdleStatesNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readCpuIdleStates failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Disable or re-enable an idle state on every core of a cluster */
+  fun setClusterIdleState(cluster: Int, state: Int, disable: Boolean): String? {
+    if (!isLoaded) return null
+    return try {
+      setClusterIdleStateNative(cluster, state, disable)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setClusterIdleState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readCpuIdleStatesNative(): String
+
+  private external fun setClusterIdleStateNative(cluster: Int, state: Int, disable: Boolean): String
+
+  // ============== Device-tree OPP Tables ==============
+
+  /**
+   * Read every operating-points-v2 table from the device tree. CPU tables carry their cpus and
+   * cpufreq policy, the GPU table is tagged "gpu" with the kgsl/Mali device it belongs to; each
+   * entry has freq_hz, microvolt, supported_hw and turbo.
+   */
+  fun getOppTables(): String? {
+    if (!isLoaded) return null
+    return try {
+      getOppTablesNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getOppTables failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getOppTablesNative(): String
+
+  // ============== SoC Identification ==============
+
+  /**
+   * Identify the SoC from props, soc0, device-tree compatible and CPU part IDs. Returns JSON with
+   * name, vendor, model, process_node, core_layout (e.g. "1×X3 + 2×A715 + 2×A710 + 3×A510"),
+   * detected_layout, matched_by and the raw sources.
+   */
+  fun getSocInfo(): String? {
+    if (!isLoaded) return null
+    return try {
+      getSocInfoNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getSocInfo failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getSocInfoNative(): String
+
+  // ============== Per-core Thermal Mapping ==============
+
+  /**
+   * Report which thermal zones measure each core, derived from zone type names (cpu-1-2-usr,
+   * cpu0-silver-usr, cpu_big0, BIG...). Cores without a known sensor have an empty list.
+   */
+  fun getCoreThermalMapping(): String? {
+    if (!isLoaded) return null
+    return try {
+      getCoreThermalMappingNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getCoreThermalMapping failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getCoreThermalMappingNative(): String
+
+  // ============== CPU Hotplug ==============
+
+  /**
+   * Bring cores ("4-7", "1,3") online or offline. cpu0 and the last online core of each cluster
+   * are kept unless allowEmptyCluster is set; core_ctl is held back so it does not re-online them.
+   * Returns JSON with per-core status (applied, unchanged, rejected, refused).
+   */
+  fun setCoresOnline(cores: String, online: Boolean, allowEmptyCluster: Boolean = false): String? {
+    if (!isLoaded) return null
+    return try {
+      setCoresOnlineNative(cores, online, allowEmptyCluster)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setCoresOnline failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setCoresOnlineNative(
+      cores: String,
+      online: Boolean,
+      allowEmptyCluster: Boolean,
+  ): String
+
+  /** Read Qualcomm core_ctl state (enable, min_cpus, max_cpus) per cluster. */
+  fun getCoreCtl(): String? {
+    if (!isLoaded) return null
+    return try {
+      getCoreCtlNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getCoreCtl failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getCoreCtlNative(): String
+
+  /** Set core_ctl nodes for the cluster starting at [cpu]. Pass -1 to leave a value unchanged. */
+  fun setCoreCtl(cpu: Int, enable: Int = -1, minCpus: Int = -1, maxCpus: Int = -1): String? {
+    if (!isLoaded) return null
+    return try {
+      setCoreCtlNative(cpu, enable, minCpus, maxCpus)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setCoreCtl failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setCoreCtlNative(cpu: Int, enable: Int, minCpus: Int, maxCpus: Int): String
+
+  // ============== Frequency Lock Engine ==============
+
+  /**
+   * Point the native lock engine at [stateDir] for persistence and resume a lock left by a
+   * previous process. Returns the engine state as JSON.
+   */
+  fun initFreqLock(stateDir: String): String? {
+    if (!isLoaded) return null
+    return try {
+      initFreqLockNative(stateDir)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native initFreqLock failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun initFreqLockNative(stateDir: String): String
+
+  /**
+   * Pin cluster min/max frequencies (MHz, as in [CpuClusterLockConfig]). With [thermalGuard] the native guard thread steps the max
+   * down and back up following [policy]. Returns the engine state, or {"error": ...}.
+   */
+  fun lockCpuFrequencies(
+      clusterConfigs: Map<Int, CpuClusterLockConfig>,
+      policy: ThermalPolicy,
+      thermalGuard: Boolean = true,
+  ): String? {
+    if (!isLoaded) return null
+    return try {
+      val locks = JSONArray()
+      clusterConfigs.forEach { (cluster, config) ->
+        locks.put(
+            JSONObject()
+                .put("cluster", cluster)
+                .put("min_mhz", config.minFreq)
+                .put("max_mhz", config.maxFreq))
+      }
+      val request =
+          JSONObject()
+              .put("locks", locks)
+              .put("policy", thermalPolicyJson(policy))
+              .put("thermal_guard", thermalGuard)
+      lockCpuFrequenciesNative(request.toString())
+    } catch (e: Exception) {
+      Log.e(TAG, "Native lockCpuFrequencies failed: ${e.message}")
+      null
+    }
+  }
+
+  private fun thermalPolicyJson(policy: ThermalPolicy): JSONObject {
+    fun action(action: ThermalAction) =
+        when (action) {
+          ThermalAction.NONE -> "none"
+          ThermalAction.UNLOCK_ALL -> "unlock_all"
+          ThermalAction.UNLOCK_AND_GOVERNOR_POWERSAVE -> "unlock_and_governor_powersave"
+          // The native engine cannot power the device off; its strongest action
+          // is dropping every policy to powersave.
+          ThermalAction.EMERGENCY_SHUTDOWN -> "unlock_and_governor_powersave"
+        }
+
+    return JSONObject()
+        .put("name", policy.name)
+        .put("critical_threshold", policy.criticalThreshold.toDouble())
+        .put("emergency_threshold", policy.emergencyThreshold.toDouble())
+        .put("warning_threshold", policy.warningThreshold.toDouble())
+        .put("restore_threshold", policy.restoreThreshold.toDouble())
+        .put("restore_delay_ms", policy.restoreDelay)
+        .put("warning_cooldown_ms", policy.warningCooldown)
+        .put("action_on_emergency", action(policy.behavior.actionOnEmergency))
+        .put("action_on_critical", action(policy.behavior.actionOnCritical))
+        .put("auto_restore", policy.behavior.autoRestoreEnabled)
+        .put("max_retries_per_hour", policy.behavior.maxRetriesPerHour)
+  }
+
+  private external fun lockCpuFrequenciesNative(request: String): String
+
+  /** Restore the frequencies and governors recorded before the lock. */
+  fun unlockCpuFrequencies(): String? {
+    if (!isLoaded) return null
+    return try {
+      unlockCpuFrequenciesNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native unlockCpuFrequencies failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun unlockCpuFrequenciesNative(): String
+
+  /** Current lock engine state: locks, step, override flag, last temperature. */
+  fun getFreqLockState(): String? {
+    if (!isLoaded) return null
+    return try {
+      getFreqLockStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getFreqLockState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getFreqLockStateNative(): String
+
+  /**
+   * Drain lock engine events (locked, step_down, step_up, emergency, critical, restored...) raised
+   * since the last call.
+   */
+  fun pollFreqLockEvents(): String? {
+    if (!isLoaded) return null
+    return try {
+      pollFreqLockEventsNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native pollFreqLockEvents failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun pollFreqLockEventsNative(): String
+
+  // ============== Frequency Limit Explainer ==============
+
+  /**
+   * Explain each cluster's effective max frequency: cooling devices, PM QoS, msm_performance,
+   * core_ctl and boost nodes, each flagged active or not. [requested] is a per-CPU map such as
+   * "4:3200000 7:3200000"; clusters without an entry use the frequency lock target.
+   */
+  fun explainFrequencyLimits(requested: String = ""): String? {
+    if (!isLoaded) return null
+    return try {
+      explainFrequencyLimitsNative(requested)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native explainFrequencyLimits failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun explainFrequencyLimitsNative(requested: String): String
+
+  // ============== Scheduler cgroups ==============
+
+  /** Read cpuset masks, cpuctl uclamp/shares and schedtune boost/prefer_idle per group. */
+  fun readCgroups(): String? {
+    if (!isLoaded) return null
+    return try {
+      readCgroupsNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readCgroups failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readCgroupsNative(): String
+
+  /**
+   * Write one cgroup parameter after validation, e.g. ("cpuset", "top-app", "cpus", "0-7") or
+   * ("cpuctl", "top-app", "cpu.uclamp.min", "20"). CPU masks are checked against the topology.
+   */
+  fun writeCgroup(controller: String, group: String, param: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      writeCgroupNative(controller, group, param, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native writeCgroup failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun writeCgroupNative(
+      controller: String,
+      group: String,
+      param: String,
+      value: String,
+  ): String
+
+  /**
+   * Apply the cpuset/uclamp/schedtune policy for a perf mode. battery and performance record the
+   * vendor values first, balance restores them. Returns results, errors and restored.
+   */
+  fun applySchedProfile(mode: String): String? {
+    if (!isLoaded) return null
+    return try {
+      applySchedProfileNative(mode)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native applySchedProfile failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun applySchedProfileNative(mode: String): String
+
+  // ============== Scheduler sysctls ==============
+
+  /**
+   * List /proc/sys/kernel/sched_* and /proc/sys/walt/* with current value, kind and, for known
+   * names, the valid range and a description.
+   */
+  fun readSchedSysctls(): String? {
+    if (!isLoaded) return null
+    return try {
+      readSchedSysctlsNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readSchedSysctls failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readSchedSysctlsNative(): String
+
+  /** Write a scheduler sysctl; [source] is "kernel" or "walt". Returns {"error": ...} if invalid. */
+  fun writeSchedSysctl(source: String, name: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      writeSchedSysctlNative(source, name, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native writeSchedSysctl failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun writeSchedSysctlNative(source: String, name: String, value: String): String
+
+  // ============== Input Boost ==============
+
+  /**
+   * Detect cpu_boost / cpu_input_boost / msm_performance and return their parameters, with
+   * frequency maps resolved per cluster.
+   */
+  fun readInputBoost(): String? {
+    if (!isLoaded) return null
+    return try {
+      readInputBoostNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readInputBoost failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readInputBoostNative(): String
+
+  /**
+   * Write a boost parameter, e.g. ("cpu_boost", "input_boost_freq", "0:1209600 4:0") or
+   * ("cpu_boost", "input_boost_ms", "40"). Frequencies must be in the cluster's table; a
+   * single-value input_boost_freq (kind "freq") takes a bare frequency such as "1209600".
+   */
+  fun writeInputBoost(driver: String, param: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      writeInputBoostNative(driver, param, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native writeInputBoost failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun writeInputBoostNative(driver: String, param: String, value: String): String
+
+  // ============== Process Sampler ==============
+
+  /**
+   * Top [limit] processes by CPU% since the previous call (the first call primes and reports 0).
+   * Each entry has pid, name, cmdline, state, uid, cpu_percent, last_cpu, priority, nice, threads,
+   * rss_kb, oom_score_adj and cgroup.
+   */
+  fun sampleProcesses(limit: Int = 50): String? {
+    if (!isLoaded) return null
+    return try {
+      sampleProcessesNative(limit)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native sampleProcesses failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun sampleProcessesNative(limit: Int): String
+
+  /** Top [limit] threads of [pid] by CPU% since the previous call for the same pid. */
+  fun sampleThreads(pid: Int, limit: Int = 50): String? {
+    if (!isLoaded) return null
+    return try {
+      sampleThreadsNative(pid, limit)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native sampleThreads failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun sampleThreadsNative(pid: Int, limit: Int): String
+
+  // ============== Per-UID CPU Energy ==============
+
+  /**
+   * Per-UID CPU time and energy over the last [windowSecs], from `/proc/uid_cputime`,
+   * `uid_time_in_state` and the `uid_concurrent_*` files. Returns a report with since_boot,
+   * interval_ms, sources, power_model, policies and uids (user_ms, system_ms, time_in_state_ms,
+   * concurrent_active_ms, concurrent_policy_ms, energy_mj, energy_share). The first call reports
+   * totals since boot.
+   */
+  fun sampleUidCpu(windowSecs: Int = 3600): String? {
+    if (!isLoaded) return null
+    return try {
+      sampleUidCpuNative(windowSecs)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native sampleUidCpu failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun sampleUidCpuNative(windowSecs: Int): String
+
+  // ============== Performance Counters ==============
+
+  /**
+   * System-wide perf counters on every online core over [intervalMs] (10-10000 ms). Blocks for the
+   * interval, so call it off the main thread. Reports cycles, instructions, cache/branch misses,
+   * task-clock and context switches with IPC, MPKI and a memory/compute verdict per core, per
+   * cluster and in total. When hardware counters are refused (PMU missing or
+   * `perf_event_paranoid` too strict) mode is "software"; errors lists each refusal.
+   */
+  fun measurePerfCounters(intervalMs: Int = 1000): String? {
+    if (!isLoaded) return null
+    return try {
+      measurePerfCountersNative(intervalMs)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native measurePerfCounters failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun measurePerfCountersNative(intervalMs: Int): String
+
+  // ============== Frequency Tracer ==============
+
+  /**
+   * Starts sampling every core's `scaling_cur_freq` and the GPU clock at [rateHz] (1-100) on a
+   * native thread, e.g. when a game session begins. Returns the initial summary, or an object with
+   * "error" if a trace is already running.
+   */
+  fun startFreqTrace(rateHz: Int = 50): String? {
+    if (!isLoaded) return null
+    return try {
+      startFreqTraceNative(rateHz)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native startFreqTrace failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun startFreqTraceNative(rateHz: Int): String
+
+  /**
+   * Stops the trace and returns its summary: per-channel min/avg/max, histogram `[mhz, samples]`,
+   * per-second `[second, min, avg, max]`, and per-domain throttle counts. "null" if none was started.
+   */
+  fun stopFreqTrace(): String? {
+    if (!isLoaded) return null
+    return try {
+      stopFreqTraceNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native stopFreqTrace failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun stopFreqTraceNative(): String
+
+  /** Summary so far of the running trace, or of the last finished one. */
+  fun getFreqTraceSummary(): String? {
+    if (!isLoaded) return null
+    return try {
+      getFreqTraceSummaryNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getFreqTraceSummary failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getFreqTraceSummaryNative(): String
+
+  // ============== Adreno (kgsl) Tuning ==============
+
+  /**
+   * kgsl state: levels_mhz (power-level order, level 0 fastest), devfreq min/max, max/min/default
+   * power levels, governor, throttling, force_clk_on, force_bus_on and idle_timer_ms. "null" when
+   * the device has no kgsl.
+   */
+  fun readKgslState(): String? {
+    if (!isLoaded) return null
+    return try {
+      readKgslStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readKgslState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readKgslStateNative(): String
+
+  /**
+   * Writes one kgsl param and reads it back. [param] is one of min_freq, max_freq, max_pwrlevel,
+   * min_pwrlevel, default_pwrlevel (all in MHz; power levels are resolved from the frequency
+   * table), governor, throttling, force_clk_on, force_bus_on (0/1) or idle_timer (ms). The result
+   * carries the read-back value, the resulting freq_mhz and whether it was clamped.
+   */
+  fun setKgslParam(param: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      setKgslParamNative(param, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setKgslParam failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setKgslParamNative(param: String, value: String): String
+
+  /**
+   * Sets devfreq min/max and the matching power levels in an order the kernel accepts. Returns
+   * writes and errors (nodes that could not be written); an error object when nothing was written.
+   */
+  fun setKgslFreqRange(minMhz: Int, maxMhz: Int): String? {
+    if (!isLoaded) return null
+    return try {
+      setKgslFreqRangeNative(minMhz, maxMhz)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setKgslFreqRange failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setKgslFreqRangeNative(minMhz: Int, maxMhz: Int): String
+
+  // ============== Mali Tuning ==============
+
+  /**
+   * Mali state from the kbase device and its devfreq node (Tensor, Dimensity, Exynos):
+   * available_frequencies_mhz, cur/min/max_freq_mhz, governor, dvfs_period_ms, core_mask
+   * (current/available), power_policy and utilization. "null" when no Mali GPU is found.
+   */
+  fun readMaliState(): String? {
+    if (!isLoaded) return null
+    return try {
+      readMaliStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readMaliState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readMaliStateNative(): String
+
+  /**
+   * Writes one Mali param and reads it back. [param] is one of min_freq, max_freq (MHz, snapped to
+   * the table), governor, dvfs_period (ms), core_mask (hex, within the available cores) or
+   * power_policy.
+   */
+  fun setMaliParam(param: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      setMaliParamNative(param, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setMaliParam failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setMaliParamNative(param: String, value: String): String
+
+  // ============== Devfreq ==============
+
+  /**
+   * Every /sys/class/devfreq device (GPU, DDR, LLCC, bus voters) with kind, governor,
+   * available_governors, cur/min/max/target_freq, available_frequencies and trans_stat residency.
+   * Frequencies are in each driver's own unit.
+   */
+  fun listDevfreq(): String? {
+    if (!isLoaded) return null
+    return try {
+      listDevfreqNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native listDevfreq failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun listDevfreqNative(): String
+
+  /**
+   * Writes governor, min_freq or max_freq of one devfreq [device] and reads it back. Limits snap to
+   * available_frequencies.
+   */
+  fun setDevfreqParam(device: String, param: String, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      setDevfreqParamNative(device, param, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setDevfreqParam failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setDevfreqParamNative(device: String, param: String, value: String): String
+
+  /** Sets min and max of one devfreq [device] in an order the kernel accepts. */
+  fun setDevfreqRange(device: String, minFreq: Long, maxFreq: Long): String? {
+    if (!isLoaded) return null
+    return try {
+      setDevfreqRangeNative(device, minFreq, maxFreq)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setDevfreqRange failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setDevfreqRangeNative(device: String, minFreq: Long, maxFreq: Long): String
+
+  // ============== GPU Residency ==============
+
+  /**
+   * Time at each GPU frequency (slowest first) with percent, plus delta_ms/delta_percent since the
+   * previous call. source is devfreq_trans_stat (wall time) or kgsl_clock_stats (busy time only).
+   */
+  fun readGpuResidency(): String? {
+    if (!isLoaded) return null
+    return try {
+      readGpuResidencyNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readGpuResidency failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readGpuResidencyNative(): String
+
+  /** Starts counting GPU residency from now, e.g. when a game is launched. */
+  fun resetGpuResidency() {
+    if (!isLoaded) return
+    try {
+      resetGpuResidencyNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native resetGpuResidency failed: ${e.message}")
+    }
+  }
+
+  private external fun resetGpuResidencyNative()
+
+  // ============== Samplers ==============
+
+  /** Disk read/write totals and speeds (bytes/s) since the previous global read. */
+  fun readDiskStats(): String? {
+    if (!isLoaded) return null
+    return try {
+      readDiskStatsNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readDiskStats failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readDiskStatsNative(): String
+
+  /**
+   * Creates a sampler that keeps its own previous CPU, GPU, disk and cpuidle readings, so each
+   * consumer (dashboard, overlay, tile) gets deltas over its own polling interval. Returns 0 when the
+   * library is not loaded. Every handle must be passed to [releaseSampler] exactly once, and not
+   * used afterwards.
+   */
+  fun createSampler(): Long {
+    if (!isLoaded) return 0L
+    return try {
+      createSamplerNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native createSampler failed: ${e.message}")
+      0L
+    }
+  }
+
+  fun releaseSampler(handle: Long) {
+    if (!isLoaded || handle == 0L) return
+    try {
+      releaseSamplerNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native releaseSampler failed: ${e.message}")
+    }
+  }
+
+  fun samplerReadCpuLoad(handle: Long): Float? {
+    if (!isLoaded || handle == 0L) return null
+    return try {
+      samplerReadCpuLoadNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReadCpuLoad failed: ${e.message}")
+      null
+    }
+  }
+
+  /** total_load and per_core_load since this sampler's previous CPU read. */
+  fun samplerReadCoreLoads(handle: Long): String? {
+    if (!isLoaded || handle == 0L) return null
+    return try {
+      samplerReadCoreLoadsNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReadCoreLoads failed: ${e.message}")
+      null
+    }
+  }
+
+  fun samplerReadGpuBusy(handle: Long): Int? {
+    if (!isLoaded || handle == 0L) return null
+    return try {
+      samplerReadGpuBusyNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReadGpuBusy failed: ${e.message}")
+      null
+    }
+  }
+
+  fun samplerReadDiskStats(handle: Long): String? {
+    if (!isLoaded || handle == 0L) return null
+    return try {
+      samplerReadDiskStatsNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReadDiskStats failed: ${e.message}")
+      null
+    }
+  }
+
+  /**
+   * Per-core, per-state residency percentages since this sampler's previous idle read. The first
+   * read only primes the sample and returns an empty list.
+   */
+  fun samplerReadCpuIdleResidency(handle: Long): String? {
+    if (!isLoaded || handle == 0L) return null
+    return try {
+      samplerReadCpuIdleResidencyNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReadCpuIdleResidency failed: ${e.message}")
+      null
+    }
+  }
+
+  /** Clears only this sampler's previous readings; other consumers are unaffected. */
+  fun samplerReset(handle: Long) {
+    if (!isLoaded || handle == 0L) return
+    try {
+      samplerResetNative(handle)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native samplerReset failed: ${e.message}")
+    }
+  }
+
+  private external fun createSamplerNative(): Long
+
+  private external fun releaseSamplerNative(handle: Long)
+
+  private external fun samplerReadCpuLoadNative(handle: Long): Float
+
+  private external fun samplerReadCoreLoadsNative(handle: Long): String
+
+  private external fun samplerReadGpuBusyNative(handle: Long): Int
+
+  private external fun samplerReadDiskStatsNative(handle: Long): String
+
+  private external fun samplerReadCpuIdleResidencyNative(handle: Long): String
+
+  private external fun samplerResetNative(handle: Long)
+
+  // ============== GPU Identity ==============
+
+  /**
+   * GPU vendor, exact model (e.g. "Adreno 740"), generation, core count and raw chip id, read from
+   * kgsl/kbase sysfs, the device tree and ro.hardware.egl/vulkan. "null" when nothing matched.
+   */
+  fun getGpuIdentity(): String? {
+    if (!isLoaded) return null
+    return try {
+      getGpuIdentityNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getGpuIdentity failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getGpuIdentityNative(): String
+
+  // ============== Xclipse / PowerVR ==============
+
+  /**
+   * Samsung Xclipse state from /sys/kernel/gpu: cur/min/max_freq_mhz, available_frequencies_mhz,
+   * utilization, governor and available_governors. "null" on other GPUs.
+   */
+  fun readXclipseState(): String? {
+    if (!isLoaded) return null
+    return try {
+      readXclipseStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readXclipseState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readXclipseStateNative(): String
+
+  /**
+   * PowerVR state from devfreq (Unisoc) or MediaTek gpufreq: source, cur_freq_mhz,
+   * available_frequencies_mhz, utilization (GED or pvr debugfs) and governor. "null" when neither
+   * is present.
+   */
+  fun readPowerVrState(): String? {
+    if (!isLoaded) return null
+    return try {
+      readPowerVrStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readPowerVrState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readPowerVrStateNative(): String
+
+  // ============== MediaTek ==============
+
+  /**
+   * MediaTek platform state: detected, platform, gpu (gpufreq OPP table, current and fixed OPP,
+   * GED utilization) and cpu (PPM policies and user limits, /proc/cpufreq DVFS domains, power
+   * mode). gpu and cpu are null off MediaTek.
+   */
+  fun readMtkPlatform(): String? {
+    if (!isLoaded) return null
+    return try {
+      readMtkPlatformNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readMtkPlatform failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readMtkPlatformNative(): String
+
+  /**
+   * Sets a MediaTek parameter: gpu_fixed_freq (MHz, 0 releases), ppm_enabled, ppm_policy (target =
+   * policy index, 0/1), ppm_min_freq / ppm_max_freq (target = cluster, kHz, -1 lifts),
+   * cpu_fixed_freq (target = DVFS domain, kHz, 0 releases) or cpu_power_mode. Returns a JSON array
+   * of write results, or {"error": ...}.
+   */
+  fun setMtkParam(param: String, target: Int, value: String): String? {
+    if (!isLoaded) return null
+    return try {
+      setMtkParamNative(param, target, value)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native setMtkParam failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun setMtkParamNative(param: String, target: Int, value: String): String
+
+  // ============== GPU Memory ==============
+
+  /**
+   * GPU memory accounting: source (kgsl or mali), total_bytes, kgsl page_alloc/coherent, dma-buf
+   * totals per exporter, and the top [limit] processes (pid, name, uid, total_bytes, plus kgsl
+   * mapped/unmapped/imported) largest first.
+   */
+  fun readGpuMemory(limit: Int): String? {
+    if (!isLoaded) return null
+    return try {
+      readGpuMemoryNative(limit)
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readGpuMemory failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readGpuMemoryNative(limit: Int): String
+
+  // ============== GPU Throttling / Lock ==============
+
+  /**
+   * GPU throttling state: throttled, max_freq_mhz, allowed_max_mhz, throttle_percent, the sources
+   * (kgsl thermal_pwrlevel, max_gpuclk, GPU cooling devices), GPU zone temperatures and the
+   * current episode (throttled_since_ms, throttled_for_ms, episodes).
+   */
+  fun readGpuThrottle(): String? {
+    if (!isLoaded) return null
+    return try {
+      readGpuThrottleNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native readGpuThrottle failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun readGpuThrottleNative(): String
+
+  /**
+   * Hold the GPU between [minMhz] and [maxMhz]. With [thermalGuard] the ceiling drops one
+   * frequency level per step above [warningThreshold] (up to [maxSteps]), climbs back below
+   * [restoreThreshold] and the lock is released at [criticalThreshold]. Returns the lock state, or
+   * {"error": ...}.
+   */
+  fun lockGpuFrequency(
+      minMhz: Int,
+      maxMhz: Int,
+      warningThreshold: Float = 75f,
+      restoreThreshold: Float = 68f,
+      criticalThreshold: Float = 90f,
+      maxSteps: Int = 3,
+      thermalGuard: Boolean = true,
+  ): String? {
+    if (!isLoaded) return null
+    return try {
+      val request =
+          JSONObject()
+              .put("min_mhz", minMhz)
+              .put("max_mhz", maxMhz)
+              .put(
+                  "policy",
+                  JSONObject()
+                      .put("warning_threshold", warningThreshold.toDouble())
+                      .put("restore_threshold", restoreThreshold.toDouble())
+                      .put("critical_threshold", criticalThreshold.toDouble())
+                      .put("max_steps", maxSteps))
+              .put("thermal_guard", thermalGuard)
+      lockGpuFrequencyNative(request.toString())
+    } catch (e: Exception) {
+      Log.e(TAG, "Native lockGpuFrequency failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun lockGpuFrequencyNative(request: String): String
+
+  /** Restore the GPU range recorded before the lock. */
+  fun unlockGpuFrequency(): String? {
+    if (!isLoaded) return null
+    return try {
+      unlockGpuFrequencyNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native unlockGpuFrequency failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun unlockGpuFrequencyNative(): String
+
+  /** Current GPU lock state: requested and applied range, step, last temperature. */
+  fun getGpuLockState(): String? {
+    if (!isLoaded) return null
+    return try {
+      getGpuLockStateNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native getGpuLockState failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun getGpuLockStateNative(): String
+
+  /** Drain GPU lock events (locked, step_down, step_up, critical, unlocked) since the last poll. */
+  fun pollGpuLockEvents(): String? {
+    if (!isLoaded) return null
+    return try {
+      pollGpuLockEventsNative()
+    } catch (e: Exception) {
+      Log.e(TAG, "Native pollGpuLockEvents failed: ${e.message}")
+      null
+    }
+  }
+
+  private external fun pollGpuLockEventsNative(): String
 }
@@ -220,6 +220,7 @@ class CPUControlUseCase {
     val clusters = detectClusters()
     val targetCluster =
         clusters.getOrNull(cluster) ?: return Result.failure(Exception("Cluster not found"))
+    setMtkClusterLimits(cluster, minFreq, maxFreq)
     targetCluster.cores.forEach { coreNum ->
       val basePath = "/sys/devices/system/cpu/cpu$coreNum"
       RootManager.executeCommand(
@@ -233,6 +234,17 @@ class CPUControlUseCase {
     return Result.success(Unit)
   }
 
+  /**
+   * MediaTek PPM clamps cpufreq with its own hard user limits, so they follow the range. The floor
+   * is lifted first so the new cap never lands below it. Off MediaTek the native side refuses and
+   * nothing is written.
+   */
+  private fun setMtkClusterLimits(cluster: Int, minFreq: Int, maxFreq: Int) {
+    NativeLib.setMtkParam("ppm_min_freq", cluster, "-1")
+    NativeLib.setMtkParam("ppm_max_freq", cluster, "${maxFreq * 1000}")
+    NativeLib.setMtkParam("ppm_min_freq", cluster, "${minFreq * 1000}")
+  }
+
   suspend fun setClusterGovernor(cluster: Int, governor: String): Result<Unit> {
     val clusters = detectClusters()
     val targetCluster =
@@ -268,6 +280,22 @@ class CPUControlUseCase {
 
   suspend fun setCoreOnline(core: Int, online: Boolean): Result<Unit> {
     if (core == 0) return Result.success(Unit)
+
+    // The native hotplug manager enforces the cpu0/last-core-per-cluster invariants and holds
+    // core_ctl back. A refused write usually means the app process lacks permission, so the
+    // shell path below still applies it.
+    NativeLib.setCoresOnline(core.toString(), online)?.let { json ->
+      val result =
+          runCatching { org.json.JSONObject(json).getJSONArray("results").getJSONObject(0) }
+              .getOrNull()
+      when (result?.optString("status")) {
+        "applied",
+        "unchanged" -> return Result.success(Unit)
+        "rejected" ->
+            return Result.failure(IllegalStateException(result.optString("reason", "rejected")))
+      }
+    }
+
     val corePath = "/sys/devices/system/cpu/cpu$core"
     val value = if (online) "1" else "0"
     return RootManager.executeCommand("echo $value > $corePath/online 2>/dev/null").map { Unit }
@@ -2,7 +2,10 @@ package id.xms.xtrakernelmanager.domain.usecase
 
 import android.util.Log
 import id.xms.xtrakernelmanager.data.model.GPUInfo
+import id.xms.xtrakernelmanager.domain.native.NativeLib
 import id.xms.xtrakernelmanager.domain.root.RootManager
+import org.json.JSONArray
+import org.json.JSONObject
 
 class GPUControlUseCase {
 
@@ -373,6 +376,31 @@ class GPUControlUseCase {
 
     Log.d(TAG, "Setting GPU frequency: min=$minFreq MHz, max=$maxFreq MHz")
 
+    setMtkGpuFrequency(minFreq, maxFreq)?.let { return it }
+
+    // The native setter snaps to the level table and verifies every node by read-back. The shell
+    // path only runs when it changed nothing (usually missing permission in the app process);
+    // after a partial write the nodes it missed are reported instead of overwritten.
+    NativeLib.setKgslFreqRange(minFreq, maxFreq)?.let { json ->
+      val report = runCatching { JSONObject(json) }.getOrNull()
+      val writes = report?.optJSONArray("writes")
+      if (writes != null) {
+        val (applied, rejected) =
+            (0 until writes.length())
+                .map { writes.getJSONObject(it) }
+                .partition { it.getJSONObject("result").optBoolean("success") }
+        val errors = report.optJSONArray("errors")
+        val failed =
+            rejected.map { it.optString("param") } +
+                errors?.let { e -> (0 until e.length()).map { e.getString(it) } }.orEmpty()
+        if (failed.isEmpty()) return Result.success(Unit)
+        if (applied.isNotEmpty()) {
+          Log.w(TAG, "GPU frequency partially applied, failed: $failed")
+          return Result.failure(IllegalStateException("GPU frequency partially applied: $failed"))
+        }
+      }
+    }
+
     // Convert MHz to Hz
     val minFreqHz = minFreq * 1000000L
     val maxFreqHz = maxFreq * 1000000L
@@ -426,6 +454,22 @@ class GPUControlUseCase {
     }
   }
 
+  /**
+   * MediaTek gpufreq has no range, only a fixed OPP: min == max pins it and any wider range hands
+   * the GPU back to DVFS. Returns null off MediaTek, where the native side refuses.
+   */
+  private fun setMtkGpuFrequency(minFreq: Int, maxFreq: Int): Result<Unit>? {
+    val mhz = if (minFreq == maxFreq) maxFreq else 0
+    val json = NativeLib.setMtkParam("gpu_fixed_freq", 0, "$mhz") ?: return null
+    val writes = runCatching { JSONArray(json) }.getOrNull() ?: return null
+    val applied = (0 until writes.length()).all { writes.getJSONObject(it).optBoolean("success") }
+    return if (applied) {
+      Result.success(Unit)
+    } else {
+      Result.failure(IllegalStateException("MediaTek GPU frequency not applied: $json"))
+    }
+  }
+
   /**
    * Lock GPU frequency to specific min/max values by changing governor and forcing constraints
    * NOTE: This does NOT modify power level - user controls that separately
@@ -1239,6 +1239,10 @@ class TuningViewModel(
         cpuUseCase.setClusterGovernor(cluster.clusterNumber, governor)
       }
 
+      NativeLib.applySchedProfile(mode)?.let {
+        Log.d("TuningViewModel", "Scheduler profile for $mode: $it")
+      }
+
       preferencesManager.setPerfMode(mode)
 
       delay(500)
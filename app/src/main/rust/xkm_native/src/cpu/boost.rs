@@ -0,0 +1,437 @@
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+const MAX_DURATION_MS: i64 = 10_000;
+
+/// Known boost drivers and where they keep their parameters.
+const DRIVERS: [(&str, &str); 4] = [
+    ("cpu_boost", "module/cpu_boost/parameters"),
+    ("cpu_boost", "devices/system/cpu/cpu_boost"),
+    ("cpu_input_boost", "module/cpu_input_boost/parameters"),
+    ("msm_performance", "module/msm_performance/parameters"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoostParamKind {
+    /// `0:1209600 4:0 7:0`, keyed by CPU.
+    FreqMap,
+    /// A single frequency for every CPU (`1209600`), on drivers without the
+    /// per-CPU map.
+    Freq,
+    /// A single frequency for one cluster (`*_lp`, `*_hp`, `*_perf`).
+    ClusterFreq,
+    DurationMs,
+    Integer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterBoost {
+    pub cluster: i32,
+    pub freq: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoostParam {
+    pub name: String,
+    pub path: String,
+    pub value: String,
+    pub kind: BoostParamKind,
+    pub clusters: Vec<ClusterBoost>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoostDriver {
+    pub driver: String,
+    pub path: String,
+    pub params: Vec<BoostParam>,
+}
+
+/// Kind of a parameter, plus the cluster for `ClusterFreq` or the upper
+/// bound for `Integer`.
+fn param_kind(driver: &str, name: &str) -> Option<(BoostParamKind, i64)> {
+    use BoostParamKind::*;
+
+    let cluster_suffix = |name: &str| {
+        ["_lp", "_hp", "_perf"]
+            .iter()
+            .position(|s| name.ends_with(s))
+            .map(|i| i as i64)
+    };
+
+    let kind = match (driver, name) {
+        ("msm_performance", "cpu_min_freq" | "cpu_max_freq") => (FreqMap, 0),
+        ("msm_performance", _) => return None,
+        (_, "input_boost_freq" | "powerkey_input_boost_freq") => (FreqMap, 0),
+        (_, "input_boost_ms" | "powerkey_input_boost_ms") => (DurationMs, 0),
+        (_, "input_boost_duration" | "wake_boost_duration") => (DurationMs, 0),
+        (_, "sched_boost_on_input") => (Integer, 3),
+        (_, "input_boost_enabled" | "enabled") => (Integer, 1),
+        (_, n) if n.contains("boost_freq") => (ClusterFreq, cluster_suffix(n)?),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// `input_boost_freq` holds a bare frequency instead of a `cpu:freq` map.
+fn is_single_freq(value: &str) -> bool {
+    !value.contains(':') && value.trim().parse::<i64>().is_ok()
+}
+
+fn cluster_of(topology: &[Vec<i32>], cpu: i32) -> Option<i32> {
+    topology
+        .iter()
+        .position(|c| c.contains(&cpu))
+        .map(|i| i as i32)
+}
+
+/// Per-cluster values of a CPU-keyed map, taking the first CPU that appears
+/// for each cluster.
+fn map_to_clusters(topology: &[Vec<i32>], value: &str) -> Vec<ClusterBoost> {
+    let mut clusters: Vec<ClusterBoost> = Vec::new();
+    for (cpu, freq) in super::parse_cpu_freq_map(value) {
+        if let Some(cluster) = cluster_of(topology, cpu)
+            && !clusters.iter().any(|c| c.cluster == cluster)
+        {
+            clusters.push(ClusterBoost { cluster, freq });
+        }
+    }
+    clusters.sort_by_key(|c| c.cluster);
+    clusters
+}
+
+fn read_boost_at(sys: &str) -> Vec<BoostDriver> {
+    let topology = super::cluster_topology(&format!("{}/devices/system/cpu", sys));
+
+    DRIVERS
+        .iter()
+        .filter_map(|&(driver, rel)| {
+            let dir = format!("{}/{}", sys, rel);
+            let params: Vec<BoostParam> = utils::list_dir(&dir)
+                .into_iter()
+                .filter_map(|name| {
+                    let (mut kind, extra) = param_kind(driver, &name)?;
+                    let path = format!("{}/{}", dir, name);
+                    let value = utils::read_sysfs_full(&path)?;
+                    if kind == BoostParamKind::FreqMap && is_single_freq(&value) {
+                        kind = BoostParamKind::Freq;
+                    }
+                    let clusters = match kind {
+                        BoostParamKind::FreqMap => map_to_clusters(&topology, &value),
+                        BoostParamKind::Freq => (0..topology.len())
+                            .map(|cluster| ClusterBoost {
+                                cluster: cluster as i32,
+                                freq: value.trim().parse().unwrap_or(0),
+                            })
+                            .collect(),
+                        BoostParamKind::ClusterFreq => vec![ClusterBoost {
+                            cluster: extra as i32,
+                            freq: value.parse().unwrap_or(0),
+                        }],
+                        _ => Vec::new(),
+                    };
+                    Some(BoostParam {
+                        name,
+                        path,
+                        value,
+                        kind,
+                        clusters,
+                    })
+                })
+                .collect();
+
+            (!params.is_empty()).then(|| BoostDriver {
+                driver: driver.to_string(),
+                path: dir,
+                params,
+            })
+        })
+        .collect()
+}
+
+/// Boost frequencies must be 0 (off), `UINT_MAX` (msm_performance's "no
+/// limit") or an entry of the cluster's table.
+fn validate_freq(cpu_root: &str, cluster: &[i32], freq: i64) -> Result<(), String> {
+    if freq == 0 || freq == u32::MAX as i64 {
+        return Ok(());
+    }
+
+    let policy = *cluster.first().ok_or("empty cluster")?;
+    let dir = super::governor::policy_dir(cpu_root, policy)
+        .ok_or_else(|| format!("no cpufreq policy{}", policy))?;
+    let table: Vec<i64> = utils::read_sysfs_full(&format!("{}/scaling_available_frequencies", dir))
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|f| f.parse().ok())
+        .collect();
+
+    if table.is_empty() {
+        let read = |node: &str| {
+            utils::read_sysfs_full(&format!("{}/{}", dir, node))
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(0)
+        };
+        let (min, max) = (read("cpuinfo_min_freq"), read("cpuinfo_max_freq"));
+        if freq < min || freq > max {
+            return Err(format!(
+                "{} outside policy{} range {}-{}",
+                freq, policy, min, max
+            ));
+        }
+        return Ok(());
+    }
+
+    if !table.contains(&freq) {
+        let nearest = table
+            .iter()
+            .min_by_key(|f| (*f - freq).abs())
+            .copied()
+            .unwrap_or(0);
+        return Err(format!(
+            "{} is not in policy{}'s frequency table (nearest {})",
+            freq, policy, nearest
+        ));
+    }
+    Ok(())
+}
+
+fn write_boost_at(
+    sys: &str,
+    driver: &str,
+    param: &str,
+    value: &str,
+) -> Result<WriteResult, String> {
+    let cpu_root = format!("{}/devices/system/cpu", sys);
+    let topology = super::cluster_topology(&cpu_root);
+
+    let (mut kind, extra) = param_kind(driver, param)
+        .ok_or_else(|| format!("unsupported parameter {}/{}", driver, param))?;
+    let path = DRIVERS
+        .iter()
+        .filter(|(d, _)| *d == driver)
+        .map(|(_, rel)| format!("{}/{}/{}", sys, rel, param))
+        .find(|p| utils::file_exists(p))
+        .ok_or_else(|| format!("{} has no {}", driver, param))?;
+    if kind == BoostParamKind::FreqMap
+        && utils::read_sysfs_full(&path).is_some_and(|v| is_single_freq(&v))
+    {
+        kind = BoostParamKind::Freq;
+    }
+
+    let value = value.trim();
+    match kind {
+        BoostParamKind::FreqMap => {
+            let requested = map_to_clusters(&topology, value);
+            if requested.is_empty() {
+                return Err(format!("'{}' is not a cpu:freq map", value));
+            }
+            for boost in &requested {
+                validate_freq(&cpu_root, &topology[boost.cluster as usize], boost.freq)?;
+            }
+
+            // Clusters not mentioned keep their value. Every CPU of a
+            // cluster is written since older drivers store values per CPU.
+            let current = map_to_clusters(
+                &topology,
+                &utils::read_sysfs_full(&path).unwrap_or_default(),
+            );
+            let map: Vec<String> = topology
+                .iter()
+                .enumerate()
+                .flat_map(|(i, cpus)| {
+                    let freq = requested
+                        .iter()
+                        .chain(&current)
+                        .find(|c| c.cluster == i as i32)
+                        .map(|c| c.freq)
+                        .unwrap_or(0);
+                    cpus.iter().map(move |cpu| format!("{}:{}", cpu, freq))
+                })
+                .collect();
+
+            utils::write_sysfs(&path, &map.join(" "));
+            let actual = utils::read_sysfs_full(&path).unwrap_or_default();
+            let applied = map_to_clusters(&topology, &actual);
+            let success = requested.iter().all(|r| {
+                applied
+                    .iter()
+                    .any(|a| a.cluster == r.cluster && a.freq == r.freq)
+            });
+
+            Ok(WriteResult {
+                path,
+                requested: value.to_string(),
+                actual,
+                success,
+            })
+        }
+        BoostParamKind::Freq => {
+            let freq: i64 = value
+                .parse()
+                .map_err(|_| format!("invalid frequency '{}'", value))?;
+            // The driver applies it to every CPU and each policy clamps it,
+            // so it only has to be a step of one of the clusters.
+            let checks: Vec<Result<(), String>> = topology
+                .iter()
+                .map(|cluster| validate_freq(&cpu_root, cluster, freq))
+                .collect();
+            if !checks.iter().any(Result::is_ok) {
+                return Err(checks
+                    .into_iter()
+                    .find_map(Result::err)
+                    .unwrap_or_else(|| "no cpufreq clusters".to_string()));
+            }
+            Ok(utils::write_sysfs_verified(&path, &freq.to_string()))
+        }
+        BoostParamKind::ClusterFreq => {
+            let freq: i64 = value
+                .parse()
+                .map_err(|_| format!("invalid frequency '{}'", value))?;
+            let cluster = topology
+                .get(extra as usize)
+                .ok_or_else(|| format!("no cluster {}", extra))?;
+            validate_freq(&cpu_root, cluster, freq)?;
+            Ok(utils::write_sysfs_verified(&path, &freq.to_string()))
+        }
+        BoostParamKind::DurationMs | BoostParamKind::Integer => {
+            let max = if kind == BoostParamKind::DurationMs {
+                MAX_DURATION_MS
+            } else {
+                extra
+            };
+            let v: i64 = value
+                .parse()
+                .map_err(|_| format!("invalid integer '{}'", value))?;
+            if !(0..=max).contains(&v) {
+                return Err(format!("{} must be within 0-{}", param, max));
+            }
+            Ok(utils::write_sysfs_verified(&path, &v.to_string()))
+        }
+    }
+}
+
+/// Every boost driver present, with frequency parameters resolved to
+/// clusters.
+pub fn read_input_boost() -> Vec<BoostDriver> {
+    read_boost_at(SYS_ROOT)
+}
+
+/// Writes a boost parameter. Frequency maps use the kernel's own `cpu:freq`
+/// format; any CPU of a cluster selects that cluster. Drivers that keep a
+/// single `input_boost_freq` take a bare frequency.
+pub fn write_input_boost(driver: &str, param: &str, value: &str) -> Result<WriteResult, String> {
+    write_boost_at(SYS_ROOT, driver, param, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn tree(name: &str) -> String {
+        let cpu = "devices/system/cpu/cpufreq";
+        let files = [
+            (format!("{}/policy0/related_cpus", cpu), "0 1 2 3"),
+            (
+                format!("{}/policy0/scaling_available_frequencies", cpu),
+                "300000 1209600 1804800",
+            ),
+            (format!("{}/policy4/related_cpus", cpu), "4 5 6"),
+            (
+                format!("{}/policy4/scaling_available_frequencies", cpu),
+                "710400 1497600 2419200",
+            ),
+            (format!("{}/policy7/related_cpus", cpu), "7"),
+            (format!("{}/policy7/cpuinfo_min_freq", cpu), "844800"),
+            (format!("{}/policy7/cpuinfo_max_freq", cpu), "2841600"),
+            (
+                "module/cpu_boost/parameters/input_boost_freq".into(),
+                "0:1209600 1:1209600 2:1209600 3:1209600 4:0 5:0 6:0 7:0",
+            ),
+            ("module/cpu_boost/parameters/input_boost_ms".into(), "40"),
+            (
+                "module/cpu_boost/parameters/sched_boost_on_input".into(),
+                "1",
+            ),
+            ("module/cpu_boost/parameters/unrelated".into(), "1"),
+            (
+                "module/cpu_input_boost/parameters/input_boost_freq_hp".into(),
+                "1497600",
+            ),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        fixture::build(name, &files)
+    }
+
+    #[test]
+    fn test_read_boost() {
+        let sys = tree("boost_read");
+        let drivers = read_boost_at(&sys);
+
+        assert_eq!(drivers.len(), 2);
+        let cpu_boost = &drivers[0];
+        assert_eq!(cpu_boost.params.len(), 3);
+        let freq = cpu_boost
+            .params
+            .iter()
+            .find(|p| p.name == "input_boost_freq")
+            .unwrap();
+        assert_eq!(freq.clusters.len(), 3);
+        assert_eq!(freq.clusters[0].freq, 1209600);
+
+        let hp = &drivers[1].params[0];
+        assert_eq!(hp.kind, BoostParamKind::ClusterFreq);
+        assert_eq!(hp.clusters[0].cluster, 1);
+    }
+
+    #[test]
+    fn test_write_boost() {
+        let sys = tree("boost_write");
+        let write = |driver, param, value| write_boost_at(&sys, driver, param, value);
+
+        assert!(write("cpu_boost", "input_boost_freq", "4:1500000").is_err());
+        assert!(write("cpu_boost", "input_boost_freq", "7:3000000").is_err());
+        let result = write("cpu_boost", "input_boost_freq", "4:1497600 7:1000000").unwrap();
+        assert!(result.success);
+        assert_eq!(
+            result.actual,
+            "0:1209600 1:1209600 2:1209600 3:1209600 4:1497600 5:1497600 6:1497600 7:1000000"
+        );
+
+        assert!(write("cpu_boost", "input_boost_ms", "20000").is_err());
+        assert!(write("cpu_boost", "input_boost_ms", "120").unwrap().success);
+        assert!(write("cpu_boost", "sched_boost_on_input", "4").is_err());
+        assert!(
+            write("cpu_input_boost", "input_boost_freq_hp", "2419200")
+                .unwrap()
+                .success
+        );
+        assert!(write("cpu_input_boost", "input_boost_freq_hp", "1804800").is_err());
+    }
+
+    #[test]
+    fn test_single_value_freq() {
+        let sys = tree("boost_single");
+        let path = format!("{}/module/cpu_boost/parameters/input_boost_freq", sys);
+        std::fs::write(&path, "1209600\n").unwrap();
+
+        let drivers = read_boost_at(&sys);
+        let freq = drivers[0]
+            .params
+            .iter()
+            .find(|p| p.name == "input_boost_freq")
+            .unwrap();
+        assert_eq!(freq.kind, BoostParamKind::Freq);
+        assert_eq!(freq.clusters.len(), 3);
+        assert_eq!(freq.clusters[2].freq, 1209600);
+
+        let write = |value| write_boost_at(&sys, "cpu_boost", "input_boost_freq", value);
+        assert!(write("4:1497600").is_err());
+        assert!(write("3000000").is_err());
+        let result = write("1497600").unwrap();
+        assert!(result.success);
+        assert_eq!(result.actual, "1497600");
+        assert!(write("0").unwrap().success);
+    }
+}
@@ -0,0 +1,405 @@
+use crate::power;
+use crate::utils;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Little,
+    Mid,
+    Big,
+}
+
+/// What a thermal zone name says it measures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ZoneTarget {
+    /// Logical CPU number, e.g. `cpu4-gold-usr`.
+    Core(i32),
+    /// Cluster by position (`cpuss-0-usr`), or a numbered sensor in a tsens
+    /// group that may span clusters (`cpu-1-2-usr`).
+    Cluster(usize, Option<usize>),
+    /// Cluster by role, e.g. `cpu_big0` or Exynos/Tensor `BIG`.
+    Role(Role, Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorScope {
+    Core,
+    Cluster,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreSensor {
+    pub zone: i32,
+    pub zone_type: String,
+    pub scope: SensorScope,
+    /// Vendor pattern that matched the zone name.
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreThermalMapping {
+    pub cpu: i32,
+    pub sensors: Vec<CoreSensor>,
+}
+
+type ZoneMatcher = fn(&str) -> Option<ZoneTarget>;
+
+/// Tried in order; the first matcher that understands a zone name wins.
+static ZONE_PATTERNS: &[(&str, ZoneMatcher)] = &[
+    ("qcom cpu-<cluster>-<n>", match_qcom_cluster_slot),
+    ("qcom cpu<n>-<tier>", match_qcom_named_core),
+    ("qcom cpuss-<cluster>", match_qcom_cpuss),
+    ("mtk cpu_<role><n>", match_role_slot),
+    ("exynos/tensor <role>", match_role_name),
+    ("generic cpu<n>", match_generic_core),
+];
+
+static CORE_THERMAL_MAP: OnceCell<Vec<CoreThermalMapping>> = OnceCell::new();
+
+fn normalize(zone_type: &str) -> String {
+    let lower = zone_type.trim().to_lowercase();
+    ["-usr", "-step", "-lowf", "-thermal", "_thermal", "-therm"]
+        .iter()
+        .find_map(|suffix| lower.strip_suffix(suffix))
+        .unwrap_or(&lower)
+        .to_string()
+}
+
+fn role(word: &str) -> Option<Role> {
+    match word {
+        "little" | "lit" | "silver" => Some(Role::Little),
+        "mid" | "middle" => Some(Role::Mid),
+        "big" => Some(Role::Big),
+        _ => None,
+    }
+}
+
+fn match_qcom_cluster_slot(name: &str) -> Option<ZoneTarget> {
+    let (cluster, slot) = name.strip_prefix("cpu-")?.split_once('-')?;
+    Some(ZoneTarget::Cluster(
+        cluster.parse().ok()?,
+        Some(slot.parse().ok()?),
+    ))
+}
+
+fn match_qcom_named_core(name: &str) -> Option<ZoneTarget> {
+    let (cpu, tier) = name.strip_prefix("cpu")?.split_once('-')?;
+    matches!(
+        tier,
+        "silver" | "gold" | "goldplus" | "gold-plus" | "prime" | "titanium"
+    )
+    .then_some(ZoneTarget::Core(cpu.parse().ok()?))
+}
+
+fn match_qcom_cpuss(name: &str) -> Option<ZoneTarget> {
+    let cluster = name.strip_prefix("cpuss-")?;
+    Some(ZoneTarget::Cluster(cluster.parse().ok()?, None))
+}
+
+fn match_role_slot(name: &str) -> Option<ZoneTarget> {
+    let rest = name
+        .strip_prefix("cpu_")
+        .or_else(|| name.strip_prefix("cpu-"))?;
+    let split = rest
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (word, slot) = rest.split_at(split);
+    let slot = if slot.is_empty() {
+        None
+    } else {
+        Some(slot.parse().ok()?)
+    };
+    Some(ZoneTarget::Role(
+        role(word.trim_end_matches(['_', '-']))?,
+        slot,
+    ))
+}
+
+fn match_role_name(name: &str) -> Option<ZoneTarget> {
+    Some(ZoneTarget::Role(role(name)?, None))
+}
+
+fn match_generic_core(name: &str) -> Option<ZoneTarget> {
+    Some(ZoneTarget::Core(name.strip_prefix("cpu")?.parse().ok()?))
+}
+
+fn classify(zone_type: &str) -> Option<(ZoneTarget, &'static str)> {
+    let name = normalize(zone_type);
+    ZONE_PATTERNS
+        .iter()
+        .find_map(|(label, matcher)| Some((matcher(&name)?, *label)))
+}
+
+/// Clusters a target refers to. `Mid` is every cluster between the first and
+/// the last, so it only exists on tri-cluster (or larger) parts.
+fn resolve_clusters(target: ZoneTarget, clusters: &[Vec<i32>]) -> Vec<&Vec<i32>> {
+    let n = clusters.len();
+    match target {
+        ZoneTarget::Core(_) => Vec::new(),
+        ZoneTarget::Cluster(index, _) => clusters.get(index).into_iter().collect(),
+        ZoneTarget::Role(Role::Little, _) => clusters.first().into_iter().collect(),
+        ZoneTarget::Role(Role::Big, _) if n > 1 => clusters.last().into_iter().collect(),
+        ZoneTarget::Role(Role::Mid, _) if n > 2 => clusters[1..n - 1].iter().collect(),
+        ZoneTarget::Role(..) => Vec::new(),
+    }
+}
+
+/// Cores under each tsens `cpu-<cluster>-<n>` group, given as (group, sensor
+/// count) in order. tsens numbers its groups on its own rather than by
+/// policy: SM8550 puts gold and prime both under `cpu-1`. Every group but
+/// the last takes one policy cluster; the last takes whole clusters until it
+/// has a core per sensor.
+fn tsens_cores(groups: &[(usize, usize)], clusters: &[Vec<i32>]) -> Vec<(usize, Vec<i32>)> {
+    let mut next = 0;
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, &(group, sensors))| {
+            let last = i > 0 && i + 1 == groups.len();
+            let mut cores = Vec::new();
+            while next < clusters.len() && (cores.is_empty() || (last && cores.len() < sensors)) {
+                cores.extend(&clusters[next]);
+                next += 1;
+            }
+            (group, cores)
+        })
+        .collect()
+}
+
+/// Builds the core → sensor map. `clusters` holds the CPUs of each cluster,
+/// ordered from the smallest to the biggest. Numbered sensors inside a
+/// cluster are spread evenly over its cores, so two sensors per core on a
+/// four-core cluster map as 0,0,1,1,...
+fn build_mapping(zones: &[(i32, String)], clusters: &[Vec<i32>]) -> Vec<CoreThermalMapping> {
+    let mut cpus: Vec<i32> = clusters.iter().flatten().copied().collect();
+    cpus.sort();
+
+    let mut mapping: Vec<CoreThermalMapping> = cpus
+        .iter()
+        .map(|&cpu| CoreThermalMapping {
+            cpu,
+            sensors: Vec::new(),
+        })
+        .collect();
+
+    let classified: Vec<(i32, &str, ZoneTarget, &str)> = zones
+        .iter()
+        .filter_map(|(zone, zone_type)| {
+            let (target, label) = classify(zone_type)?;
+            Some((*zone, zone_type.as_str(), target, label))
+        })
+        .collect();
+
+    let slot_count = |target: ZoneTarget| {
+        classified
+            .iter()
+            .filter(|(_, _, other, _)| match (target, *other) {
+                (ZoneTarget::Cluster(a, Some(_)), ZoneTarget::Cluster(b, Some(_))) => a == b,
+                (ZoneTarget::Role(a, Some(_)), ZoneTarget::Role(b, Some(_))) => a == b,
+                _ => false,
+            })
+            .count()
+    };
+
+    let mut groups: Vec<(usize, usize)> = classified
+        .iter()
+        .filter_map(|(_, _, target, _)| match *target {
+            ZoneTarget::Cluster(group, Some(_)) => Some((group, slot_count(*target))),
+            _ => None,
+        })
+        .collect();
+    groups.sort_unstable();
+    groups.dedup();
+    let tsens = tsens_cores(&groups, clusters);
+
+    let mut attach = |cpu: i32, zone: i32, zone_type: &str, scope, label: &str| {
+        if let Some(entry) = mapping.iter_mut().find(|m| m.cpu == cpu) {
+            entry.sensors.push(CoreSensor {
+                zone,
+                zone_type: zone_type.to_string(),
+                scope,
+                pattern: label.to_string(),
+            });
+        }
+    };
+
+    for &(zone, zone_type, target, label) in &classified {
+        if let ZoneTarget::Core(cpu) = target {
+            attach(cpu, zone, zone_type, SensorScope::Core, label);
+            continue;
+        }
+        if let ZoneTarget::Cluster(group, Some(slot)) = target {
+            if let Some((_, cores)) = tsens.iter().find(|(g, _)| *g == group) {
+                let spread = slot_count(target).max(cores.len());
+                if let Some(&cpu) = cores.get(slot * cores.len() / spread) {
+                    attach(cpu, zone, zone_type, SensorScope::Core, label);
+                }
+            }
+            continue;
+        }
+
+        let slot = match target {
+            ZoneTarget::Cluster(_, slot) | ZoneTarget::Role(_, slot) => slot,
+            ZoneTarget::Core(_) => None,
+        };
+
+        for cores in resolve_clusters(target, clusters) {
+            match slot {
+                Some(slot) => {
+                    let spread = slot_count(target).max(cores.len());
+                    if let Some(&cpu) = cores.get(slot * cores.len() / spread) {
+                        attach(cpu, zone, zone_type, SensorScope::Core, label);
+                    }
+                }
+                None => {
+                    for &cpu in cores {
+                        attach(cpu, zone, zone_type, SensorScope::Cluster, label);
+                    }
+                }
+            }
+        }
+    }
+
+    mapping
+}
+
+fn read_zone_types() -> Vec<(i32, String)> {
+    let mut zones: Vec<(i32, String)> = utils::list_dir("/sys/class/thermal")
+        .iter()
+        .filter_map(|name| name.strip_prefix("thermal_zone")?.parse().ok())
+        .map(|zone| (zone, power::get_thermal_zone_type(zone)))
+        .collect();
+    zones.sort_by_key(|(zone, _)| *zone);
+    zones
+}
+
+/// Sensors measuring each core, built once from thermal zone type names.
+/// Clusters come from `related_cpus`, so cores that are offline or have
+/// tweaked limits on the first call are still placed right.
+pub fn get_core_thermal_mapping() -> Vec<CoreThermalMapping> {
+    CORE_THERMAL_MAP
+        .get_or_init(|| build_mapping(&read_zone_types(), &super::cluster_topology(CPU_ROOT)))
+        .clone()
+}
+
+/// Hottest sensor on the core itself, falling back to its cluster sensor.
+/// `None` when no zone is known to measure the core.
+pub fn read_mapped_core_temperature(core: i32) -> Option<f32> {
+    let mapping = get_core_thermal_mapping();
+    let sensors = &mapping.iter().find(|m| m.cpu == core)?.sensors;
+
+    let hottest = |scope: SensorScope| {
+        sensors
+            .iter()
+            .filter(|s| s.scope == scope)
+            .map(|s| power::read_thermal_zone(s.zone))
+            .filter(|t| *t > 0.0)
+            .reduce(f32::max)
+    };
+
+    hottest(SensorScope::Core).or_else(|| hottest(SensorScope::Cluster))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones(names: &[&str]) -> Vec<(i32, String)> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (i as i32, n.to_string()))
+            .collect()
+    }
+
+    fn sensor_zones(mapping: &[CoreThermalMapping], cpu: i32) -> Vec<i32> {
+        mapping[cpu as usize]
+            .sensors
+            .iter()
+            .map(|s| s.zone)
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify("cpu-1-2-usr").map(|c| c.0),
+            Some(ZoneTarget::Cluster(1, Some(2)))
+        );
+        assert_eq!(
+            classify("cpu0-silver-usr").map(|c| c.0),
+            Some(ZoneTarget::Core(0))
+        );
+        assert_eq!(
+            classify("cpu_big0").map(|c| c.0),
+            Some(ZoneTarget::Role(Role::Big, Some(0)))
+        );
+        assert_eq!(
+            classify("BIG").map(|c| c.0),
+            Some(ZoneTarget::Role(Role::Big, None))
+        );
+        assert_eq!(classify("cpu3").map(|c| c.0), Some(ZoneTarget::Core(3)));
+        assert_eq!(classify("battery"), None);
+        assert_eq!(classify("gpuss-0-usr"), None);
+    }
+
+    #[test]
+    fn test_qcom_cluster_slots() {
+        let clusters = vec![vec![0, 1, 2, 3], vec![4, 5, 6], vec![7]];
+        let names = [
+            "cpu-0-0-usr",
+            "cpu-0-1-usr",
+            "cpu-0-2-usr",
+            "cpu-0-3-usr",
+            "cpu-1-0-usr",
+            "cpu-1-1-usr",
+            "cpu-1-2-usr",
+            "cpu-1-3-usr",
+            "cpu-1-4-usr",
+            "cpu-1-5-usr",
+            "cpu-2-0-usr",
+            "cpuss-0-usr",
+            "battery",
+        ];
+        let mapping = build_mapping(&zones(&names), &clusters);
+
+        assert_eq!(sensor_zones(&mapping, 0), vec![0, 11]);
+        assert_eq!(sensor_zones(&mapping, 3), vec![3, 11]);
+        assert_eq!(sensor_zones(&mapping, 4), vec![4, 5]);
+        assert_eq!(sensor_zones(&mapping, 6), vec![8, 9]);
+        assert_eq!(sensor_zones(&mapping, 7), vec![10]);
+    }
+
+    #[test]
+    fn test_qcom_group_spans_clusters() {
+        // SM8550: silver 0-2, gold 3-4 and 5-6, prime 7; tsens has two
+        // sensors per big core, all under cpu-1.
+        let clusters = vec![vec![0, 1, 2], vec![3, 4], vec![5, 6], vec![7]];
+        let mut names = vec!["cpu-0-0-usr", "cpu-0-1-usr", "cpu-0-2-usr"];
+        let big = (0..10)
+            .map(|n| format!("cpu-1-{}-usr", n))
+            .collect::<Vec<_>>();
+        names.extend(big.iter().map(String::as_str));
+        let mapping = build_mapping(&zones(&names), &clusters);
+
+        assert_eq!(sensor_zones(&mapping, 2), vec![2]);
+        assert_eq!(sensor_zones(&mapping, 3), vec![3, 4]);
+        assert_eq!(sensor_zones(&mapping, 5), vec![7, 8]);
+        assert_eq!(sensor_zones(&mapping, 7), vec![11, 12]);
+    }
+
+    #[test]
+    fn test_role_names() {
+        let clusters = vec![vec![0, 1, 2, 3], vec![4, 5], vec![6, 7]];
+        let mapping = build_mapping(&zones(&["LITTLE", "MID", "BIG", "soc"]), &clusters);
+
+        assert_eq!(sensor_zones(&mapping, 0), vec![0]);
+        assert_eq!(sensor_zones(&mapping, 5), vec![1]);
+        assert_eq!(sensor_zones(&mapping, 7), vec![2]);
+        assert_eq!(mapping[7].sensors[0].scope, SensorScope::Cluster);
+    }
+}
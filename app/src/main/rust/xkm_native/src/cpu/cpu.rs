@@ -37,17 +37,22 @@ pub struct CpuLoadInfo {
     pub per_core_load: Vec<f32>,
 }
 
-struct CpuStats {
+/// Per-core jiffies from the previous `/proc/stat` read.
+pub struct CpuStats {
     total_time: Vec<u64>,
     idle_time: Vec<u64>,
 }
 
-static CPU_STATS: Lazy<Mutex<CpuStats>> = Lazy::new(|| {
-    Mutex::new(CpuStats {
-        total_time: vec![0; 16],
-        idle_time: vec![0; 16],
-    })
-});
+impl Default for CpuStats {
+    fn default() -> Self {
+        CpuStats {
+            total_time: vec![0; 16],
+            idle_time: vec![0; 16],
+        }
+    }
+}
+
+static CPU_STATS: Lazy<Mutex<CpuStats>> = Lazy::new(|| Mutex::new(CpuStats::default()));
 
 static CPU_MODEL: OnceCell<String> = OnceCell::new();
 
@@ -72,10 +77,7 @@ pub fn detect_cpu_clusters() -> Vec<CpuCluster> {
         let max_freq = utils::read_sysfs_int(&max_path, 1000).unwrap_or(0) as i32;
 
         if min_freq > 0 && max_freq > 0 {
-            clusters
-                .entry((min_freq, max_freq))
-                .or_insert_with(Vec::new)
-                .push(cpu);
+            clusters.entry((min_freq, max_freq)).or_default().push(cpu);
         }
     }
 
@@ -182,67 +184,70 @@ pub fn read_core_data() -> String {
 }
 
 pub fn read_cpu_load_detailed() -> CpuLoadInfo {
+    read_cpu_load_with(&mut CPU_STATS.lock().unwrap())
+}
+
+/// Load since the read that last updated `stats`.
+pub fn read_cpu_load_with(stats: &mut CpuStats) -> CpuLoadInfo {
     let mut per_core_load = Vec::with_capacity(16);
 
     let mut buf = [0u8; 4096];
-    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/stat", &mut buf) {
-        if let Ok(content) = std::str::from_utf8(&buf[..bytes_read]) {
-            let mut total_load = 0.0f32;
-            let mut cpu_index = 0;
+    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/stat", &mut buf)
+        && let Ok(content) = std::str::from_utf8(&buf[..bytes_read])
+    {
+        let mut total_load = 0.0f32;
+        let mut cpu_index = 0;
+
+        for line in content.lines() {
+            if !line.starts_with("cpu") || line.starts_with("cpu ") {
+                continue;
+            }
 
-            let mut stats = CPU_STATS.lock().unwrap();
+            let parts: Vec<&str> = line.split_whitespace().collect();
 
-            for line in content.lines() {
-                if !line.starts_with("cpu") || line.starts_with("cpu ") {
-                    continue;
-                }
-                
-                let parts: Vec<&str> = line.split_whitespace().collect();
-
-                if parts.len() >= 5 {
-                    let user: u64 = parts[1].parse().unwrap_or(0);
-                    let nice: u64 = parts[2].parse().unwrap_or(0);
-                    let system: u64 = parts[3].parse().unwrap_or(0);
-                    let idle: u64 = parts[4].parse().unwrap_or(0);
-
-                    let total = user + nice + system + idle;
-
-                    let load = if cpu_index < stats.total_time.len() {
-                        let total_diff = total.saturating_sub(stats.total_time[cpu_index]);
-                        let idle_diff = idle.saturating_sub(stats.idle_time[cpu_index]);
-
-                        if total_diff > 0 {
-                            100.0 - (idle_diff as f32 / total_diff as f32 * 100.0)
-                        } else {
-                            0.0
-                        }
-                    } else {
-                        0.0
-                    };
+            if parts.len() >= 5 {
+                let user: u64 = parts[1].parse().unwrap_or(0);
+                let nice: u64 = parts[2].parse().unwrap_or(0);
+                let system: u64 = parts[3].parse().unwrap_or(0);
+                let idle: u64 = parts[4].parse().unwrap_or(0);
+
+                let total = user + nice + system + idle;
 
-                    per_core_load.push(load.clamp(0.0, 100.0));
+                let load = if cpu_index < stats.total_time.len() {
+                    let total_diff = total.saturating_sub(stats.total_time[cpu_index]);
+                    let idle_diff = idle.saturating_sub(stats.idle_time[cpu_index]);
 
-                    if cpu_index < stats.total_time.len() {
-                        stats.total_time[cpu_index] = total;
-                        stats.idle_time[cpu_index] = idle;
+                    if total_diff > 0 {
+                        100.0 - (idle_diff as f32 / total_diff as f32 * 100.0)
                     } else {
-                        stats.total_time.push(total);
-                        stats.idle_time.push(idle);
+                        0.0
                     }
-
-                    cpu_index += 1;
+                } else {
+                    0.0
+                };
+
+                per_core_load.push(load.clamp(0.0, 100.0));
+
+                if cpu_index < stats.total_time.len() {
+                    stats.total_time[cpu_index] = total;
+                    stats.idle_time[cpu_index] = idle;
+                } else {
+                    stats.total_time.push(total);
+                    stats.idle_time.push(idle);
                 }
-            }
 
-            if !per_core_load.is_empty() {
-                total_load = per_core_load.iter().sum::<f32>() / per_core_load.len() as f32;
+                cpu_index += 1;
             }
+        }
 
-            return CpuLoadInfo {
-                total_load,
-                per_core_load,
-            };
+        if !per_core_load.is_empty() {
+            total_load = per_core_load.iter().sum::<f32>() / per_core_load.len() as f32;
         }
+
+        return CpuLoadInfo {
+            total_load,
+            per_core_load,
+        };
     }
 
     CpuLoadInfo {
@@ -256,40 +261,30 @@ pub fn read_cpu_load() -> f32 {
 }
 
 pub fn read_core_temperature(core: i32) -> f32 {
-    let paths = [
-        format!("/sys/class/hwmon/hwmon1/temp{}_input", core + 1),
-        format!("/sys/devices/virtual/thermal/thermal_zone{}/temp", core),
-    ];
-
-    for path in &paths {
-        if let Some(temp) = utils::read_sysfs_float(path, 500) {
-            let temp_c = if temp > 1000.0 { temp / 1000.0 } else { temp };
-            if temp_c > 0.0 && temp_c < 150.0 {
-                return temp_c;
-            }
-        }
-    }
-
-    0.0
+    super::read_mapped_core_temperature(core).unwrap_or(0.0)
 }
 
 pub fn get_cpu_model() -> String {
     CPU_MODEL
         .get_or_init(|| {
+            if let Some(name) = super::get_soc_identity().name {
+                return name;
+            }
+
             let mut buf = [0u8; 2048];
-            if let Some(bytes_read) = utils::read_file_libc_buf("/proc/cpuinfo", &mut buf) {
-                if let Ok(content) = std::str::from_utf8(&buf[..bytes_read]) {
-                    for line in content.lines() {
-                        if line.starts_with("Hardware") {
-                            if let Some(model) = line.split(':').nth(1) {
-                                return model.trim().to_string();
-                            }
-                        }
-                        if line.starts_with("Processor") {
-                            if let Some(model) = line.split(':').nth(1) {
-                                return model.trim().to_string();
-                            }
-                        }
+            if let Some(bytes_read) = utils::read_file_libc_buf("/proc/cpuinfo", &mut buf)
+                && let Ok(content) = std::str::from_utf8(&buf[..bytes_read])
+            {
+                for line in content.lines() {
+                    if line.starts_with("Hardware")
+                        && let Some(model) = line.split(':').nth(1)
+                    {
+                        return model.trim().to_string();
+                    }
+                    if line.starts_with("Processor")
+                        && let Some(model) = line.split(':').nth(1)
+                    {
+                        return model.trim().to_string();
                     }
                 }
             }
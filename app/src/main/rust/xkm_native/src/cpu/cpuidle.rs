@@ -0,0 +1,249 @@
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleState {
+    pub index: i32,
+    pub name: String,
+    pub desc: String,
+    pub latency_us: u64,
+    pub residency_us: u64,
+    pub usage: u64,
+    pub time_us: u64,
+    pub disabled: bool,
+    pub above: u64,
+    pub below: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreIdleStates {
+    pub cpu: i32,
+    pub states: Vec<IdleState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleStateResidency {
+    pub index: i32,
+    pub name: String,
+    pub residency_pct: f32,
+    pub usage: u64,
+    pub above: u64,
+    pub below: u64,
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreIdleResidency {
+    pub cpu: i32,
+    /// Share of the interval not spent in any idle state.
+    pub active_pct: f32,
+    pub states: Vec<IdleStateResidency>,
+}
+
+/// The previous reading, kept by whoever computes residency from it.
+pub struct IdleSample {
+    cores: Vec<CoreIdleStates>,
+    taken: Instant,
+}
+
+fn read_core_states(root: &str, cpu: i32) -> Vec<IdleState> {
+    let mut states = Vec::new();
+
+    for index in 0..16 {
+        let dir = format!("{}/cpu{}/cpuidle/state{}", root, cpu, index);
+        let Some(name) = utils::read_sysfs(&format!("{}/name", dir)) else {
+            break;
+        };
+        let read = |node: &str| utils::read_sysfs_num(&format!("{}/{}", dir, node)).unwrap_or(0);
+
+        states.push(IdleState {
+            index,
+            name,
+            desc: utils::read_sysfs(&format!("{}/desc", dir)).unwrap_or_default(),
+            latency_us: read("latency"),
+            residency_us: read("residency"),
+            usage: read("usage"),
+            time_us: read("time"),
+            disabled: read("disable") != 0,
+            above: read("above"),
+            below: read("below"),
+        });
+    }
+
+    states
+}
+
+fn read_idle_states_at(root: &str) -> Vec<CoreIdleStates> {
+    let mut cores = Vec::new();
+
+    for cpu in 0..16 {
+        if !utils::file_exists(&format!("{}/cpu{}", root, cpu)) {
+            break;
+        }
+
+        let states = read_core_states(root, cpu);
+        if !states.is_empty() {
+            cores.push(CoreIdleStates { cpu, states });
+        }
+    }
+
+    cores
+}
+
+/// Residency of every state between two samples taken `elapsed_us` apart.
+/// Cores or states missing from `prev` (hotplugged in between) are skipped.
+fn compute_residency(
+    prev: &[CoreIdleStates],
+    curr: &[CoreIdleStates],
+    elapsed_us: u64,
+) -> Vec<CoreIdleResidency> {
+    if elapsed_us == 0 {
+        return Vec::new();
+    }
+
+    curr.iter()
+        .filter_map(|core| {
+            let old = prev.iter().find(|c| c.cpu == core.cpu)?;
+
+            let states: Vec<IdleStateResidency> = core
+                .states
+                .iter()
+                .filter_map(|state| {
+                    let before = old.states.iter().find(|s| s.index == state.index)?;
+                    let time = state.time_us.saturating_sub(before.time_us);
+
+                    Some(IdleStateResidency {
+                        index: state.index,
+                        name: state.name.clone(),
+                        residency_pct: (time as f32 / elapsed_us as f32 * 100.0).clamp(0.0, 100.0),
+                        usage: state.usage.saturating_sub(before.usage),
+                        above: state.above.saturating_sub(before.above),
+                        below: state.below.saturating_sub(before.below),
+                        disabled: state.disabled,
+                    })
+                })
+                .collect();
+
+            let idle_pct: f32 = states.iter().map(|s| s.residency_pct).sum();
+
+            Some(CoreIdleResidency {
+                cpu: core.cpu,
+                active_pct: (100.0 - idle_pct).clamp(0.0, 100.0),
+                states,
+            })
+        })
+        .collect()
+}
+
+fn set_idle_state_at(root: &str, cpus: &[i32], state: i32, disable: bool) -> Vec<WriteResult> {
+    let value = if disable { "1" } else { "0" };
+
+    cpus.iter()
+        .map(|cpu| format!("{}/cpu{}/cpuidle/state{}/disable", root, cpu, state))
+        .filter(|path| utils::file_exists(path))
+        .map(|path| utils::write_sysfs_verified(&path, value))
+        .collect()
+}
+
+pub fn read_cpuidle_states() -> Vec<CoreIdleStates> {
+    read_idle_states_at(CPU_ROOT)
+}
+
+/// Per-core residency since the read that last updated `last`. The first read
+/// only primes the sample and returns an empty list.
+pub fn read_cpuidle_residency_with(last: &mut Option<IdleSample>) -> Vec<CoreIdleResidency> {
+    let cores = read_cpuidle_states();
+    let now = Instant::now();
+
+    let residency = match last {
+        Some(prev) => compute_residency(
+            &prev.cores,
+            &cores,
+            now.duration_since(prev.taken).as_micros() as u64,
+        ),
+        None => Vec::new(),
+    };
+
+    *last = Some(IdleSample { cores, taken: now });
+    residency
+}
+
+/// Disables (or re-enables) idle `state` on every core of `cluster`.
+pub fn set_cluster_idle_state(cluster: i32, state: i32, disable: bool) -> Vec<WriteResult> {
+    let cores = usize::try_from(cluster)
+        .ok()
+        .and_then(|index| super::cluster_topology(CPU_ROOT).into_iter().nth(index))
+        .unwrap_or_default();
+
+    set_idle_state_at(CPU_ROOT, &cores, state, disable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn state(index: i32, time_us: u64, usage: u64) -> IdleState {
+        IdleState {
+            index,
+            name: format!("C{}", index),
+            desc: String::new(),
+            latency_us: 0,
+            residency_us: 0,
+            usage,
+            time_us,
+            disabled: false,
+            above: 0,
+            below: 0,
+        }
+    }
+
+    #[test]
+    fn test_compute_residency() {
+        let prev = vec![CoreIdleStates {
+            cpu: 0,
+            states: vec![state(0, 1_000, 10), state(1, 5_000, 2)],
+        }];
+        let curr = vec![CoreIdleStates {
+            cpu: 0,
+            states: vec![state(0, 251_000, 40), state(1, 505_000, 5)],
+        }];
+
+        let result = compute_residency(&prev, &curr, 1_000_000);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].states[0].residency_pct, 25.0);
+        assert_eq!(result[0].states[1].residency_pct, 50.0);
+        assert_eq!(result[0].states[1].usage, 3);
+        assert_eq!(result[0].active_pct, 25.0);
+    }
+
+    #[test]
+    fn test_read_and_disable_states() {
+        let root = fixture::build(
+            "cpuidle",
+            &[
+                ("cpu0/cpuidle/state0/name", "WFI\n"),
+                ("cpu0/cpuidle/state0/time", "1200\n"),
+                ("cpu0/cpuidle/state0/disable", "0\n"),
+                ("cpu0/cpuidle/state1/name", "cpu-sleep-0\n"),
+                ("cpu0/cpuidle/state1/latency", "500\n"),
+                ("cpu0/cpuidle/state1/disable", "0\n"),
+                ("cpu1/cpuidle/state0/name", "WFI\n"),
+                ("cpu1/cpuidle/state0/disable", "0\n"),
+            ],
+        );
+
+        let cores = read_idle_states_at(&root);
+        assert_eq!(cores.len(), 2);
+        assert_eq!(cores[0].states[1].name, "cpu-sleep-0");
+        assert_eq!(cores[0].states[1].latency_us, 500);
+
+        let results = set_idle_state_at(&root, &[0, 1], 1, true);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success);
+        assert!(read_idle_states_at(&root)[0].states[1].disabled);
+    }
+}
@@ -0,0 +1,920 @@
+use crate::mtk;
+use crate::utils;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+const STATE_FILE: &str = "freq_lock_state.json";
+const GUARD_INTERVAL: Duration = Duration::from_secs(1);
+const HOUR_MS: u64 = 3_600_000;
+const MAX_EVENTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermalAction {
+    None,
+    UnlockAll,
+    UnlockAndGovernorPowersave,
+}
+
+/// Mirrors the app's `ThermalPolicy`: thresholds in °C, delays in ms.
+/// Between `restore_threshold` and `warning_threshold` the engine holds its
+/// current step, which is the hysteresis band.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalPolicy {
+    pub name: String,
+    pub critical_threshold: f32,
+    pub emergency_threshold: f32,
+    pub warning_threshold: f32,
+    pub restore_threshold: f32,
+    pub restore_delay_ms: u64,
+    pub warning_cooldown_ms: u64,
+    pub action_on_emergency: ThermalAction,
+    pub action_on_critical: ThermalAction,
+    pub auto_restore: bool,
+    pub max_retries_per_hour: u32,
+    /// Percentage of the locked max removed per warning step.
+    pub step_pct: u32,
+    pub max_steps: u32,
+}
+
+impl Default for ThermalPolicy {
+    fn default() -> Self {
+        Self {
+            name: "Policy B (Balanced)".to_string(),
+            critical_threshold: 87.0,
+            emergency_threshold: 82.0,
+            warning_threshold: 72.0,
+            restore_threshold: 68.0,
+            restore_delay_ms: 10_000,
+            warning_cooldown_ms: 3_000,
+            action_on_emergency: ThermalAction::UnlockAll,
+            action_on_critical: ThermalAction::UnlockAndGovernorPowersave,
+            auto_restore: true,
+            max_retries_per_hour: 3,
+            step_pct: 10,
+            max_steps: 5,
+        }
+    }
+}
+
+impl ThermalPolicy {
+    fn validate(&self) -> Result<(), String> {
+        let ordered = self.critical_threshold > self.emergency_threshold
+            && self.emergency_threshold > self.warning_threshold
+            && self.warning_threshold > self.restore_threshold;
+        if !ordered {
+            return Err("thresholds must satisfy critical > emergency > warning > restore".into());
+        }
+        let total = self.step_pct.checked_mul(self.max_steps);
+        if total.is_none_or(|pct| pct >= 100) {
+            return Err("step_pct * max_steps must stay below 100".into());
+        }
+        Ok(())
+    }
+}
+
+/// A cluster lock as applied, in kHz like the `scaling_*` nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterLock {
+    pub cluster: i32,
+    pub min_freq: i32,
+    pub max_freq: i32,
+}
+
+/// A cluster lock as the app sends it, in MHz.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClusterLockRequest {
+    pub cluster: i32,
+    pub min_mhz: i32,
+    pub max_mhz: i32,
+}
+
+impl ClusterLockRequest {
+    fn to_khz(&self) -> Result<ClusterLock, String> {
+        let khz = |mhz: i32| {
+            mhz.checked_mul(1000)
+                .ok_or_else(|| format!("cluster {}: {} MHz out of range", self.cluster, mhz))
+        };
+        Ok(ClusterLock {
+            cluster: self.cluster,
+            min_freq: khz(self.min_mhz)?,
+            max_freq: khz(self.max_mhz)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginalFreq {
+    pub policy: i32,
+    pub min_freq: i32,
+    pub max_freq: i32,
+    pub governor: String,
+    /// MediaTek PPM hard user limits before the lock; `None` is unlimited.
+    #[serde(default)]
+    pub ppm_min_khz: Option<i64>,
+    #[serde(default)]
+    pub ppm_max_khz: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockRequest {
+    pub locks: Vec<ClusterLockRequest>,
+    #[serde(default)]
+    pub policy: ThermalPolicy,
+    #[serde(default = "default_guard")]
+    pub thermal_guard: bool,
+}
+
+fn default_guard() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FreqLockState {
+    pub locked: bool,
+    pub thermal_guard: bool,
+    pub policy: ThermalPolicy,
+    pub locks: Vec<ClusterLock>,
+    pub originals: Vec<OriginalFreq>,
+    /// Number of warning steps currently applied; 0 is the full lock.
+    pub step: u32,
+    /// Set by emergency/critical handling: originals are back in place until
+    /// the engine restores the lock.
+    pub override_active: bool,
+    pub critical: bool,
+    pub last_temperature: f32,
+    pub last_change_ms: u64,
+    pub cool_since_ms: Option<u64>,
+    pub retries: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockEventKind {
+    Locked,
+    Unlocked,
+    Resumed,
+    StepDown,
+    StepUp,
+    Emergency,
+    Critical,
+    Restored,
+    RestoreFailed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockEvent {
+    pub kind: LockEventKind,
+    pub temperature: f32,
+    pub step: u32,
+    pub message: String,
+    pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transition {
+    StepDown,
+    StepUp,
+    Emergency,
+    Critical,
+    Restore,
+}
+
+#[derive(Default)]
+struct Engine {
+    state: FreqLockState,
+    state_path: Option<String>,
+    events: VecDeque<LockEvent>,
+}
+
+static ENGINE: Lazy<Mutex<Engine>> = Lazy::new(|| Mutex::new(Engine::default()));
+static GUARD_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Decides what the guard should do for `temp`, updating only the timers.
+/// Counterpart of the critical/emergency/warning/safe handlers in the app.
+fn evaluate(state: &mut FreqLockState, temp: f32, now: u64) -> Option<Transition> {
+    let policy = &state.policy;
+    state.last_temperature = temp;
+
+    if temp > policy.restore_threshold {
+        state.cool_since_ms = None;
+    }
+
+    if temp >= policy.critical_threshold {
+        return (!state.critical).then_some(Transition::Critical);
+    }
+
+    if temp >= policy.emergency_threshold {
+        return (!state.override_active).then_some(Transition::Emergency);
+    }
+
+    if temp >= policy.warning_threshold {
+        let cooled_down = now.saturating_sub(state.last_change_ms) >= policy.warning_cooldown_ms;
+        let can_step = !state.override_active && state.step < policy.max_steps;
+        return (can_step && cooled_down).then_some(Transition::StepDown);
+    }
+
+    if temp > policy.restore_threshold {
+        return None;
+    }
+
+    let since = *state.cool_since_ms.get_or_insert(now);
+    if now.saturating_sub(since) < policy.restore_delay_ms {
+        return None;
+    }
+
+    if state.override_active {
+        state.retries.retain(|&t| now.saturating_sub(t) < HOUR_MS);
+        let allowed = state.retries.len() < policy.max_retries_per_hour as usize;
+        return (policy.auto_restore && allowed).then_some(Transition::Restore);
+    }
+
+    (state.step > 0).then_some(Transition::StepUp)
+}
+
+fn available_frequencies(root: &str, policy: i32) -> Vec<i32> {
+    let Some(dir) = super::governor::policy_dir(root, policy) else {
+        return Vec::new();
+    };
+
+    let mut freqs: Vec<i32> = utils::read_sysfs(&format!("{}/scaling_available_frequencies", dir))
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|f| f.parse().ok())
+        .collect();
+    freqs.sort();
+    freqs
+}
+
+/// Max frequency for `lock` after `step` warning steps, snapped down to an
+/// available OPP.
+fn stepped_max(lock: &ClusterLock, step: u32, step_pct: u32, freqs: &[i32]) -> i32 {
+    let scale = 100 - (step * step_pct).min(100) as i64;
+    let target = (lock.max_freq as i64 * scale / 100) as i32;
+
+    freqs
+        .iter()
+        .rev()
+        .find(|&&f| f <= target)
+        .or(freqs.first())
+        .copied()
+        .unwrap_or(target)
+}
+
+fn read_freq(dir: &str, node: &str) -> i32 {
+    utils::read_sysfs_num(&format!("{}/{}", dir, node)).unwrap_or(0)
+}
+
+/// Writes a min/max pair in the order that never leaves min above max.
+fn write_range(root: &str, policy: i32, min: i32, max: i32) -> bool {
+    let Some(dir) = super::governor::policy_dir(root, policy) else {
+        return false;
+    };
+
+    let min_path = format!("{}/scaling_min_freq", dir);
+    let max_path = format!("{}/scaling_max_freq", dir);
+
+    let mut writes = [(min_path, min), (max_path, max)];
+    if min > read_freq(&dir, "scaling_max_freq") {
+        writes.reverse();
+    }
+
+    let ok = writes
+        .iter()
+        .all(|(path, value)| utils::write_sysfs(path, &value.to_string()));
+
+    ok && read_freq(&dir, "scaling_max_freq") == max
+}
+
+fn mtk_cluster(root: &str, policy: i32) -> Option<usize> {
+    super::cluster_topology(root)
+        .iter()
+        .position(|cpus| cpus.first() == Some(&policy))
+}
+
+/// PPM hard user limits of `policy`'s cluster, so unlock can put back what
+/// was there instead of lifting them. Only the live tree is read.
+fn read_mtk_limits(root: &str, policy: i32) -> (Option<i64>, Option<i64>) {
+    if root != CPU_ROOT || !mtk::is_mtk_platform() {
+        return (None, None);
+    }
+    let limit = mtk::read_mtk_cpu_state()
+        .zip(mtk_cluster(root, policy))
+        .and_then(|(state, cluster)| {
+            state
+                .limits
+                .into_iter()
+                .find(|l| l.cluster == cluster as i32)
+        });
+    limit.map_or((None, None), |l| (l.min_khz, l.max_khz))
+}
+
+/// MediaTek kernels clamp cpufreq through the PPM hard user limits and pin it
+/// through `/proc/cpufreq`, so on those SoCs the range goes there as well.
+/// `pin` of 0 releases a fixed frequency. Only the live tree is routed.
+fn write_mtk_range(
+    root: &str,
+    policy: i32,
+    limits: (Option<i64>, Option<i64>),
+    pin: Option<u64>,
+) -> bool {
+    if root != CPU_ROOT || !mtk::is_mtk_platform() {
+        return true;
+    }
+    let Some(state) = mtk::read_mtk_cpu_state() else {
+        return true;
+    };
+    let Some(cluster) = mtk_cluster(root, policy) else {
+        return false;
+    };
+
+    let mut ok = true;
+    if state.limits.iter().any(|l| l.cluster == cluster as i32) {
+        ok &= mtk::set_ppm_cpu_limit(cluster as i32, limits.0, limits.1)
+            .is_ok_and(|results| results.iter().all(|r| r.success));
+    }
+    if let (Some(khz), Some(domain)) = (pin, state.domains.get(cluster)) {
+        ok &= mtk::set_mtk_cpu_fixed_freq(&domain.name, khz).is_ok_and(|r| r.success);
+    }
+    ok
+}
+
+fn write_governor(root: &str, policy: i32, governor: &str) -> bool {
+    super::governor::policy_dir(root, policy)
+        .map(|dir| utils::write_sysfs(&format!("{}/scaling_governor", dir), governor))
+        .unwrap_or(false)
+}
+
+fn policy_of(topology: &[Vec<i32>], cluster: i32) -> Option<i32> {
+    topology.get(cluster as usize)?.first().copied()
+}
+
+/// Applies the lock at the state's current step. Returns the clusters whose
+/// limits did not stick.
+fn apply_lock_at(root: &str, state: &FreqLockState) -> Vec<i32> {
+    let topology = super::cluster_topology(root);
+
+    state
+        .locks
+        .iter()
+        .filter(|lock| {
+            let Some(policy) = policy_of(&topology, lock.cluster) else {
+                return true;
+            };
+            let freqs = available_frequencies(root, policy);
+            let max = stepped_max(lock, state.step, state.policy.step_pct, &freqs);
+            let min = lock.min_freq.min(max);
+            // A pin is only taken for min == max and only released on
+            // restore, so a plain range leaves a fixed frequency set
+            // elsewhere alone.
+            let pin = (min == max).then_some(max as u64);
+            let mtk_ok = write_mtk_range(root, policy, (Some(min as i64), Some(max as i64)), pin);
+            !(write_range(root, policy, min, max) && mtk_ok)
+        })
+        .map(|lock| lock.cluster)
+        .collect()
+}
+
+fn restore_originals_at(root: &str, state: &FreqLockState) -> bool {
+    state.originals.iter().fold(true, |ok, orig| {
+        let governor_ok = write_governor(root, orig.policy, &orig.governor);
+        let limits = (orig.ppm_min_khz, orig.ppm_max_khz);
+        let mtk_ok = write_mtk_range(root, orig.policy, limits, Some(0));
+        write_range(root, orig.policy, orig.min_freq, orig.max_freq) && mtk_ok && governor_ok && ok
+    })
+}
+
+fn run_action(root: &str, state: &FreqLockState, action: ThermalAction) {
+    match action {
+        ThermalAction::None => {}
+        ThermalAction::UnlockAll => {
+            restore_originals_at(root, state);
+        }
+        ThermalAction::UnlockAndGovernorPowersave => {
+            restore_originals_at(root, state);
+            for orig in &state.originals {
+                write_governor(root, orig.policy, "powersave");
+            }
+        }
+    }
+}
+
+fn apply_transition_at(
+    root: &str,
+    state: &mut FreqLockState,
+    transition: Transition,
+    now: u64,
+) -> LockEvent {
+    let temp = state.last_temperature;
+    let (kind, message) = match transition {
+        Transition::StepDown => {
+            state.step += 1;
+            apply_lock_at(root, state);
+            (
+                LockEventKind::StepDown,
+                format!("{:.1}°C: max frequency stepped down", temp),
+            )
+        }
+        Transition::StepUp => {
+            state.step -= 1;
+            state.cool_since_ms = Some(now);
+            apply_lock_at(root, state);
+            (
+                LockEventKind::StepUp,
+                format!("{:.1}°C: max frequency stepped up", temp),
+            )
+        }
+        Transition::Emergency => {
+            state.override_active = true;
+            run_action(root, state, state.policy.action_on_emergency);
+            (
+                LockEventKind::Emergency,
+                format!("{:.1}°C: lock released for safety", temp),
+            )
+        }
+        Transition::Critical => {
+            state.override_active = true;
+            state.critical = true;
+            run_action(root, state, state.policy.action_on_critical);
+            (
+                LockEventKind::Critical,
+                format!("{:.1}°C: critical temperature", temp),
+            )
+        }
+        Transition::Restore => {
+            state.retries.push(now);
+            state.cool_since_ms = Some(now);
+            for orig in &state.originals {
+                write_governor(root, orig.policy, &orig.governor);
+            }
+            if apply_lock_at(root, state).is_empty() {
+                state.override_active = false;
+                state.critical = false;
+                (
+                    LockEventKind::Restored,
+                    format!("{:.1}°C: lock restored", temp),
+                )
+            } else {
+                (
+                    LockEventKind::RestoreFailed,
+                    format!("{:.1}°C: failed to restore lock", temp),
+                )
+            }
+        }
+    };
+
+    state.last_change_ms = now;
+    LockEvent {
+        kind,
+        temperature: temp,
+        step: state.step,
+        message,
+        timestamp_ms: now,
+    }
+}
+
+fn lock_at(
+    root: &str,
+    state: &mut FreqLockState,
+    request: LockRequest,
+) -> Result<LockEvent, String> {
+    request.policy.validate()?;
+    if request.locks.is_empty() {
+        return Err("no clusters to lock".into());
+    }
+
+    let topology = super::cluster_topology(root);
+
+    // Validate the whole request first so a rejected re-lock leaves the
+    // current lock and its originals untouched.
+    let locks = request
+        .locks
+        .iter()
+        .map(ClusterLockRequest::to_khz)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut targets = Vec::with_capacity(locks.len());
+    for lock in &locks {
+        let policy = policy_of(&topology, lock.cluster)
+            .ok_or_else(|| format!("unknown cluster {}", lock.cluster))?;
+        if lock.min_freq > lock.max_freq {
+            return Err(format!("cluster {}: min above max", lock.cluster));
+        }
+        let dir = super::governor::policy_dir(root, policy)
+            .ok_or_else(|| format!("cluster {}: no cpufreq policy", lock.cluster))?;
+        targets.push((policy, dir));
+    }
+
+    // Keep the pre-lock values across re-locks so unlock always returns to
+    // what the user had before the first lock.
+    let mut originals = if state.locked {
+        std::mem::take(&mut state.originals)
+    } else {
+        Vec::new()
+    };
+
+    for (policy, dir) in targets {
+        if originals.iter().any(|o: &OriginalFreq| o.policy == policy) {
+            continue;
+        }
+
+        let (ppm_min_khz, ppm_max_khz) = read_mtk_limits(root, policy);
+        originals.push(OriginalFreq {
+            policy,
+            min_freq: read_freq(&dir, "scaling_min_freq"),
+            max_freq: read_freq(&dir, "scaling_max_freq"),
+            governor: utils::read_sysfs_full(&format!("{}/scaling_governor", dir))
+                .unwrap_or_default(),
+            ppm_min_khz,
+            ppm_max_khz,
+        });
+    }
+
+    *state = FreqLockState {
+        locked: true,
+        thermal_guard: request.thermal_guard,
+        policy: request.policy,
+        locks,
+        originals,
+        ..Default::default()
+    };
+
+    let failed = apply_lock_at(root, state);
+    if failed.len() == state.locks.len() {
+        restore_originals_at(root, state);
+        *state = FreqLockState::default();
+        return Err("failed to lock any cluster".into());
+    }
+
+    let now = utils::now_ms();
+    state.last_change_ms = now;
+    let message = if failed.is_empty() {
+        "frequencies locked".to_string()
+    } else {
+        format!("locked with failures on clusters {:?}", failed)
+    };
+
+    Ok(LockEvent {
+        kind: LockEventKind::Locked,
+        temperature: state.last_temperature,
+        step: 0,
+        message,
+        timestamp_ms: now,
+    })
+}
+
+impl Engine {
+    fn push(&mut self, event: LockEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn persist(&self) {
+        if let Some(path) = &self.state_path
+            && let Ok(json) = serde_json::to_string(&self.state)
+        {
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+fn start_guard() {
+    if GUARD_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(|| {
+        loop {
+            std::thread::sleep(GUARD_INTERVAL);
+
+            let mut engine = ENGINE.lock().unwrap();
+            if !engine.state.locked || !engine.state.thermal_guard {
+                // Cleared under the engine lock so a lock taken right now
+                // sees the flag down and starts a fresh guard.
+                GUARD_RUNNING.store(false, Ordering::SeqCst);
+                break;
+            }
+
+            let temp = crate::power::read_cpu_temperature();
+            if temp <= 0.0 {
+                continue;
+            }
+
+            let now = utils::now_ms();
+            if let Some(transition) = evaluate(&mut engine.state, temp, now) {
+                let event = apply_transition_at(CPU_ROOT, &mut engine.state, transition, now);
+                engine.push(event);
+                engine.persist();
+            }
+        }
+    });
+}
+
+/// Points persistence at `dir` and resumes a lock left by a previous process,
+/// re-applying it at the persisted step.
+pub fn init_freq_lock(dir: &str) -> FreqLockState {
+    let path = format!("{}/{}", dir.trim_end_matches('/'), STATE_FILE);
+    let mut engine = ENGINE.lock().unwrap();
+    engine.state_path = Some(path.clone());
+
+    if engine.state.locked {
+        return engine.state.clone();
+    }
+
+    let persisted = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<FreqLockState>(&json).ok());
+
+    if let Some(state) = persisted.filter(|s| s.locked) {
+        if !state.override_active {
+            apply_lock_at(CPU_ROOT, &state);
+        }
+        let now = utils::now_ms();
+        let event = LockEvent {
+            kind: LockEventKind::Resumed,
+            temperature: state.last_temperature,
+            step: state.step,
+            message: "lock resumed after restart".to_string(),
+            timestamp_ms: now,
+        };
+        let guard = state.thermal_guard;
+        engine.state = state;
+        engine.push(event);
+        if guard {
+            start_guard();
+        }
+    }
+
+    engine.state.clone()
+}
+
+pub fn lock_cpu_frequencies(request: LockRequest) -> Result<FreqLockState, String> {
+    let mut engine = ENGINE.lock().unwrap();
+
+    let temp = crate::power::read_cpu_temperature();
+    if request.thermal_guard && temp >= request.policy.critical_threshold {
+        return Err(format!(
+            "temperature {:.1}°C is above the critical threshold",
+            temp
+        ));
+    }
+
+    let event = lock_at(CPU_ROOT, &mut engine.state, request)?;
+    engine.state.last_temperature = temp;
+    engine.push(event);
+    engine.persist();
+
+    if engine.state.thermal_guard {
+        start_guard();
+    }
+
+    Ok(engine.state.clone())
+}
+
+/// Restores the frequencies and governors recorded before the first lock.
+pub fn unlock_cpu_frequencies() -> FreqLockState {
+    let mut engine = ENGINE.lock().unwrap();
+    if !engine.state.locked {
+        return engine.state.clone();
+    }
+
+    let restored = restore_originals_at(CPU_ROOT, &engine.state);
+    let event = LockEvent {
+        kind: LockEventKind::Unlocked,
+        temperature: engine.state.last_temperature,
+        step: 0,
+        message: if restored {
+            "frequencies unlocked".to_string()
+        } else {
+            "unlocked, some clusters were not restored".to_string()
+        },
+        timestamp_ms: utils::now_ms(),
+    };
+
+    engine.state = FreqLockState::default();
+    engine.push(event);
+    engine.persist();
+    engine.state.clone()
+}
+
+pub fn get_freq_lock_state() -> FreqLockState {
+    ENGINE.lock().unwrap().state.clone()
+}
+
+/// Drains events raised since the last call.
+pub fn poll_freq_lock_events() -> Vec<LockEvent> {
+    ENGINE.lock().unwrap().events.drain(..).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn locked_state() -> FreqLockState {
+        FreqLockState {
+            locked: true,
+            thermal_guard: true,
+            locks: vec![ClusterLock {
+                cluster: 1,
+                min_freq: 1_200_000,
+                max_freq: 2_400_000,
+            }],
+            originals: vec![OriginalFreq {
+                policy: 4,
+                min_freq: 600_000,
+                max_freq: 2_400_000,
+                governor: "walt".to_string(),
+                ppm_min_khz: None,
+                ppm_max_khz: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate_hysteresis() {
+        let mut state = locked_state();
+
+        assert_eq!(
+            evaluate(&mut state, 74.0, 10_000),
+            Some(Transition::StepDown)
+        );
+        state.step = 1;
+        state.last_change_ms = 10_000;
+        // Still warm, but inside the warning cooldown.
+        assert_eq!(evaluate(&mut state, 74.0, 11_000), None);
+
+        // Between restore and warning: hold.
+        assert_eq!(evaluate(&mut state, 70.0, 20_000), None);
+        assert_eq!(state.cool_since_ms, None);
+
+        assert_eq!(evaluate(&mut state, 66.0, 30_000), None);
+        assert_eq!(evaluate(&mut state, 66.0, 40_000), Some(Transition::StepUp));
+
+        assert_eq!(
+            evaluate(&mut state, 83.0, 41_000),
+            Some(Transition::Emergency)
+        );
+        state.override_active = true;
+        assert_eq!(evaluate(&mut state, 83.0, 42_000), None);
+        assert_eq!(
+            evaluate(&mut state, 90.0, 43_000),
+            Some(Transition::Critical)
+        );
+    }
+
+    #[test]
+    fn test_restore_retry_limit() {
+        let mut state = locked_state();
+        state.override_active = true;
+        state.retries = vec![1_000, 2_000, 3_000];
+
+        evaluate(&mut state, 60.0, 10_000);
+        assert_eq!(evaluate(&mut state, 60.0, 30_000), None);
+        assert_eq!(
+            evaluate(&mut state, 60.0, HOUR_MS + 3_000),
+            Some(Transition::Restore)
+        );
+    }
+
+    #[test]
+    fn test_stepped_max() {
+        let lock = &locked_state().locks[0];
+        let freqs = [1_000_000, 1_800_000, 2_100_000, 2_400_000];
+
+        assert_eq!(stepped_max(lock, 0, 10, &freqs), 2_400_000);
+        assert_eq!(stepped_max(lock, 1, 10, &freqs), 2_100_000);
+        assert_eq!(stepped_max(lock, 3, 10, &freqs), 1_000_000);
+        assert_eq!(stepped_max(lock, 1, 10, &[]), 2_160_000);
+    }
+
+    #[test]
+    fn test_lock_step_and_restore() {
+        let root = fixture::build(
+            "freqlock",
+            &[
+                ("cpufreq/policy0/related_cpus", "0 1 2 3\n"),
+                ("cpufreq/policy4/related_cpus", "4 5 6 7\n"),
+                ("cpufreq/policy4/scaling_min_freq", "600000\n"),
+                ("cpufreq/policy4/scaling_max_freq", "2400000\n"),
+                ("cpufreq/policy4/scaling_governor", "walt\n"),
+                (
+                    "cpufreq/policy4/scaling_available_frequencies",
+                    "600000 1200000 1800000 2100000 2400000\n",
+                ),
+            ],
+        );
+        let read =
+            |node: &str| utils::read_sysfs_full(&format!("{}/cpufreq/policy4/{}", root, node));
+
+        let mut state = FreqLockState::default();
+        let request = LockRequest {
+            locks: vec![ClusterLockRequest {
+                cluster: 1,
+                min_mhz: 2100,
+                max_mhz: 2400,
+            }],
+            policy: ThermalPolicy::default(),
+            thermal_guard: true,
+        };
+        lock_at(&root, &mut state, request).unwrap();
+        assert_eq!(read("scaling_min_freq").as_deref(), Some("2100000"));
+        assert_eq!(state.originals[0].min_freq, 600_000);
+
+        state.last_temperature = 75.0;
+        apply_transition_at(&root, &mut state, Transition::StepDown, 5_000);
+        assert_eq!(read("scaling_max_freq").as_deref(), Some("2100000"));
+
+        state.last_temperature = 88.0;
+        apply_transition_at(&root, &mut state, Transition::Critical, 6_000);
+        assert_eq!(read("scaling_min_freq").as_deref(), Some("600000"));
+        assert_eq!(read("scaling_governor").as_deref(), Some("powersave"));
+
+        let event = apply_transition_at(&root, &mut state, Transition::Restore, 20_000);
+        assert_eq!(event.kind, LockEventKind::Restored);
+        assert_eq!(read("scaling_governor").as_deref(), Some("walt"));
+        assert_eq!(read("scaling_max_freq").as_deref(), Some("2100000"));
+        assert!(!state.override_active);
+    }
+
+    #[test]
+    fn test_rejected_relock_keeps_originals() {
+        let root = fixture::build(
+            "freqlock_relock",
+            &[
+                ("cpufreq/policy0/related_cpus", "0 1 2 3\n"),
+                ("cpufreq/policy4/related_cpus", "4 5 6 7\n"),
+                ("cpufreq/policy4/scaling_min_freq", "600000\n"),
+                ("cpufreq/policy4/scaling_max_freq", "2400000\n"),
+                ("cpufreq/policy4/scaling_governor", "walt\n"),
+            ],
+        );
+        let read =
+            |node: &str| utils::read_sysfs_full(&format!("{}/cpufreq/policy4/{}", root, node));
+        let request = |cluster: i32, min_mhz: i32, max_mhz: i32| LockRequest {
+            locks: vec![ClusterLockRequest {
+                cluster,
+                min_mhz,
+                max_mhz,
+            }],
+            policy: ThermalPolicy::default(),
+            thermal_guard: false,
+        };
+
+        let mut state = FreqLockState::default();
+        lock_at(&root, &mut state, request(1, 1800, 2100)).unwrap();
+        assert_eq!(read("scaling_max_freq").as_deref(), Some("2100000"));
+
+        assert!(lock_at(&root, &mut state, request(5, 600, 2400)).is_err());
+        assert!(lock_at(&root, &mut state, request(1, 2100, 1800)).is_err());
+        assert!(lock_at(&root, &mut state, request(1, 1800, i32::MAX)).is_err());
+        assert!(state.locked);
+        assert_eq!(state.originals.len(), 1);
+
+        assert!(restore_originals_at(&root, &state));
+        assert_eq!(read("scaling_min_freq").as_deref(), Some("600000"));
+        assert_eq!(read("scaling_max_freq").as_deref(), Some("2400000"));
+    }
+
+    #[test]
+    fn test_lock_request_in_mhz() {
+        let root = fixture::build(
+            "freqlock_mhz",
+            &[
+                ("cpufreq/policy0/related_cpus", "0 1 2 3\n"),
+                ("cpufreq/policy0/scaling_min_freq", "300000\n"),
+                ("cpufreq/policy0/scaling_max_freq", "2000000\n"),
+                ("cpufreq/policy0/scaling_governor", "walt\n"),
+            ],
+        );
+        let request: LockRequest = serde_json::from_str(
+            r#"{"locks":[{"cluster":0,"min_mhz":1800,"max_mhz":1800}],"thermal_guard":false}"#,
+        )
+        .unwrap();
+
+        let mut state = FreqLockState::default();
+        lock_at(&root, &mut state, request).unwrap();
+        let max = utils::read_sysfs_full(&format!("{}/cpufreq/policy0/scaling_max_freq", root));
+        assert_eq!(max.as_deref(), Some("1800000"));
+        assert_eq!(state.locks[0].max_freq, 1_800_000);
+    }
+
+    #[test]
+    fn test_step_overflow_rejected() {
+        let policy = ThermalPolicy {
+            step_pct: u32::MAX,
+            max_steps: 2,
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use crate::utils::{self, WriteResult};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TunableKind {
+    Integer,
+    Boolean,
+    Percent,
+    Frequency,
+    FrequencyMap,
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovernorTunable {
+    pub name: String,
+    pub value: String,
+    pub kind: TunableKind,
+    pub writable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovernorTunables {
+    pub policy: i32,
+    pub governor: String,
+    pub path: String,
+    pub tunables: Vec<GovernorTunable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovernorSwitchResult {
+    pub policy: i32,
+    pub previous: String,
+    pub governor: WriteResult,
+    pub restored: Vec<WriteResult>,
+}
+
+type TunableValues = Vec<(String, String)>;
+
+/// Last known tunable values per (policy, governor), so that switching away
+/// from a governor and back does not lose the user's settings.
+static TUNABLE_SNAPSHOTS: Lazy<Mutex<HashMap<(i32, String), TunableValues>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Tunables whose meaning cannot be guessed from the value alone.
+fn known_kind(name: &str) -> Option<TunableKind> {
+    let kind = match name {
+        "up_rate_limit_us"
+        | "down_rate_limit_us"
+        | "rate_limit_us"
+        | "timer_rate"
+        | "timer_slack"
+        | "min_sample_time"
+        | "sampling_rate"
+        | "sampling_down_factor"
+        | "boostpulse_duration" => TunableKind::Integer,
+        "pl"
+        | "io_is_busy"
+        | "ignore_nice_load"
+        | "boost"
+        | "fast_ramp_down"
+        | "use_sched_load"
+        | "use_migration_notif"
+        | "align_windows" => TunableKind::Boolean,
+        "hispeed_load"
+        | "go_hispeed_load"
+        | "up_threshold"
+        | "down_threshold"
+        | "freq_step"
+        | "up_threshold_any_cpu_load" => TunableKind::Percent,
+        "hispeed_freq" | "rtg_boost_freq" | "boostpulse_freq" | "max_freq_hysteresis" => {
+            TunableKind::Frequency
+        }
+        "target_loads" | "above_hispeed_delay" => TunableKind::FrequencyMap,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn infer_kind(name: &str, value: &str) -> TunableKind {
+    if let Some(kind) = known_kind(name) {
+        return kind;
+    }
+
+    if value.parse::<i64>().is_ok() {
+        return TunableKind::Integer;
+    }
+
+    if !value.is_empty() && parse_frequency_map(value).is_some() {
+        return TunableKind::FrequencyMap;
+    }
+
+    TunableKind::Text
+}
+
+/// Parses interactive-style maps such as `80 1300000:90 1800000:95`. The
+/// first token is the default, every following `freq:value` pair applies from
+/// `freq` upwards, so frequencies must be ascending.
+fn parse_frequency_map(value: &str) -> Option<Vec<(Option<u32>, u32)>> {
+    let mut entries = Vec::new();
+    let mut last_freq = 0u32;
+
+    for token in value.split_whitespace() {
+        match token.split_once(':') {
+            Some((freq, val)) => {
+                let freq: u32 = freq.parse().ok()?;
+                if freq <= last_freq {
+                    return None;
+                }
+                last_freq = freq;
+                entries.push((Some(freq), val.parse().ok()?));
+            }
+            None if entries.is_empty() => entries.push((None, token.parse().ok()?)),
+            None => return None,
+        }
+    }
+
+    (!entries.is_empty()).then_some(entries)
+}
+
+pub(crate) fn policy_dir(root: &str, policy: i32) -> Option<String> {
+    let candidates = [
+        format!("{}/cpufreq/policy{}", root, policy),
+        format!("{}/cpu{}/cpufreq", root, policy),
+    ];
+
+    candidates.into_iter().find(|p| utils::file_exists(p))
+}
+
+/// Per-policy tunables live in `policyN/<governor>`; kernels without
+/// `have_governor_per_policy` keep a single global `cpufreq/<governor>`.
+fn governor_dir(root: &str, policy: i32, governor: &str) -> Option<String> {
+    let per_policy = format!("{}/{}", policy_dir(root, policy)?, governor);
+    if utils::file_exists(&per_policy) {
+        return Some(per_policy);
+    }
+
+    let global = format!("{}/cpufreq/{}", root, governor);
+    utils::file_exists(&global).then_some(global)
+}
+
+fn current_governor(root: &str, policy: i32) -> Option<String> {
+    utils::read_sysfs(&format!("{}/scaling_governor", policy_dir(root, policy)?))
+}
+
+fn is_writable(path: &str) -> bool {
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && !m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+fn read_tunables_at(root: &str, policy: i32) -> Option<GovernorTunables> {
+    let governor = current_governor(root, policy)?;
+    let path = governor_dir(root, policy, &governor)?;
+
+    let tunables = utils::list_dir(&path)
+        .into_iter()
+        .filter_map(|name| {
+            let file = format!("{}/{}", path, name);
+            let value = utils::read_sysfs_full(&file)?;
+            Some(GovernorTunable {
+                kind: infer_kind(&name, &value),
+                writable: is_writable(&file),
+                name,
+                value,
+            })
+        })
+        .collect();
+
+    Some(GovernorTunables {
+        policy,
+        governor,
+        path,
+        tunables,
+    })
+}
+
+fn frequency_bounds(root: &str, policy: i32) -> Option<(u32, u32)> {
+    let dir = policy_dir(root, policy)?;
+    let min = utils::read_sysfs(&format!("{}/cpuinfo_min_freq", dir))?
+        .parse()
+        .ok()?;
+    let max = utils::read_sysfs(&format!("{}/cpuinfo_max_freq", dir))?
+        .parse()
+        .ok()?;
+    Some((min, max))
+}
+
+fn validate(root: &str, policy: i32, kind: TunableKind, value: &str) -> Result<(), String> {
+    let in_range = |freq: u32| match frequency_bounds(root, policy) {
+        Some((min, max)) if freq < min || freq > max => {
+            Err(format!("{} kHz is outside {}-{} kHz", freq, min, max))
+        }
+        _ => Ok(()),
+    };
+
+    match kind {
+        TunableKind::Integer => value
+            .parse::<u64>()
+            .map(|_| ())
+            .map_err(|_| format!("'{}' is not a non-negative integer", value)),
+        TunableKind::Boolean => match value {
+            "0" | "1" => Ok(()),
+            _ => Err(format!("'{}' is not 0 or 1", value)),
+        },
+        TunableKind::Percent => match value.parse::<u32>() {
+            Ok(v) if v <= 100 => Ok(()),
+            _ => Err(format!("'{}' is not a percentage", value)),
+        },
+        TunableKind::Frequency => match value.parse::<u32>() {
+            Ok(0) => Ok(()),
+            Ok(freq) => in_range(freq),
+            Err(_) => Err(format!("'{}' is not a frequency in kHz", value)),
+        },
+        TunableKind::FrequencyMap => {
+            let entries = parse_frequency_map(value)
+                .ok_or_else(|| format!("'{}' is not a 'value freq:value ...' map", value))?;
+            entries
+                .iter()
+                .filter_map(|(freq, _)| *freq)
+                .try_for_each(in_range)
+        }
+        TunableKind::Text => {
+            if value.is_empty() || value.contains('\n') {
+                Err("value must be a single non-empty line".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_tunable_at(
+    root: &str,
+    policy: i32,
+    name: &str,
+    value: &str,
+) -> Result<WriteResult, String> {
+    let tunables = read_tunables_at(root, policy)
+        .ok_or_else(|| format!("policy{} has no governor tunables", policy))?;
+    let tunable = tunables
+        .tunables
+        .iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("{} has no tunable '{}'", tunables.governor, name))?;
+
+    if !tunable.writable {
+        return Err(format!("{} is read-only", name));
+    }
+
+    let value = value.trim();
+    validate(root, policy, tunable.kind, value)?;
+
+    let path = format!("{}/{}", tunables.path, name);
+    let mut result = utils::write_sysfs_verified(&path, value);
+
+    // Maps are echoed back with the kernel's own spacing.
+    if !result.success && tunable.kind == TunableKind::FrequencyMap {
+        result.success = parse_frequency_map(&result.actual) == parse_frequency_map(value);
+    }
+
+    Ok(result)
+}
+
+fn snapshot_at(root: &str, policy: i32) -> Option<GovernorTunables> {
+    let tunables = read_tunables_at(root, policy)?;
+    let values = tunables
+        .tunables
+        .iter()
+        .filter(|t| t.writable)
+        .map(|t| (t.name.clone(), t.value.clone()))
+        .collect();
+
+    TUNABLE_SNAPSHOTS
+        .lock()
+        .unwrap()
+        .insert((policy, tunables.governor.clone()), values);
+
+    Some(tunables)
+}
+
+fn restore_at(root: &str, policy: i32) -> Vec<WriteResult> {
+    let Some(governor) = current_governor(root, policy) else {
+        return Vec::new();
+    };
+    let Some(dir) = governor_dir(root, policy, &governor) else {
+        return Vec::new();
+    };
+
+    let saved = TUNABLE_SNAPSHOTS
+        .lock()
+        .unwrap()
+        .get(&(policy, governor))
+        .cloned()
+        .unwrap_or_default();
+
+    saved
+        .iter()
+        .map(|(name, value)| utils::write_sysfs_verified(&format!("{}/{}", dir, name), value))
+        .collect()
+}
+
+fn switch_governor_at(root: &str, policy: i32, governor: &str) -> Option<GovernorSwitchResult> {
+    let dir = policy_dir(root, policy)?;
+    let previous = current_governor(root, policy).unwrap_or_default();
+
+    snapshot_at(root, policy);
+
+    let result = utils::write_sysfs_verified(&format!("{}/scaling_governor", dir), governor);
+    let restored = if result.success {
+        restore_at(root, policy)
+    } else {
+        Vec::new()
+    };
+
+    Some(GovernorSwitchResult {
+        policy,
+        previous,
+        governor: result,
+        restored,
+    })
+}
+
+/// Tunables of the governor currently running on `policy` (numbered after
+/// its first CPU, as in `cpufreq/policyN`).
+pub fn read_governor_tunables(policy: i32) -> Option<GovernorTunables> {
+    read_tunables_at(CPU_ROOT, policy)
+}
+
+pub fn write_governor_tunable(policy: i32, name: &str, value: &str) -> Result<WriteResult, String> {
+    write_tunable_at(CPU_ROOT, policy, name, value)
+}
+
+pub fn snapshot_governor_tunables(policy: i32) -> Option<GovernorTunables> {
+    snapshot_at(CPU_ROOT, policy)
+}
+
+pub fn restore_governor_tunables(policy: i32) -> Vec<WriteResult> {
+    restore_at(CPU_ROOT, policy)
+}
+
+/// Changes the governor of `policy`, saving the outgoing governor's tunables
+/// and restoring the incoming governor's tunables from an earlier snapshot.
+pub fn switch_governor(policy: i32, governor: &str) -> Option<GovernorSwitchResult> {
+    switch_governor_at(CPU_ROOT, policy, governor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn tree(name: &str) -> String {
+        fixture::build(
+            name,
+            &[
+                ("cpufreq/policy4/scaling_governor", "walt\n"),
+                ("cpufreq/policy4/cpuinfo_min_freq", "633600\n"),
+                ("cpufreq/policy4/cpuinfo_max_freq", "2841600\n"),
+                ("cpufreq/policy4/walt/up_rate_limit_us", "500\n"),
+                ("cpufreq/policy4/walt/hispeed_freq", "1555200\n"),
+                ("cpufreq/policy4/walt/hispeed_load", "90\n"),
+                ("cpufreq/policy4/walt/pl", "1\n"),
+                ("cpufreq/policy4/walt/target_loads", "80 1500000:90\n"),
+                ("cpufreq/policy4/schedutil/rate_limit_us", "1000\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_parse_frequency_map() {
+        assert_eq!(
+            parse_frequency_map("80 1300000:90 1800000:95"),
+            Some(vec![(None, 80), (Some(1300000), 90), (Some(1800000), 95)])
+        );
+        assert_eq!(parse_frequency_map("1800000:95 1300000:90"), None);
+        assert_eq!(parse_frequency_map("80 90"), None);
+    }
+
+    #[test]
+    fn test_read_tunables() {
+        let root = tree("governor_read");
+        let tunables = read_tunables_at(&root, 4).unwrap();
+
+        assert_eq!(tunables.governor, "walt");
+        let kind = |name: &str| {
+            tunables
+                .tunables
+                .iter()
+                .find(|t| t.name == name)
+                .map(|t| t.kind)
+        };
+        assert_eq!(kind("hispeed_freq"), Some(TunableKind::Frequency));
+        assert_eq!(kind("pl"), Some(TunableKind::Boolean));
+        assert_eq!(kind("target_loads"), Some(TunableKind::FrequencyMap));
+        assert_eq!(kind("rate_limit_us"), None);
+    }
+
+    #[test]
+    fn test_write_validation() {
+        let root = tree("governor_write");
+
+        assert!(write_tunable_at(&root, 4, "hispeed_load", "120").is_err());
+        assert!(write_tunable_at(&root, 4, "hispeed_freq", "3000000").is_err());
+        assert!(write_tunable_at(&root, 4, "missing", "1").is_err());
+
+        let result = write_tunable_at(&root, 4, "hispeed_freq", "1804800").unwrap();
+        assert!(result.success);
+        assert_eq!(result.actual, "1804800");
+    }
+
+    #[test]
+    fn test_switch_restores_snapshot() {
+        let root = tree("governor_switch");
+        let limit = format!("{}/cpufreq/policy4/schedutil/rate_limit_us", root);
+
+        switch_governor_at(&root, 4, "schedutil").unwrap();
+        utils::write_sysfs(&limit, "2000");
+        switch_governor_at(&root, 4, "walt").unwrap();
+        utils::write_sysfs(&limit, "1000");
+
+        let result = switch_governor_at(&root, 4, "schedutil").unwrap();
+        assert_eq!(result.previous, "walt");
+        assert!(result.governor.success);
+        assert_eq!(utils::read_sysfs(&limit).as_deref(), Some("2000"));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::utils;
+use serde::{Deserialize, Serialize};
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HotplugStatus {
+    Applied,
+    Unchanged,
+    /// Blocked by an invariant before anything was written.
+    Rejected,
+    /// Written, but the kernel kept (or restored) the old state.
+    Refused,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotplugResult {
+    pub cpu: i32,
+    pub online: bool,
+    pub status: HotplugStatus,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreCtlState {
+    /// First CPU of the cluster, where Qualcomm exposes `core_ctl/`.
+    pub cpu: i32,
+    pub cores: Vec<i32>,
+    pub enable: Option<bool>,
+    pub min_cpus: Option<i32>,
+    pub max_cpus: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotplugReport {
+    pub results: Vec<HotplugResult>,
+    /// Clusters whose `core_ctl` was disabled or capped so it would not undo
+    /// the change.
+    pub core_ctl_adjusted: Vec<i32>,
+    pub core_ctl: Vec<CoreCtlState>,
+}
+
+/// Parses sysfs CPU lists such as `0-3`, `4 5 6` or `0-1,7`.
+pub fn parse_cpu_list(value: &str) -> Vec<i32> {
+    let mut cpus = Vec::new();
+    for token in value.split([' ', ',']).filter(|t| !t.is_empty()) {
+        match token.split_once('-') {
+            Some((a, b)) => {
+                if let (Ok(a), Ok(b)) = (a.parse::<i32>(), b.parse::<i32>()) {
+                    cpus.extend(a..=b);
+                }
+            }
+            None => cpus.extend(token.parse::<i32>().ok()),
+        }
+    }
+    cpus
+}
+
+/// Formats CPUs the way the kernel prints cpumasks, e.g. `0-3,6-7`.
+pub fn format_cpu_list(cpus: &[i32]) -> String {
+    let mut sorted = cpus.to_vec();
+    sorted.sort();
+    sorted.dedup();
+
+    let mut ranges: Vec<(i32, i32)> = Vec::new();
+    for cpu in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(a, b)| {
+            if a == b {
+                a.to_string()
+            } else {
+                format!("{}-{}", a, b)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Clusters including offline CPUs. `related_cpus` survives hotplug, unlike
+/// `scaling_*` which disappears with the core.
+pub(crate) fn cluster_topology(root: &str) -> Vec<Vec<i32>> {
+    let mut clusters: Vec<Vec<i32>> = utils::list_dir(&format!("{}/cpufreq", root))
+        .iter()
+        .filter(|name| name.starts_with("policy"))
+        .filter_map(|name| utils::read_sysfs(&format!("{}/cpufreq/{}/related_cpus", root, name)))
+        .map(|cpus| parse_cpu_list(&cpus))
+        .filter(|cpus| !cpus.is_empty())
+        .collect();
+
+    clusters.sort();
+    clusters
+}
+
+fn is_online(root: &str, cpu: i32) -> bool {
+    // cpu0 often has no `online` node because it cannot be taken down.
+    utils::read_sysfs_num::<i32>(&format!("{}/cpu{}/online", root, cpu))
+        .map_or(cpu == 0, |v| v == 1)
+}
+
+pub(crate) fn read_core_ctl_at(root: &str) -> Vec<CoreCtlState> {
+    cluster_topology(root)
+        .into_iter()
+        .filter_map(|cores| {
+            let cpu = *cores.first()?;
+            let dir = format!("{}/cpu{}/core_ctl", root, cpu);
+            if !utils::file_exists(&dir) {
+                return None;
+            }
+
+            Some(CoreCtlState {
+                cpu,
+                cores,
+                enable: utils::read_sysfs_num::<i32>(&format!("{}/enable", dir)).map(|v| v != 0),
+                min_cpus: utils::read_sysfs_num::<i32>(&format!("{}/min_cpus", dir)),
+                max_cpus: utils::read_sysfs_num::<i32>(&format!("{}/max_cpus", dir)),
+            })
+        })
+        .collect()
+}
+
+/// Keeps `core_ctl` from re-onlining cores of `state`'s cluster: disable it
+/// when the node exists, otherwise cap `max_cpus` to what stays online.
+fn hold_core_ctl(root: &str, state: &CoreCtlState, online_after: usize) -> bool {
+    let dir = format!("{}/cpu{}/core_ctl", root, state.cpu);
+
+    if state.enable == Some(true) {
+        return utils::write_sysfs_verified(&format!("{}/enable", dir), "0").success;
+    }
+
+    if state.enable.is_none()
+        && let Some(max) = state.max_cpus
+        && max as usize > online_after
+    {
+        let min = state.min_cpus.unwrap_or(0).min(online_after as i32);
+        utils::write_sysfs(&format!("{}/min_cpus", dir), &min.to_string());
+        return utils::write_sysfs_verified(
+            &format!("{}/max_cpus", dir),
+            &online_after.to_string(),
+        )
+        .success;
+    }
+
+    false
+}
+
+fn set_cores_online_at(
+    root: &str,
+    cpus: &[i32],
+    online: bool,
+    allow_empty_cluster: bool,
+) -> HotplugReport {
+    let clusters = cluster_topology(root);
+    let core_ctl = read_core_ctl_at(root);
+
+    let mut online_now: Vec<i32> = clusters
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|&cpu| is_online(root, cpu))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut core_ctl_adjusted = Vec::new();
+
+    for &cpu in cpus {
+        let result = |status, reason: Option<&str>| HotplugResult {
+            cpu,
+            online,
+            status,
+            reason: reason.map(|r| r.to_string()),
+        };
+
+        let Some(cluster) = clusters.iter().find(|c| c.contains(&cpu)) else {
+            results.push(result(HotplugStatus::Rejected, Some("unknown cpu")));
+            continue;
+        };
+
+        if online_now.contains(&cpu) == online {
+            results.push(result(HotplugStatus::Unchanged, None));
+            continue;
+        }
+
+        if !online && cpu == 0 {
+            results.push(result(
+                HotplugStatus::Rejected,
+                Some("cpu0 must stay online"),
+            ));
+            continue;
+        }
+
+        let remaining = cluster
+            .iter()
+            .filter(|c| online_now.contains(c) && **c != cpu)
+            .count();
+        if !online && remaining == 0 && !allow_empty_cluster {
+            results.push(result(
+                HotplugStatus::Rejected,
+                Some("last online core of its cluster"),
+            ));
+            continue;
+        }
+
+        if !online
+            && let Some(state) = core_ctl.iter().find(|s| s.cores.contains(&cpu))
+            && !core_ctl_adjusted.contains(&state.cpu)
+            && hold_core_ctl(root, state, remaining)
+        {
+            core_ctl_adjusted.push(state.cpu);
+        }
+
+        let path = format!("{}/cpu{}/online", root, cpu);
+        let written = utils::write_sysfs(&path, if online { "1" } else { "0" });
+
+        if written && is_online(root, cpu) == online {
+            if online {
+                online_now.push(cpu);
+            } else {
+                online_now.retain(|&c| c != cpu);
+            }
+            results.push(result(HotplugStatus::Applied, None));
+        } else {
+            let reason = if written {
+                "kernel kept the previous state"
+            } else {
+                "write rejected by the kernel"
+            };
+            results.push(result(HotplugStatus::Refused, Some(reason)));
+        }
+    }
+
+    HotplugReport {
+        results,
+        core_ctl_adjusted,
+        core_ctl: read_core_ctl_at(root),
+    }
+}
+
+fn set_core_ctl_at(
+    root: &str,
+    cpu: i32,
+    enable: Option<bool>,
+    min: Option<i32>,
+    max: Option<i32>,
+) -> Vec<utils::WriteResult> {
+    let dir = format!("{}/cpu{}/core_ctl", root, cpu);
+    let mut results = Vec::new();
+
+    // Raise max before min and lower it after, so the pair never crosses on
+    // the way.
+    let mut range = [("min_cpus", min), ("max_cpus", max)];
+    let current_max = utils::read_sysfs_num::<i32>(&format!("{}/max_cpus", dir)).unwrap_or(0);
+    if max.is_some_and(|m| m > current_max) {
+        range.reverse();
+    }
+    let writes = range
+        .into_iter()
+        .chain([("enable", enable.map(|e| e as i32))]);
+
+    for (node, value) in writes {
+        let Some(value) = value else { continue };
+        let path = format!("{}/{}", dir, node);
+        if utils::file_exists(&path) {
+            results.push(utils::write_sysfs_verified(&path, &value.to_string()));
+        }
+    }
+
+    results
+}
+
+/// Brings `cpus` on or offline while keeping cpu0 and at least one core per
+/// cluster online (unless `allow_empty_cluster`). `core_ctl` on affected
+/// clusters is held back so it does not re-online the cores.
+pub fn set_cores_online(cpus: &[i32], online: bool, allow_empty_cluster: bool) -> HotplugReport {
+    set_cores_online_at(CPU_ROOT, cpus, online, allow_empty_cluster)
+}
+
+pub fn read_core_ctl() -> Vec<CoreCtlState> {
+    read_core_ctl_at(CPU_ROOT)
+}
+
+pub fn set_core_ctl(
+    cpu: i32,
+    enable: Option<bool>,
+    min_cpus: Option<i32>,
+    max_cpus: Option<i32>,
+) -> Vec<utils::WriteResult> {
+    set_core_ctl_at(CPU_ROOT, cpu, enable, min_cpus, max_cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn tree(name: &str) -> String {
+        fixture::build(
+            name,
+            &[
+                ("cpufreq/policy0/related_cpus", "0 1 2 3\n"),
+                ("cpufreq/policy4/related_cpus", "4 5 6\n"),
+                ("cpufreq/policy7/related_cpus", "7\n"),
+                ("cpu1/online", "1\n"),
+                ("cpu2/online", "1\n"),
+                ("cpu3/online", "1\n"),
+                ("cpu4/online", "1\n"),
+                ("cpu5/online", "1\n"),
+                ("cpu6/online", "0\n"),
+                ("cpu7/online", "1\n"),
+                ("cpu4/core_ctl/enable", "1\n"),
+                ("cpu4/core_ctl/min_cpus", "2\n"),
+                ("cpu4/core_ctl/max_cpus", "3\n"),
+            ],
+        )
+    }
+
+    fn status(report: &HotplugReport, cpu: i32) -> HotplugStatus {
+        report.results.iter().find(|r| r.cpu == cpu).unwrap().status
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3"), vec![0, 1, 2, 3]);
+        assert_eq!(parse_cpu_list("4 5 6"), vec![4, 5, 6]);
+        assert_eq!(parse_cpu_list("0-1,7"), vec![0, 1, 7]);
+        assert_eq!(format_cpu_list(&[7, 0, 1, 2, 3, 5]), "0-3,5,7");
+    }
+
+    #[test]
+    fn test_invariants() {
+        let root = tree("hotplug_invariants");
+
+        let report = set_cores_online_at(&root, &[0, 7, 6, 3], false, false);
+        assert_eq!(status(&report, 0), HotplugStatus::Rejected);
+        assert_eq!(status(&report, 7), HotplugStatus::Rejected);
+        assert_eq!(status(&report, 6), HotplugStatus::Unchanged);
+        assert_eq!(status(&report, 3), HotplugStatus::Applied);
+        assert!(!is_online(&root, 3));
+
+        let forced = set_cores_online_at(&root, &[7], false, true);
+        assert_eq!(status(&forced, 7), HotplugStatus::Applied);
+    }
+
+    #[test]
+    fn test_core_ctl_is_held() {
+        let root = tree("hotplug_core_ctl");
+
+        let report = set_cores_online_at(&root, &[4, 5], false, false);
+        assert_eq!(status(&report, 4), HotplugStatus::Applied);
+        assert_eq!(status(&report, 5), HotplugStatus::Rejected);
+        assert_eq!(report.core_ctl_adjusted, vec![4]);
+        assert_eq!(report.core_ctl[0].enable, Some(false));
+    }
+
+    #[test]
+    fn test_core_ctl_range_order() {
+        let root = tree("hotplug_core_ctl_range");
+        let nodes = |results: &[utils::WriteResult]| -> Vec<String> {
+            results
+                .iter()
+                .map(|r| r.path.rsplit('/').next().unwrap().to_string())
+                .collect()
+        };
+
+        let raised = set_core_ctl_at(&root, 4, None, Some(3), Some(4));
+        assert_eq!(nodes(&raised), vec!["max_cpus", "min_cpus"]);
+        assert!(raised.iter().all(|r| r.success));
+
+        let lowered = set_core_ctl_at(&root, 4, Some(true), Some(1), Some(2));
+        assert_eq!(nodes(&lowered), vec!["min_cpus", "max_cpus", "enable"]);
+        assert!(lowered.iter().all(|r| r.success));
+    }
+}
@@ -0,0 +1,550 @@
+use crate::utils;
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitEffect {
+    /// Lowers the maximum frequency.
+    Cap,
+    /// Raises the minimum frequency.
+    Floor,
+    /// Restricts how many cores may run.
+    Cores,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limiter {
+    pub source: String,
+    pub path: String,
+    pub value: String,
+    pub effect: LimitEffect,
+    pub freq: Option<i32>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterLimits {
+    pub policy: i32,
+    pub cpus: Vec<i32>,
+    pub cpuinfo_max_freq: i32,
+    pub scaling_max_freq: i32,
+    pub requested_max_freq: Option<i32>,
+    pub effective_max_freq: i32,
+    /// Active caps sitting at the effective ceiling.
+    pub limited_by: Vec<String>,
+    pub limiters: Vec<Limiter>,
+}
+
+#[derive(Clone, Copy)]
+enum NodeFormat {
+    /// `0:1209600 4:0`, keyed by CPU.
+    CpuMap,
+    Value,
+}
+
+/// Module and vendor nodes that cap or boost every policy. `{cpu}` is
+/// replaced by the policy's first CPU.
+const VENDOR_NODES: [(&str, &str, NodeFormat, LimitEffect); 6] = [
+    (
+        "msm_performance",
+        "module/msm_performance/parameters/cpu_max_freq",
+        NodeFormat::CpuMap,
+        LimitEffect::Cap,
+    ),
+    (
+        "msm_performance",
+        "module/msm_performance/parameters/cpu_min_freq",
+        NodeFormat::CpuMap,
+        LimitEffect::Floor,
+    ),
+    (
+        "cpu_boost",
+        "module/cpu_boost/parameters/input_boost_freq",
+        NodeFormat::CpuMap,
+        LimitEffect::Floor,
+    ),
+    (
+        "cpu_boost",
+        "module/cpu_boost/parameters/powerkey_input_boost_freq",
+        NodeFormat::CpuMap,
+        LimitEffect::Floor,
+    ),
+    (
+        "lmh_dcvsh",
+        "devices/system/cpu/cpu{cpu}/dcvsh_freq_limit",
+        NodeFormat::Value,
+        LimitEffect::Cap,
+    ),
+    (
+        "exynos_cpufreq",
+        "power/cpufreq_max_limit",
+        NodeFormat::Value,
+        LimitEffect::Cap,
+    ),
+];
+
+/// Parses per-CPU frequency maps such as `0:1209600 4:0` or `0:0,1:0`.
+pub fn parse_cpu_freq_map(value: &str) -> Vec<(i32, i64)> {
+    value
+        .split([' ', ',', '\n'])
+        .filter_map(|pair| {
+            let (cpu, freq) = pair.trim().split_once(':')?;
+            Some((cpu.parse().ok()?, freq.parse().ok()?))
+        })
+        .collect()
+}
+
+fn limiter(
+    source: &str,
+    path: String,
+    value: String,
+    effect: LimitEffect,
+    freq: Option<i32>,
+    active: bool,
+) -> Limiter {
+    Limiter {
+        source: source.to_string(),
+        path,
+        value,
+        effect,
+        freq,
+        active,
+    }
+}
+
+/// `thermal-cpufreq-N` counts policies in order, `cpufreq-cpuN` names the
+/// first CPU. Both are cpufreq cooling devices whose state indexes the
+/// frequency table from the top.
+fn cooling_policy(cooling_type: &str, policies: &[i32], cpus: &[i32]) -> Option<bool> {
+    if let Some(n) = cooling_type.strip_prefix("thermal-cpufreq-") {
+        let n: usize = n.parse().ok()?;
+        return Some(policies.get(n) == cpus.first());
+    }
+    if let Some(cpu) = cooling_type.strip_prefix("cpufreq-cpu") {
+        return Some(cpus.contains(&cpu.parse().ok()?));
+    }
+    None
+}
+
+fn thermal_limiters(sys: &str, policies: &[i32], cpus: &[i32], freqs_desc: &[i32]) -> Vec<Limiter> {
+    let thermal = format!("{}/class/thermal", sys);
+    let mut limiters = Vec::new();
+
+    for name in utils::list_dir(&thermal) {
+        if !name.starts_with("cooling_device") {
+            continue;
+        }
+        let dir = format!("{}/{}", thermal, name);
+        let cooling_type = utils::read_sysfs_full(&format!("{}/type", dir)).unwrap_or_default();
+        let state = utils::read_sysfs_num::<i64>(&format!("{}/cur_state", dir)).unwrap_or(0);
+        let max_state = utils::read_sysfs_num::<i64>(&format!("{}/max_state", dir)).unwrap_or(0);
+        let value = format!("{}/{}", state, max_state);
+        let path = format!("{}/cur_state", dir);
+
+        if let Some(cpu) = cooling_type.strip_prefix("cpu-isolate") {
+            if cpu.parse().ok().is_some_and(|cpu: i32| cpus.contains(&cpu)) {
+                limiters.push(limiter(
+                    &cooling_type,
+                    path,
+                    value,
+                    LimitEffect::Cores,
+                    None,
+                    state > 0,
+                ));
+            }
+            continue;
+        }
+
+        if cooling_policy(&cooling_type, policies, cpus) != Some(true) {
+            continue;
+        }
+
+        let freq = (state > 0)
+            .then(|| {
+                freqs_desc
+                    .get(state as usize)
+                    .or(freqs_desc.last())
+                    .copied()
+            })
+            .flatten();
+        limiters.push(limiter(
+            &cooling_type,
+            path,
+            value,
+            LimitEffect::Cap,
+            freq,
+            state > 0,
+        ));
+    }
+
+    limiters
+}
+
+/// PM QoS requests exposed per policy (`min_freq_qos`, `max_freq_qos`, ...).
+fn qos_limiters(policy_dir: &str, cpuinfo_min: i32, cpuinfo_max: i32) -> Vec<Limiter> {
+    utils::list_dir(policy_dir)
+        .into_iter()
+        .filter(|name| name.contains("qos"))
+        .filter_map(|name| {
+            let path = format!("{}/{}", policy_dir, name);
+            let raw = utils::read_sysfs_full(&path)?;
+            let freq = utils::read_sysfs_num::<i64>(&path).map(|v| v.min(i32::MAX as i64) as i32);
+
+            let (effect, active) = if name.contains("min") {
+                (LimitEffect::Floor, freq.is_some_and(|f| f > cpuinfo_min))
+            } else {
+                (
+                    LimitEffect::Cap,
+                    freq.is_some_and(|f| f > 0 && f < cpuinfo_max),
+                )
+            };
+            Some(limiter("pm_qos", path, raw, effect, freq, active))
+        })
+        .collect()
+}
+
+fn vendor_limiters(sys: &str, cpus: &[i32], cpuinfo_min: i32, cpuinfo_max: i32) -> Vec<Limiter> {
+    let first = cpus.first().copied().unwrap_or(0);
+    let mut limiters = Vec::new();
+
+    for (source, rel, format, effect) in VENDOR_NODES {
+        let path = format!("{}/{}", sys, rel.replace("{cpu}", &first.to_string()));
+        let Some(raw) = utils::read_sysfs_full(&path) else {
+            continue;
+        };
+
+        let freq = match format {
+            NodeFormat::CpuMap => parse_cpu_freq_map(&raw)
+                .into_iter()
+                .find(|(cpu, _)| cpus.contains(cpu))
+                .map(|(_, freq)| freq),
+            NodeFormat::Value => utils::read_sysfs_num::<i64>(&path),
+        };
+        let Some(freq) = freq else { continue };
+        let freq = freq.clamp(0, i32::MAX as i64) as i32;
+
+        let active = match effect {
+            LimitEffect::Cap => freq > 0 && freq < cpuinfo_max,
+            _ => freq > cpuinfo_min,
+        };
+        limiters.push(limiter(source, path, raw, effect, Some(freq), active));
+    }
+
+    limiters
+}
+
+fn core_ctl_limiter(cpu_root: &str, cpus: &[i32]) -> Option<Limiter> {
+    let state = super::hotplug::read_core_ctl_at(cpu_root)
+        .into_iter()
+        .find(|s| cpus.contains(&s.cpu))?;
+
+    let max = state.max_cpus?;
+    let active = state.enable != Some(false) && (max as usize) < state.cores.len();
+    let value = format!(
+        "enable={} min_cpus={} max_cpus={}",
+        state.enable.map(|e| e as i32).unwrap_or(-1),
+        state.min_cpus.unwrap_or(-1),
+        max
+    );
+
+    Some(limiter(
+        "core_ctl",
+        format!("{}/cpu{}/core_ctl", cpu_root, state.cpu),
+        value,
+        LimitEffect::Cores,
+        None,
+        active,
+    ))
+}
+
+/// MediaTek PPM hard user limits; PPM numbers clusters in topology order.
+fn ppm_limiters(proc: &str, cluster: usize, cpuinfo_min: i32, cpuinfo_max: i32) -> Vec<Limiter> {
+    let Some(limit) = crate::mtk::read_ppm_limits_at(proc)
+        .into_iter()
+        .find(|l| l.cluster as usize == cluster)
+    else {
+        return Vec::new();
+    };
+    let path = |node: &str| format!("{}/ppm/policy/{}", proc, node);
+    let freq = |khz: i64| khz.min(i32::MAX as i64) as i32;
+
+    let mut limiters = Vec::new();
+    if let Some(khz) = limit.min_khz {
+        limiters.push(limiter(
+            "ppm_userlimit",
+            path("hard_userlimit_min_cpu_freq"),
+            khz.to_string(),
+            LimitEffect::Floor,
+            Some(freq(khz)),
+            freq(khz) > cpuinfo_min,
+        ));
+    }
+    if let Some(khz) = limit.max_khz {
+        limiters.push(limiter(
+            "ppm_userlimit",
+            path("hard_userlimit_max_cpu_freq"),
+            khz.to_string(),
+            LimitEffect::Cap,
+            Some(freq(khz)),
+            freq(khz) < cpuinfo_max,
+        ));
+    }
+    limiters
+}
+
+fn explain_at(sys: &str, proc: &str, requested: &[(i32, i64)]) -> Vec<ClusterLimits> {
+    let cpu_root = format!("{}/devices/system/cpu", sys);
+    let topology = super::cluster_topology(&cpu_root);
+    let policies: Vec<i32> = topology.iter().filter_map(|c| c.first().copied()).collect();
+
+    topology
+        .iter()
+        .enumerate()
+        .filter_map(|(cluster, cpus)| {
+            let policy = *cpus.first()?;
+            let dir = super::governor::policy_dir(&cpu_root, policy)?;
+            let freq = |node: &str| {
+                utils::read_sysfs_num::<i64>(&format!("{}/{}", dir, node)).unwrap_or(0) as i32
+            };
+
+            let cpuinfo_min = freq("cpuinfo_min_freq");
+            let cpuinfo_max = freq("cpuinfo_max_freq");
+            let scaling_max = freq("scaling_max_freq");
+
+            let mut freqs_desc: Vec<i32> =
+                utils::read_sysfs_full(&format!("{}/scaling_available_frequencies", dir))
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter_map(|f| f.parse().ok())
+                    .collect();
+            freqs_desc.sort_by(|a, b| b.cmp(a));
+
+            let mut limiters = thermal_limiters(sys, &policies, cpus, &freqs_desc);
+            limiters.extend(qos_limiters(&dir, cpuinfo_min, cpuinfo_max));
+            limiters.extend(vendor_limiters(sys, cpus, cpuinfo_min, cpuinfo_max));
+            limiters.extend(core_ctl_limiter(&cpu_root, cpus));
+            limiters.extend(ppm_limiters(proc, cluster, cpuinfo_min, cpuinfo_max));
+
+            let active_caps = || {
+                limiters
+                    .iter()
+                    .filter(|l| l.active && l.effect == LimitEffect::Cap)
+                    .filter_map(|l| Some((l, l.freq?)))
+            };
+
+            let effective = active_caps()
+                .map(|(_, f)| f)
+                .chain([scaling_max])
+                .filter(|&f| f > 0)
+                .min()
+                .unwrap_or(cpuinfo_max);
+
+            let mut limited_by: Vec<String> = Vec::new();
+            for (limiter, _) in active_caps().filter(|&(_, f)| f <= effective) {
+                if !limited_by.contains(&limiter.source) {
+                    limited_by.push(limiter.source.clone());
+                }
+            }
+
+            let requested_max = requested
+                .iter()
+                .find(|(cpu, _)| cpus.contains(cpu))
+                .map(|&(_, f)| f as i32);
+            if limited_by.is_empty() && requested_max.is_some_and(|r| r > effective) {
+                limited_by.push("scaling_max_freq".to_string());
+            }
+
+            Some(ClusterLimits {
+                policy,
+                cpus: cpus.clone(),
+                cpuinfo_max_freq: cpuinfo_max,
+                scaling_max_freq: scaling_max,
+                requested_max_freq: requested_max,
+                effective_max_freq: effective,
+                limited_by,
+                limiters,
+            })
+        })
+        .collect()
+}
+
+/// Explains each cluster's ceiling. `requested` maps a CPU of the cluster to
+/// the max the user asked for; clusters without an entry fall back to the
+/// frequency lock engine's target, if any.
+pub fn explain_frequency_limits(requested: &[(i32, i64)]) -> Vec<ClusterLimits> {
+    let mut requested = requested.to_vec();
+
+    let lock = super::get_freq_lock_state();
+    if lock.locked {
+        let topology = super::cluster_topology(&format!("{}/devices/system/cpu", SYS_ROOT));
+        for l in &lock.locks {
+            if let Some(&cpu) = topology.get(l.cluster as usize).and_then(|c| c.first())
+                && !requested.iter().any(|(c, _)| *c == cpu)
+            {
+                requested.push((cpu, l.max_freq as i64));
+            }
+        }
+    }
+
+    explain_at(SYS_ROOT, PROC_ROOT, &requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_parse_cpu_freq_map() {
+        assert_eq!(
+            parse_cpu_freq_map("0:1209600 4:0 7:4294967295"),
+            vec![(0, 1209600), (4, 0), (7, 4294967295)]
+        );
+        assert_eq!(
+            parse_cpu_freq_map("0:0,1:300000\n"),
+            vec![(0, 0), (1, 300000)]
+        );
+    }
+
+    #[test]
+    fn test_explain_limits() {
+        let cpu = "devices/system/cpu";
+        let files = [
+            (format!("{}/cpufreq/policy0/related_cpus", cpu), "0 1 2 3"),
+            (
+                format!("{}/cpufreq/policy0/cpuinfo_min_freq", cpu),
+                "300000",
+            ),
+            (
+                format!("{}/cpufreq/policy0/cpuinfo_max_freq", cpu),
+                "1800000",
+            ),
+            (
+                format!("{}/cpufreq/policy0/scaling_max_freq", cpu),
+                "1800000",
+            ),
+            (format!("{}/cpufreq/policy4/related_cpus", cpu), "4 5 6 7"),
+            (
+                format!("{}/cpufreq/policy4/cpuinfo_min_freq", cpu),
+                "600000",
+            ),
+            (
+                format!("{}/cpufreq/policy4/cpuinfo_max_freq", cpu),
+                "3200000",
+            ),
+            (
+                format!("{}/cpufreq/policy4/scaling_max_freq", cpu),
+                "2400000",
+            ),
+            (
+                format!("{}/cpufreq/policy4/scaling_available_frequencies", cpu),
+                "600000 1200000 1800000 2400000 3200000",
+            ),
+            (format!("{}/cpufreq/policy4/max_freq_qos", cpu), "2400000"),
+            (format!("{}/cpu4/core_ctl/enable", cpu), "1"),
+            (format!("{}/cpu4/core_ctl/min_cpus", cpu), "1"),
+            (format!("{}/cpu4/core_ctl/max_cpus", cpu), "2"),
+            (
+                "class/thermal/cooling_device0/type".into(),
+                "thermal-cpufreq-1",
+            ),
+            ("class/thermal/cooling_device0/cur_state".into(), "1"),
+            ("class/thermal/cooling_device0/max_state".into(), "4"),
+            (
+                "class/thermal/cooling_device1/type".into(),
+                "thermal-cpufreq-0",
+            ),
+            ("class/thermal/cooling_device1/cur_state".into(), "0"),
+            ("class/thermal/cooling_device2/type".into(), "cpufreq-cpu4"),
+            ("class/thermal/cooling_device2/cur_state".into(), "1"),
+            (
+                "class/thermal/cooling_device3/type".into(),
+                "thermal-cpufreq-1",
+            ),
+            ("class/thermal/cooling_device3/cur_state".into(), "1"),
+            (
+                "module/msm_performance/parameters/cpu_max_freq".into(),
+                "0:4294967295 4:2400000",
+            ),
+            (
+                "module/cpu_boost/parameters/input_boost_freq".into(),
+                "0:1209600 4:0",
+            ),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        let sys = fixture::build("limits", &files);
+
+        let report = explain_at(&sys, &format!("{}/proc", sys), &[(4, 3_200_000)]);
+        assert_eq!(report.len(), 2);
+
+        let little = &report[0];
+        assert_eq!(little.effective_max_freq, 1_800_000);
+        assert!(little.limited_by.is_empty());
+        let boost = little
+            .limiters
+            .iter()
+            .find(|l| l.source == "cpu_boost")
+            .unwrap();
+        assert!(boost.active);
+        assert_eq!(boost.effect, LimitEffect::Floor);
+        let cooling = little
+            .limiters
+            .iter()
+            .find(|l| l.source == "thermal-cpufreq-0")
+            .unwrap();
+        assert!(!cooling.active);
+
+        let big = &report[1];
+        assert_eq!(big.requested_max_freq, Some(3_200_000));
+        assert_eq!(big.effective_max_freq, 2_400_000);
+        assert_eq!(
+            big.limited_by,
+            vec![
+                "thermal-cpufreq-1",
+                "cpufreq-cpu4",
+                "pm_qos",
+                "msm_performance"
+            ]
+        );
+        let core_ctl = big
+            .limiters
+            .iter()
+            .find(|l| l.source == "core_ctl")
+            .unwrap();
+        assert!(core_ctl.active);
+    }
+
+    #[test]
+    fn test_ppm_userlimit() {
+        let cpu = "devices/system/cpu";
+        let files = [
+            (format!("{}/cpufreq/policy0/related_cpus", cpu), "0 1 2 3"),
+            (
+                format!("{}/cpufreq/policy0/cpuinfo_max_freq", cpu),
+                "2000000",
+            ),
+            (
+                format!("{}/cpufreq/policy0/scaling_max_freq", cpu),
+                "2000000",
+            ),
+            (
+                "proc/ppm/policy/hard_userlimit_max_cpu_freq".into(),
+                "cluster 0: 1500000\n",
+            ),
+            (
+                "proc/ppm/policy/hard_userlimit_min_cpu_freq".into(),
+                "cluster 0: -1\n",
+            ),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        let sys = fixture::build("limits_ppm", &files);
+
+        let report = explain_at(&sys, &format!("{}/proc", sys), &[]);
+        assert_eq!(report[0].effective_max_freq, 1_500_000);
+        assert_eq!(report[0].limited_by, vec!["ppm_userlimit"]);
+        assert_eq!(report[0].limiters.len(), 1);
+    }
+}
@@ -1,2 +1,21 @@
+pub mod boost;
+pub mod core_thermal;
+#[allow(clippy::module_inception)]
 mod cpu;
+pub mod cpuidle;
+pub mod freqlock;
+pub mod governor;
+pub mod hotplug;
+pub mod limits;
+pub mod perf;
+pub mod soc;
+pub use boost::*;
+pub use core_thermal::*;
 pub use cpu::*;
+pub use cpuidle::*;
+pub use freqlock::*;
+pub use governor::*;
+pub use hotplug::*;
+pub use limits::*;
+pub use perf::*;
+pub use soc::*;
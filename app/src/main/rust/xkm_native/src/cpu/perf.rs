@@ -0,0 +1,460 @@
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::FromRawFd;
+use std::time::Duration;
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+const PARANOID_PATH: &str = "/proc/sys/kernel/perf_event_paranoid";
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1;
+const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+const PERF_ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+const PERF_ATTR_EXCLUDE_HV: u64 = 1 << 6;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// Cache misses per thousand instructions above which a core with low IPC
+/// is waiting on memory rather than computing.
+const MEMORY_BOUND_MPKI: f64 = 5.0;
+const COMPUTE_BOUND_IPC: f64 = 1.0;
+
+/// `struct perf_event_attr` up to `config1` (`PERF_ATTR_SIZE_VER0`); the
+/// kernel zero-extends shorter versions.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerfEvent {
+    Cycles,
+    Instructions,
+    CacheMisses,
+    BranchMisses,
+    TaskClock,
+    ContextSwitches,
+}
+
+impl PerfEvent {
+    const HARDWARE: [PerfEvent; 4] = [
+        PerfEvent::Cycles,
+        PerfEvent::Instructions,
+        PerfEvent::CacheMisses,
+        PerfEvent::BranchMisses,
+    ];
+    const SOFTWARE: [PerfEvent; 2] = [PerfEvent::TaskClock, PerfEvent::ContextSwitches];
+
+    fn name(self) -> &'static str {
+        match self {
+            PerfEvent::Cycles => "cycles",
+            PerfEvent::Instructions => "instructions",
+            PerfEvent::CacheMisses => "cache_misses",
+            PerfEvent::BranchMisses => "branch_misses",
+            PerfEvent::TaskClock => "task_clock",
+            PerfEvent::ContextSwitches => "context_switches",
+        }
+    }
+
+    fn kind_and_config(self) -> (u32, u64) {
+        match self {
+            PerfEvent::Cycles => (PERF_TYPE_HARDWARE, 0),
+            PerfEvent::Instructions => (PERF_TYPE_HARDWARE, 1),
+            PerfEvent::CacheMisses => (PERF_TYPE_HARDWARE, 3),
+            PerfEvent::BranchMisses => (PERF_TYPE_HARDWARE, 5),
+            PerfEvent::TaskClock => (PERF_TYPE_SOFTWARE, 1),
+            PerfEvent::ContextSwitches => (PERF_TYPE_SOFTWARE, 3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerfMode {
+    /// Hardware and software events.
+    Hardware,
+    /// Hardware counters were refused; task-clock and context switches only.
+    Software,
+    Unavailable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundness {
+    Memory,
+    Compute,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerfCounts {
+    pub cycles: Option<u64>,
+    pub instructions: Option<u64>,
+    pub cache_misses: Option<u64>,
+    pub branch_misses: Option<u64>,
+    pub task_clock_ns: Option<u64>,
+    pub context_switches: Option<u64>,
+    pub ipc: Option<f64>,
+    /// Cache misses per thousand instructions.
+    pub cache_mpki: Option<f64>,
+    /// Branch misses per thousand instructions.
+    pub branch_mpki: Option<f64>,
+    pub boundness: Option<Boundness>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorePerf {
+    pub cpu: i32,
+    pub counts: PerfCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterPerf {
+    pub policy: i32,
+    pub cpus: Vec<i32>,
+    pub counts: PerfCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerfReport {
+    pub interval_ms: u64,
+    pub paranoid: Option<i32>,
+    pub mode: PerfMode,
+    /// First refusal per event, e.g. `cycles on cpu0: Permission denied`.
+    pub errors: Vec<String>,
+    pub cores: Vec<CorePerf>,
+    pub clusters: Vec<ClusterPerf>,
+    pub total: PerfCounts,
+}
+
+/// Counts are scaled by enabled/running time when the PMU had to multiplex.
+struct Counter {
+    event: PerfEvent,
+    file: File,
+    start: [u64; 3],
+}
+
+fn open_event(event: PerfEvent, pid: i32, cpu: i32, exclude_kernel: bool) -> std::io::Result<File> {
+    let (kind, config) = event.kind_and_config();
+    let mut flags = PERF_ATTR_EXCLUDE_HV;
+    if exclude_kernel {
+        flags |= PERF_ATTR_EXCLUDE_KERNEL;
+    }
+    let attr = PerfEventAttr {
+        kind,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config,
+        read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+        flags,
+        ..Default::default()
+    };
+
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            &attr as *const PerfEventAttr,
+            pid,
+            cpu,
+            -1,
+            PERF_FLAG_FD_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd as i32) })
+}
+
+fn read_counter(file: &mut File) -> Option<[u64; 3]> {
+    let mut buf = [0u8; 24];
+    file.read_exact(&mut buf).ok()?;
+    let word = |i: usize| u64::from_ne_bytes(buf[i * 8..i * 8 + 8].try_into().unwrap());
+    Some([word(0), word(1), word(2)])
+}
+
+/// Opens `events` on every target CPU. An event refused on one CPU is not
+/// retried on the others; an offline CPU (`ENODEV`) is skipped silently.
+fn open_counters(
+    events: &[PerfEvent],
+    pid: i32,
+    cpus: &[i32],
+    exclude_kernel: bool,
+    errors: &mut Vec<String>,
+) -> Vec<(i32, Vec<Counter>)> {
+    let mut refused: Vec<PerfEvent> = Vec::new();
+    let mut opened = Vec::new();
+
+    for &cpu in cpus {
+        let mut counters = Vec::new();
+        for &event in events {
+            if refused.contains(&event) {
+                continue;
+            }
+            match open_event(event, pid, cpu, exclude_kernel) {
+                Ok(mut file) => {
+                    if let Some(start) = read_counter(&mut file) {
+                        counters.push(Counter { event, file, start });
+                    }
+                }
+                Err(error) if error.raw_os_error() == Some(libc::ENODEV) => break,
+                Err(error) => {
+                    refused.push(event);
+                    errors.push(format!("{} on cpu{}: {}", event.name(), cpu, error));
+                }
+            }
+        }
+        if !counters.is_empty() {
+            opened.push((cpu, counters));
+        }
+    }
+
+    opened
+}
+
+fn read_delta(counter: &mut Counter) -> Option<u64> {
+    let [value, enabled, running] = read_counter(&mut counter.file)?;
+    let value = value.saturating_sub(counter.start[0]);
+    let enabled = enabled.saturating_sub(counter.start[1]);
+    let running = running.saturating_sub(counter.start[2]);
+
+    if running == 0 {
+        return if enabled == 0 { Some(value) } else { None };
+    }
+    Some((value as u128 * enabled as u128 / running as u128) as u64)
+}
+
+fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Fills in the ratios from the raw counts.
+fn derive(mut counts: PerfCounts) -> PerfCounts {
+    let per_kilo = |misses: Option<u64>| match (misses, counts.instructions) {
+        (Some(misses), Some(instructions)) if instructions > 0 => {
+            Some(misses as f64 * 1000.0 / instructions as f64)
+        }
+        _ => None,
+    };
+
+    counts.cache_mpki = per_kilo(counts.cache_misses);
+    counts.branch_mpki = per_kilo(counts.branch_misses);
+    counts.ipc = match (counts.instructions, counts.cycles) {
+        (Some(instructions), Some(cycles)) if cycles > 0 => {
+            Some(instructions as f64 / cycles as f64)
+        }
+        _ => None,
+    };
+    counts.boundness = match (counts.ipc, counts.cache_mpki) {
+        (Some(ipc), Some(mpki)) if ipc < COMPUTE_BOUND_IPC && mpki >= MEMORY_BOUND_MPKI => {
+            Some(Boundness::Memory)
+        }
+        (Some(ipc), Some(mpki)) if ipc >= COMPUTE_BOUND_IPC && mpki < MEMORY_BOUND_MPKI => {
+            Some(Boundness::Compute)
+        }
+        (Some(_), Some(_)) => Some(Boundness::Mixed),
+        _ => None,
+    };
+    counts
+}
+
+fn sum_counts<'a>(all: impl Iterator<Item = &'a PerfCounts>) -> PerfCounts {
+    let sum = all.fold(PerfCounts::default(), |acc, c| PerfCounts {
+        cycles: add(acc.cycles, c.cycles),
+        instructions: add(acc.instructions, c.instructions),
+        cache_misses: add(acc.cache_misses, c.cache_misses),
+        branch_misses: add(acc.branch_misses, c.branch_misses),
+        task_clock_ns: add(acc.task_clock_ns, c.task_clock_ns),
+        context_switches: add(acc.context_switches, c.context_switches),
+        ..Default::default()
+    });
+    derive(sum)
+}
+
+fn collect_counts(counters: &mut [Counter]) -> PerfCounts {
+    let mut counts = PerfCounts::default();
+    for counter in counters.iter_mut() {
+        let value = read_delta(counter);
+        match counter.event {
+            PerfEvent::Cycles => counts.cycles = value,
+            PerfEvent::Instructions => counts.instructions = value,
+            PerfEvent::CacheMisses => counts.cache_misses = value,
+            PerfEvent::BranchMisses => counts.branch_misses = value,
+            PerfEvent::TaskClock => counts.task_clock_ns = value,
+            PerfEvent::ContextSwitches => counts.context_switches = value,
+        }
+    }
+    derive(counts)
+}
+
+fn build_report(
+    interval: Duration,
+    paranoid: Option<i32>,
+    mode: PerfMode,
+    errors: Vec<String>,
+    cores: Vec<CorePerf>,
+    topology: &[Vec<i32>],
+) -> PerfReport {
+    let clusters = topology
+        .iter()
+        .filter_map(|cpus| {
+            let members: Vec<&CorePerf> = cores.iter().filter(|c| cpus.contains(&c.cpu)).collect();
+            if members.is_empty() {
+                return None;
+            }
+            Some(ClusterPerf {
+                policy: *cpus.first()?,
+                cpus: cpus.clone(),
+                counts: sum_counts(members.iter().map(|c| &c.counts)),
+            })
+        })
+        .collect();
+
+    PerfReport {
+        interval_ms: interval.as_millis() as u64,
+        paranoid,
+        mode,
+        errors,
+        total: sum_counts(cores.iter().map(|c| &c.counts)),
+        cores,
+        clusters,
+    }
+}
+
+fn measure_at(cpu_root: &str, paranoid: Option<i32>, interval: Duration) -> PerfReport {
+    let topology = super::cluster_topology(cpu_root);
+    let mut cpus: Vec<i32> = topology.iter().flatten().copied().collect();
+    if cpus.is_empty() {
+        cpus = (0..16)
+            .take_while(|cpu| utils::file_exists(&format!("{}/cpu{}", cpu_root, cpu)))
+            .collect();
+    }
+
+    let mut errors = Vec::new();
+    let events: Vec<PerfEvent> = PerfEvent::HARDWARE
+        .iter()
+        .chain(&PerfEvent::SOFTWARE)
+        .copied()
+        .collect();
+    let mut opened = open_counters(&events, -1, &cpus, false, &mut errors);
+    if opened.is_empty() {
+        // System-wide counters need perf_event_paranoid <= 0; own-process
+        // software events stay open up to 2, which stock Android ships.
+        opened = open_counters(&PerfEvent::SOFTWARE, 0, &[-1], true, &mut errors);
+    }
+
+    let has_hardware = opened
+        .iter()
+        .flat_map(|(_, counters)| counters)
+        .any(|c| PerfEvent::HARDWARE.contains(&c.event));
+    let mode = if has_hardware {
+        PerfMode::Hardware
+    } else if !opened.is_empty() {
+        PerfMode::Software
+    } else {
+        PerfMode::Unavailable
+    };
+
+    if !opened.is_empty() {
+        std::thread::sleep(interval);
+    }
+
+    let cores = opened
+        .iter_mut()
+        .map(|(cpu, counters)| CorePerf {
+            cpu: *cpu,
+            counts: collect_counts(counters),
+        })
+        .collect();
+
+    build_report(interval, paranoid, mode, errors, cores, &topology)
+}
+
+/// System-wide counters on every online CPU over `interval`, blocking the
+/// caller for that long. Hardware events that the PMU or
+/// `perf_event_paranoid` refuse are dropped and the report falls back to
+/// software events, system-wide when allowed and for this process otherwise.
+pub fn measure_perf_counters(interval: Duration) -> PerfReport {
+    let paranoid = utils::read_sysfs_full(PARANOID_PATH).and_then(|v| v.trim().parse().ok());
+    measure_at(CPU_ROOT, paranoid, interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_counts() {
+        let counts = |cycles, instructions, cache_misses| PerfCounts {
+            cycles: Some(cycles),
+            instructions: Some(instructions),
+            cache_misses: Some(cache_misses),
+            ..Default::default()
+        };
+        let cores = vec![
+            CorePerf {
+                cpu: 0,
+                counts: derive(counts(1000, 500, 10)),
+            },
+            CorePerf {
+                cpu: 4,
+                counts: derive(counts(1000, 2000, 2)),
+            },
+        ];
+        assert_eq!(cores[0].counts.boundness, Some(Boundness::Memory));
+        assert_eq!(cores[0].counts.cache_mpki, Some(20.0));
+        assert_eq!(cores[1].counts.boundness, Some(Boundness::Compute));
+
+        let report = build_report(
+            Duration::from_millis(100),
+            Some(2),
+            PerfMode::Hardware,
+            Vec::new(),
+            cores,
+            &[vec![0, 1, 2, 3], vec![4, 5], vec![6]],
+        );
+        // policy6 had no counters open and is left out.
+        assert_eq!(report.clusters.len(), 2);
+        assert_eq!(report.clusters[1].counts.ipc, Some(2.0));
+        assert_eq!(report.total.instructions, Some(2500));
+        assert_eq!(report.total.ipc, Some(1.25));
+        assert_eq!(report.total.branch_mpki, None);
+    }
+
+    #[test]
+    fn test_software_counters() {
+        // Own-thread software events are allowed up to perf_event_paranoid 2;
+        // sandboxes that filter the syscall entirely are skipped.
+        let mut errors = Vec::new();
+        let mut opened = open_counters(&PerfEvent::SOFTWARE, 0, &[-1], true, &mut errors);
+        let Some((_, counters)) = opened.first_mut() else {
+            return;
+        };
+
+        let mut spin = 0u64;
+        for i in 0..2_000_000u64 {
+            spin = spin.wrapping_add(std::hint::black_box(i));
+        }
+        std::hint::black_box(spin);
+
+        let counts = collect_counts(counters);
+        assert!(counts.task_clock_ns.unwrap() > 0);
+        assert!(counts.context_switches.is_some());
+        assert_eq!(counts.ipc, None);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::devicetree::devicetree as dt;
+use crate::utils;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+struct SocEntry {
+    name: &'static str,
+    vendor: &'static str,
+    model: &'static str,
+    /// Lowercase identifiers seen in props, soc0 and DT (`sm8550`, `kalama`).
+    keys: &'static [&'static str],
+    soc_ids: &'static [u32],
+    process: &'static str,
+    layout: &'static str,
+}
+
+const fn soc(
+    name: &'static str,
+    vendor: &'static str,
+    model: &'static str,
+    keys: &'static [&'static str],
+    soc_ids: &'static [u32],
+    process: &'static str,
+    layout: &'static str,
+) -> SocEntry {
+    SocEntry {
+        name,
+        vendor,
+        model,
+        keys,
+        soc_ids,
+        process,
+        layout,
+    }
+}
+
+#[rustfmt::skip]
+static SOC_TABLE: &[SocEntry] = &[
+    soc("Snapdragon 8 Elite", "Qualcomm", "SM8750", &["sm8750", "sun"], &[618], "TSMC N3P", "2×Oryon Prime + 6×Oryon"),
+    soc("Snapdragon 8 Gen 3", "Qualcomm", "SM8650", &["sm8650", "pineapple"], &[557], "TSMC N4P", "1×X4 + 5×A720 + 2×A520"),
+    soc("Snapdragon 8s Gen 3", "Qualcomm", "SM8635", &["sm8635", "cliffs"], &[614], "TSMC N4P", "1×X4 + 4×A720 + 3×A520"),
+    soc("Snapdragon 8 Gen 2", "Qualcomm", "SM8550", &["sm8550", "kalama"], &[519, 536], "TSMC N4", "1×X3 + 2×A715 + 2×A710 + 3×A510"),
+    soc("Snapdragon 8+ Gen 1", "Qualcomm", "SM8475", &["sm8475", "cape"], &[530], "TSMC N4", "1×X2 + 3×A710 + 4×A510"),
+    soc("Snapdragon 8 Gen 1", "Qualcomm", "SM8450", &["sm8450", "taro"], &[457], "Samsung 4LPX", "1×X2 + 3×A710 + 4×A510"),
+    soc("Snapdragon 888", "Qualcomm", "SM8350", &["sm8350", "lahaina"], &[415], "Samsung 5LPE", "1×X1 + 3×A78 + 4×A55"),
+    soc("Snapdragon 865", "Qualcomm", "SM8250", &["sm8250", "kona"], &[356], "TSMC N7P", "4×A77 + 4×A55"),
+    soc("Snapdragon 855", "Qualcomm", "SM8150", &["sm8150", "msmnile"], &[339], "TSMC N7", "4×A76 + 4×A55"),
+    soc("Snapdragon 845", "Qualcomm", "SDM845", &["sdm845"], &[321], "Samsung 10LPP", "4×A75 + 4×A55"),
+    soc("Snapdragon 7+ Gen 2", "Qualcomm", "SM7475", &["sm7475"], &[], "TSMC N4", "1×X2 + 3×A710 + 4×A510"),
+    soc("Snapdragon 7 Gen 1", "Qualcomm", "SM7450", &["sm7450", "diwali"], &[], "Samsung 4LPE", "4×A710 + 4×A510"),
+    soc("Snapdragon 778G", "Qualcomm", "SM7325", &["sm7325", "yupik"], &[], "TSMC N6", "4×A78 + 4×A55"),
+    soc("Snapdragon 765G", "Qualcomm", "SM7250", &["sm7250", "lito"], &[], "Samsung 7LPP", "2×A76 + 6×A55"),
+    soc("Snapdragon 695", "Qualcomm", "SM6375", &["sm6375", "holi"], &[], "TSMC N6", "2×A78 + 6×A55"),
+    soc("Snapdragon 662", "Qualcomm", "SM6115", &["sm6115", "bengal"], &[], "Samsung 11LPP", "4×A73 + 4×A53"),
+    soc("Snapdragon 660", "Qualcomm", "SDM660", &["sdm660"], &[], "Samsung 14LPP", "4×A73 + 4×A53"),
+    soc("Snapdragon 625", "Qualcomm", "MSM8953", &["msm8953"], &[], "Samsung 14LPP", "8×A53"),
+    soc("Dimensity 9400", "MediaTek", "MT6991", &["mt6991"], &[], "TSMC N3E", "1×X925 + 3×X4 + 4×A720"),
+    soc("Dimensity 9300", "MediaTek", "MT6989", &["mt6989"], &[], "TSMC N4P", "4×X4 + 4×A720"),
+    soc("Dimensity 9200", "MediaTek", "MT6985", &["mt6985"], &[], "TSMC N4P", "1×X3 + 3×A715 + 4×A510"),
+    soc("Dimensity 9000", "MediaTek", "MT6983", &["mt6983"], &[], "TSMC N4", "1×X2 + 3×A710 + 4×A510"),
+    soc("Dimensity 8300", "MediaTek", "MT6897", &["mt6897"], &[], "TSMC N4", "4×A715 + 4×A510"),
+    soc("Dimensity 8200", "MediaTek", "MT6896", &["mt6896"], &[], "TSMC N4", "4×A78 + 4×A55"),
+    soc("Dimensity 8100", "MediaTek", "MT6895", &["mt6895"], &[], "TSMC N5", "4×A78 + 4×A55"),
+    soc("Dimensity 1200", "MediaTek", "MT6893", &["mt6893"], &[], "TSMC N6", "4×A78 + 4×A55"),
+    soc("Dimensity 1080", "MediaTek", "MT6877", &["mt6877"], &[], "TSMC N6", "2×A78 + 6×A55"),
+    soc("Dimensity 700", "MediaTek", "MT6833", &["mt6833"], &[], "TSMC N7", "2×A76 + 6×A55"),
+    soc("Helio G95", "MediaTek", "MT6785", &["mt6785"], &[], "TSMC 12FFC", "2×A76 + 6×A55"),
+    soc("Helio G85", "MediaTek", "MT6769", &["mt6769"], &[], "TSMC 12FFC", "2×A75 + 6×A55"),
+    soc("Tensor G4", "Google", "GS401", &["zumapro", "gs401"], &[], "Samsung 4LPP", "1×X4 + 3×A720 + 4×A520"),
+    soc("Tensor G3", "Google", "GS301", &["zuma", "gs301"], &[], "Samsung 4LPP", "1×X3 + 4×A715 + 4×A510"),
+    soc("Tensor G2", "Google", "GS201", &["gs201"], &[], "Samsung 5LPE", "2×X1 + 2×A78 + 4×A55"),
+    soc("Tensor", "Google", "GS101", &["gs101"], &[], "Samsung 5LPE", "2×X1 + 2×A76 + 4×A55"),
+    soc("Exynos 2400", "Samsung", "S5E9945", &["s5e9945", "exynos2400"], &[], "Samsung 4LPP+", "1×X4 + 5×A720 + 4×A520"),
+    soc("Exynos 2200", "Samsung", "S5E9925", &["s5e9925", "exynos2200"], &[], "Samsung 4LPE", "1×X2 + 3×A710 + 4×A510"),
+    soc("Exynos 2100", "Samsung", "S5E9840", &["s5e9840", "exynos2100"], &[], "Samsung 5LPE", "1×X1 + 3×A78 + 4×A55"),
+    soc("Exynos 990", "Samsung", "Exynos990", &["exynos990"], &[], "Samsung 7LPP", "2×M5 + 2×A76 + 4×A55"),
+    soc("Exynos 1480", "Samsung", "S5E8845", &["s5e8845", "exynos1480"], &[], "Samsung 4LPP", "4×A78 + 4×A55"),
+    soc("Exynos 1380", "Samsung", "S5E8835", &["s5e8835", "exynos1380"], &[], "Samsung 5LPE", "4×A78 + 4×A55"),
+    soc("Exynos 1280", "Samsung", "S5E8825", &["s5e8825", "exynos1280"], &[], "Samsung 5LPE", "2×A78 + 6×A55"),
+];
+
+/// `(implementer, part)` from MIDR to core name.
+#[rustfmt::skip]
+static CORE_PARTS: &[(u32, u32, &str)] = &[
+    (0x41, 0xd03, "A53"), (0x41, 0xd04, "A35"), (0x41, 0xd05, "A55"),
+    (0x41, 0xd08, "A72"), (0x41, 0xd09, "A73"), (0x41, 0xd0a, "A75"),
+    (0x41, 0xd0b, "A76"), (0x41, 0xd0d, "A77"), (0x41, 0xd41, "A78"),
+    (0x41, 0xd44, "X1"), (0x41, 0xd46, "A510"), (0x41, 0xd47, "A710"),
+    (0x41, 0xd48, "X2"), (0x41, 0xd4b, "A78C"), (0x41, 0xd4d, "A715"),
+    (0x41, 0xd4e, "X3"), (0x41, 0xd80, "A520"), (0x41, 0xd81, "A720"),
+    (0x41, 0xd82, "X4"), (0x41, 0xd85, "X925"), (0x41, 0xd87, "A725"),
+    (0x51, 0x001, "Oryon"), (0x51, 0x800, "Kryo Gold"), (0x51, 0x801, "Kryo Silver"),
+    (0x51, 0x802, "Kryo Gold"), (0x51, 0x803, "Kryo Silver"), (0x51, 0x804, "Kryo Gold"),
+    (0x51, 0x805, "Kryo Silver"),
+    (0x53, 0x001, "M1"), (0x53, 0x002, "M3"), (0x53, 0x003, "M4"), (0x53, 0x004, "M5"),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SocSources {
+    pub soc_manufacturer: Option<String>,
+    pub soc_model: Option<String>,
+    pub board_platform: Option<String>,
+    pub soc0_machine: Option<String>,
+    pub soc0_soc_id: Option<String>,
+    pub soc0_family: Option<String>,
+    pub soc0_revision: Option<String>,
+    pub dt_compatible: Vec<String>,
+    pub cpuinfo_hardware: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreType {
+    pub cpu: i32,
+    pub implementer: u32,
+    pub part: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocIdentity {
+    pub name: Option<String>,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub process_node: Option<String>,
+    /// Layout from the table, or derived from the core parts when unknown.
+    pub core_layout: String,
+    pub detected_layout: String,
+    /// Which source produced the match, e.g. `ro.soc.model`.
+    pub matched_by: Option<String>,
+    pub cores: Vec<CoreType>,
+    pub sources: SocSources,
+}
+
+static SOC_IDENTITY: OnceCell<SocIdentity> = OnceCell::new();
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn read_sources(cpuinfo: &str) -> SocSources {
+    let soc0 = |name: &str| non_empty(utils::read_sysfs(&format!("/sys/devices/soc0/{}", name)));
+
+    SocSources {
+        soc_manufacturer: non_empty(utils::get_system_property("ro.soc.manufacturer")),
+        soc_model: non_empty(utils::get_system_property("ro.soc.model")),
+        board_platform: non_empty(utils::get_system_property("ro.board.platform")),
+        soc0_machine: soc0("machine"),
+        soc0_soc_id: soc0("soc_id"),
+        soc0_family: soc0("family"),
+        soc0_revision: soc0("revision"),
+        dt_compatible: dt::dt_root()
+            .map(|root| dt::read_strings(root, "compatible"))
+            .unwrap_or_default(),
+        cpuinfo_hardware: cpuinfo
+            .lines()
+            .find(|line| line.starts_with("Hardware"))
+            .and_then(|line| line.split(':').nth(1))
+            .map(|v| v.trim().to_string()),
+    }
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+}
+
+fn core_name(implementer: u32, part: u32) -> String {
+    CORE_PARTS
+        .iter()
+        .find(|(imp, p, _)| *imp == implementer && *p == part)
+        .map(|(_, _, name)| name.to_string())
+        .unwrap_or_else(|| format!("{:#x}:{:#x}", implementer, part))
+}
+
+/// Per-core MIDR fields from `/proc/cpuinfo` (`CPU implementer`, `CPU part`).
+fn parse_core_types(cpuinfo: &str) -> Vec<CoreType> {
+    let mut cores = Vec::new();
+    let mut cpu = None;
+    let mut implementer = None;
+
+    for line in cpuinfo.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        match key.trim() {
+            "processor" => {
+                cpu = value.trim().parse::<i32>().ok();
+                implementer = None;
+            }
+            "CPU implementer" => implementer = parse_hex(value),
+            "CPU part" => {
+                if let (Some(cpu), Some(implementer), Some(part)) =
+                    (cpu, implementer, parse_hex(value))
+                {
+                    cores.push(CoreType {
+                        cpu,
+                        implementer,
+                        part,
+                        name: core_name(implementer, part),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    cores
+}
+
+/// `1×X3 + 2×A715 + ...`, biggest cores first. Adjacent CPUs of the same
+/// type are merged, so clusters split only by frequency count once.
+fn layout_from_cores(cores: &[CoreType]) -> String {
+    let mut groups: Vec<(&str, usize)> = Vec::new();
+
+    for core in cores.iter().rev() {
+        match groups.last_mut() {
+            Some((name, count)) if *name == core.name => *count += 1,
+            _ => groups.push((&core.name, 1)),
+        }
+    }
+
+    groups
+        .iter()
+        .map(|(name, count)| format!("{}×{}", count, name))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Identifier-like tokens from a free-form string: `Qualcomm Technologies,
+/// Inc SM8550` yields `sm8550`, `qcom,sm8550-mtp` yields `sm8550`.
+fn candidate_keys(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '-' || c == '_')
+        .map(|t| t.to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn find_by_key(value: &str) -> Option<&'static SocEntry> {
+    let keys = candidate_keys(value);
+    let compact = value.to_lowercase().replace(' ', "");
+
+    SOC_TABLE.iter().find(|entry| {
+        entry
+            .keys
+            .iter()
+            .any(|k| *k == compact || keys.iter().any(|c| c == k))
+    })
+}
+
+fn identify(sources: SocSources, cores: Vec<CoreType>) -> SocIdentity {
+    let mut candidates: Vec<(&str, String)> = Vec::new();
+    let labelled = [
+        ("ro.soc.model", &sources.soc_model),
+        ("soc0/machine", &sources.soc0_machine),
+        ("ro.board.platform", &sources.board_platform),
+    ];
+    for (label, value) in labelled {
+        if let Some(value) = value {
+            candidates.push((label, value.clone()));
+        }
+    }
+    for compatible in &sources.dt_compatible {
+        candidates.push(("dt compatible", compatible.clone()));
+    }
+    if let Some(hardware) = &sources.cpuinfo_hardware {
+        candidates.push(("cpuinfo Hardware", hardware.clone()));
+    }
+
+    let mut matched = candidates
+        .iter()
+        .find_map(|(label, value)| Some((find_by_key(value)?, label.to_string())));
+
+    if matched.is_none()
+        && let Some(id) = sources
+            .soc0_soc_id
+            .as_deref()
+            .and_then(|v| v.parse::<u32>().ok())
+    {
+        matched = SOC_TABLE
+            .iter()
+            .find(|e| e.soc_ids.contains(&id))
+            .map(|e| (e, "soc0/soc_id".to_string()));
+    }
+
+    let detected_layout = layout_from_cores(&cores);
+    let entry = matched.as_ref().map(|(e, _)| *e);
+
+    SocIdentity {
+        name: entry.map(|e| e.name.to_string()),
+        vendor: entry
+            .map(|e| e.vendor.to_string())
+            .or_else(|| sources.soc_manufacturer.clone()),
+        model: entry
+            .map(|e| e.model.to_string())
+            .or_else(|| sources.soc_model.clone()),
+        process_node: entry.map(|e| e.process.to_string()),
+        core_layout: entry
+            .map(|e| e.layout.to_string())
+            .unwrap_or_else(|| detected_layout.clone()),
+        detected_layout,
+        matched_by: matched.map(|(_, label)| label),
+        cores,
+        sources,
+    }
+}
+
+pub fn get_soc_identity() -> SocIdentity {
+    SOC_IDENTITY
+        .get_or_init(|| {
+            let cpuinfo = utils::read_sysfs_full("/proc/cpuinfo").unwrap_or_default();
+            identify(read_sources(&cpuinfo), parse_core_types(&cpuinfo))
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPUINFO: &str = "\
+processor\t: 0
+CPU implementer\t: 0x41
+CPU part\t: 0xd46
+
+processor\t: 1
+CPU implementer\t: 0x41
+CPU part\t: 0xd46
+
+processor\t: 2
+CPU implementer\t: 0x41
+CPU part\t: 0xd47
+
+processor\t: 3
+CPU implementer\t: 0x41
+CPU part\t: 0xd4d
+
+processor\t: 4
+CPU implementer\t: 0x41
+CPU part\t: 0xd4e
+
+Hardware\t: Qualcomm Technologies, Inc SM8550
+";
+
+    #[test]
+    fn test_detected_layout() {
+        let cores = parse_core_types(CPUINFO);
+        assert_eq!(cores.len(), 5);
+        assert_eq!(cores[4].name, "X3");
+        assert_eq!(layout_from_cores(&cores), "1×X3 + 1×A715 + 1×A710 + 2×A510");
+    }
+
+    #[test]
+    fn test_identify_sources() {
+        let by_platform = identify(
+            SocSources {
+                board_platform: Some("kalama".to_string()),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        assert_eq!(by_platform.name.as_deref(), Some("Snapdragon 8 Gen 2"));
+        assert_eq!(by_platform.matched_by.as_deref(), Some("ro.board.platform"));
+        assert_eq!(by_platform.core_layout, "1×X3 + 2×A715 + 2×A710 + 3×A510");
+
+        let by_cpuinfo = identify(
+            SocSources {
+                cpuinfo_hardware: Some("Qualcomm Technologies, Inc SM8550".to_string()),
+                ..Default::default()
+            },
+            parse_core_types(CPUINFO),
+        );
+        assert_eq!(by_cpuinfo.model.as_deref(), Some("SM8550"));
+
+        let by_dt = identify(
+            SocSources {
+                dt_compatible: vec!["google,zuma-ripcurrent".into(), "google,gs301".into()],
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        assert_eq!(by_dt.name.as_deref(), Some("Tensor G3"));
+
+        let by_id = identify(
+            SocSources {
+                soc0_soc_id: Some("557".to_string()),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        assert_eq!(by_id.name.as_deref(), Some("Snapdragon 8 Gen 3"));
+
+        let unknown = identify(SocSources::default(), parse_core_types(CPUINFO));
+        assert_eq!(unknown.name, None);
+        assert_eq!(unknown.core_layout, unknown.detected_layout);
+    }
+}
@@ -0,0 +1,451 @@
+use crate::utils::{self, WriteResult};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevfreqKind {
+    Gpu,
+    Ddr,
+    Llcc,
+    Bus,
+    Other,
+}
+
+impl DevfreqKind {
+    /// Guessed from the device name: `3d00000.qcom,kgsl-3d0`,
+    /// `soc:qcom,cpu-llcc-ddr-bw`, `soc:qcom,cpu-cpu-llcc-bw`, `17000010.devfreq_mif`.
+    /// `soc:qcom,gpubw` votes for bus bandwidth, so it is not the GPU.
+    pub(crate) fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if ["kgsl-3d0", "mali", "pvrsrv"]
+            .iter()
+            .any(|key| name.contains(key))
+            || (name.contains("gpu") && !name.contains("bw"))
+        {
+            DevfreqKind::Gpu
+        } else if ["ddr", "dmc", "mif", "dram", "emc"]
+            .iter()
+            .any(|key| name.contains(key))
+        {
+            DevfreqKind::Ddr
+        } else if name.contains("llcc") || name.contains("l3") {
+            DevfreqKind::Llcc
+        } else if ["bw", "bus", "noc", "int"]
+            .iter()
+            .any(|key| name.contains(key))
+        {
+            DevfreqKind::Bus
+        } else {
+            DevfreqKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevfreqParam {
+    Governor,
+    MinFreq,
+    MaxFreq,
+}
+
+impl DevfreqParam {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    fn node(self) -> &'static str {
+        match self {
+            DevfreqParam::Governor => "governor",
+            DevfreqParam::MinFreq => "min_freq",
+            DevfreqParam::MaxFreq => "max_freq",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevfreqResidency {
+    pub freq: u64,
+    pub time_ms: u64,
+    pub percent: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevfreqTransStat {
+    /// The row `trans_stat` marks with `*`.
+    pub current: Option<u64>,
+    pub residency: Vec<DevfreqResidency>,
+    pub total_transitions: Option<u64>,
+}
+
+/// Frequencies are in the driver's own unit: Hz for GPUs, usually kHz or
+/// MB/s for bandwidth voters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevfreqDevice {
+    pub name: String,
+    pub path: String,
+    pub kind: DevfreqKind,
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+    pub cur_freq: Option<u64>,
+    pub min_freq: Option<u64>,
+    pub max_freq: Option<u64>,
+    pub target_freq: Option<u64>,
+    pub available_frequencies: Vec<u64>,
+    pub trans_stat: Option<DevfreqTransStat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevfreqWrite {
+    pub device: String,
+    pub param: DevfreqParam,
+    pub requested: String,
+    pub result: WriteResult,
+    /// The frequency was snapped to the table or the kernel kept a different
+    /// value.
+    pub clamped: bool,
+}
+
+static GPU_DEVFREQ: OnceCell<Option<String>> = OnceCell::new();
+
+fn class_dir(sys: &str) -> String {
+    format!("{}/class/devfreq", sys)
+}
+
+fn read_list(dir: &str, node: &str) -> Vec<String> {
+    utils::read_sysfs_full(&format!("{}/{}", dir, node))
+        .map(|v| v.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn read_frequencies(dir: &str) -> Vec<u64> {
+    let mut freqs: Vec<u64> = read_list(dir, "available_frequencies")
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    freqs.sort_unstable();
+    freqs.dedup();
+    freqs
+}
+
+/// ```text
+///      From  :   To
+///            :  257000000  585000000   time(ms)
+/// * 257000000:         0         12     48210
+///   585000000:        11          0      1820
+/// Total transition : 23
+/// ```
+pub(crate) fn parse_trans_stat(text: &str) -> Option<DevfreqTransStat> {
+    let mut current = None;
+    let mut rows = Vec::new();
+    let mut total_transitions = None;
+
+    for line in text.lines() {
+        if let Some(total) = line.trim().strip_prefix("Total transition") {
+            total_transitions = total.trim_start_matches([' ', ':']).trim().parse().ok();
+            continue;
+        }
+        let Some((left, right)) = line.split_once(':') else {
+            continue;
+        };
+        let marked = left.trim_start().starts_with('*');
+        let Ok(freq) = left.trim().trim_start_matches('*').trim().parse::<u64>() else {
+            continue;
+        };
+        let Some(time_ms) = right
+            .split_whitespace()
+            .last()
+            .and_then(|v| v.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        if marked {
+            current = Some(freq);
+        }
+        rows.push((freq, time_ms));
+    }
+
+    if rows.is_empty() {
+        return None;
+    }
+    let total: u64 = rows.iter().map(|(_, time)| time).sum();
+    Some(DevfreqTransStat {
+        current,
+        residency: rows
+            .into_iter()
+            .map(|(freq, time_ms)| DevfreqResidency {
+                freq,
+                time_ms,
+                percent: if total > 0 {
+                    time_ms as f32 * 100.0 / total as f32
+                } else {
+                    0.0
+                },
+            })
+            .collect(),
+        total_transitions,
+    })
+}
+
+fn read_devfreq_dir(dir: &str) -> Option<DevfreqDevice> {
+    if !utils::file_exists(&format!("{}/governor", dir))
+        && !utils::file_exists(&format!("{}/cur_freq", dir))
+    {
+        return None;
+    }
+    let name = dir.rsplit('/').next().unwrap_or(dir).to_string();
+
+    Some(DevfreqDevice {
+        kind: DevfreqKind::from_name(&name),
+        path: dir.to_string(),
+        governor: utils::read_sysfs_full(&format!("{}/governor", dir)),
+        available_governors: read_list(dir, "available_governors"),
+        cur_freq: utils::read_sysfs_num::<u64>(&format!("{}/cur_freq", dir)),
+        min_freq: utils::read_sysfs_num::<u64>(&format!("{}/min_freq", dir)),
+        max_freq: utils::read_sysfs_num::<u64>(&format!("{}/max_freq", dir)),
+        target_freq: utils::read_sysfs_num::<u64>(&format!("{}/target_freq", dir)),
+        available_frequencies: read_frequencies(dir),
+        trans_stat: utils::read_sysfs_full(&format!("{}/trans_stat", dir))
+            .and_then(|text| parse_trans_stat(&text)),
+        name,
+    })
+}
+
+fn list_devfreq_at(sys: &str) -> Vec<DevfreqDevice> {
+    let class = class_dir(sys);
+    utils::list_dir(&class)
+        .into_iter()
+        .filter_map(|name| read_devfreq_dir(&format!("{}/{}", class, name)))
+        .collect()
+}
+
+/// First `/sys/class/devfreq` entry whose name matches.
+pub(crate) fn find_devfreq_at(sys: &str, matches: impl Fn(&str) -> bool) -> Option<String> {
+    let class = class_dir(sys);
+    utils::list_dir(&class)
+        .into_iter()
+        .find(|name| matches(name))
+        .map(|name| format!("{}/{}", class, name))
+}
+
+/// The GPU's devfreq device, falling back to the link kgsl keeps under its
+/// own device.
+fn find_gpu_devfreq_at(sys: &str) -> Option<String> {
+    find_devfreq_at(sys, |name| DevfreqKind::from_name(name) == DevfreqKind::Gpu).or_else(|| {
+        let kgsl = format!("{}/class/kgsl/kgsl-3d0/devfreq", sys);
+        utils::file_exists(&kgsl).then_some(kgsl)
+    })
+}
+
+fn device_dir_at(sys: &str, name: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains('/') || name == ".." {
+        return Err(format!("Invalid devfreq device: {}", name));
+    }
+    let dir = format!("{}/{}", class_dir(sys), name);
+    if !utils::file_exists(&dir) {
+        return Err(format!("devfreq device {} not found", name));
+    }
+    Ok(dir)
+}
+
+/// Caps snap to the highest table entry not above the request, floors to
+/// the lowest not below it.
+fn snap(freqs: &[u64], value: u64, cap: bool) -> u64 {
+    let pick = if cap {
+        freqs.iter().rev().find(|f| **f <= value).or(freqs.first())
+    } else {
+        freqs.iter().find(|f| **f >= value).or(freqs.last())
+    };
+    pick.copied().unwrap_or(value)
+}
+
+fn set_param_at(
+    sys: &str,
+    name: &str,
+    param: DevfreqParam,
+    value: &str,
+) -> Result<DevfreqWrite, String> {
+    let dir = device_dir_at(sys, name)?;
+    let value = value.trim();
+    let path = format!("{}/{}", dir, param.node());
+    if !utils::file_exists(&path) {
+        return Err(format!("{} has no {} node", name, param.node()));
+    }
+
+    let written = match param {
+        DevfreqParam::Governor => {
+            let available = read_list(&dir, "available_governors");
+            if !available.is_empty() && !available.iter().any(|g| g == value) {
+                return Err(format!(
+                    "Governor {} is not one of: {}",
+                    value,
+                    available.join(" ")
+                ));
+            }
+            value.to_string()
+        }
+        DevfreqParam::MinFreq | DevfreqParam::MaxFreq => {
+            let freq: u64 = value
+                .parse()
+                .map_err(|_| format!("Invalid frequency: {}", value))?;
+            snap(
+                &read_frequencies(&dir),
+                freq,
+                param == DevfreqParam::MaxFreq,
+            )
+            .to_string()
+        }
+    };
+
+    let result = utils::write_sysfs_verified(&path, &written);
+    Ok(DevfreqWrite {
+        device: name.to_string(),
+        param,
+        requested: value.to_string(),
+        clamped: written != value || result.actual != written,
+        result,
+    })
+}
+
+/// The side that moves away from the current range is written first so the
+/// kernel never sees min above max.
+fn set_range_at(sys: &str, name: &str, min: u64, max: u64) -> Result<Vec<DevfreqWrite>, String> {
+    if min > max {
+        return Err(format!("Min {} is above max {}", min, max));
+    }
+    let dir = device_dir_at(sys, name)?;
+    let raising = utils::read_sysfs_num::<u64>(&format!("{}/max_freq", &dir))
+        .is_some_and(|current| min > current);
+
+    let mut order = vec![(DevfreqParam::MinFreq, min), (DevfreqParam::MaxFreq, max)];
+    if raising {
+        order.reverse();
+    }
+    order
+        .into_iter()
+        .map(|(param, freq)| set_param_at(sys, name, param, &freq.to_string()))
+        .collect()
+}
+
+pub fn list_devfreq() -> Vec<DevfreqDevice> {
+    list_devfreq_at(SYS_ROOT)
+}
+
+/// Path of the GPU's devfreq device, looked up once.
+pub fn gpu_devfreq_dir() -> Option<&'static str> {
+    GPU_DEVFREQ
+        .get_or_init(|| find_gpu_devfreq_at(SYS_ROOT))
+        .as_deref()
+}
+
+/// Writes the governor or a frequency limit and reads it back. Limits take
+/// the device's own unit and snap to `available_frequencies`.
+pub fn set_devfreq_param(
+    name: &str,
+    param: DevfreqParam,
+    value: &str,
+) -> Result<DevfreqWrite, String> {
+    set_param_at(SYS_ROOT, name, param, value)
+}
+
+pub fn set_devfreq_range(name: &str, min: u64, max: u64) -> Result<Vec<DevfreqWrite>, String> {
+    set_range_at(SYS_ROOT, name, min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    const GPU: &str = "class/devfreq/3d00000.qcom,kgsl-3d0";
+    const LLCC: &str = "class/devfreq/soc:qcom,cpu-cpu-llcc-bw";
+
+    fn build(name: &str) -> String {
+        let gpu = |node: &str| format!("{}/{}", GPU, node);
+        let llcc = |node: &str| format!("{}/{}", LLCC, node);
+        let files = [
+            (gpu("governor"), "msm-adreno-tz"),
+            (
+                gpu("available_governors"),
+                "msm-adreno-tz performance powersave",
+            ),
+            (gpu("cur_freq"), "257000000"),
+            (gpu("min_freq"), "257000000"),
+            (gpu("max_freq"), "585000000"),
+            (gpu("target_freq"), "257000000"),
+            (
+                gpu("available_frequencies"),
+                "585000000 427000000 257000000",
+            ),
+            (
+                gpu("trans_stat"),
+                "     From  :   To\n\
+                           :  257000000  427000000  585000000   time(ms)\n\
+                 * 257000000:         0         7         5     48000\n\
+                  427000000:         6         0         2      8000\n\
+                  585000000:         6         1         0     24000\n\
+                 Total transition : 27\n",
+            ),
+            (llcc("governor"), "bw_hwmon"),
+            (
+                llcc("available_governors"),
+                "bw_hwmon performance powersave",
+            ),
+            (llcc("cur_freq"), "2288"),
+            (llcc("min_freq"), "762"),
+            (llcc("max_freq"), "9155"),
+            (llcc("available_frequencies"), "762 1144 2288 4577 9155"),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        fixture::build(name, &files)
+    }
+
+    #[test]
+    fn test_list_devfreq() {
+        let sys = build("devfreq_list");
+        let devices = list_devfreq_at(&sys);
+        assert_eq!(devices.len(), 2);
+
+        let gpu = devices.iter().find(|d| d.kind == DevfreqKind::Gpu).unwrap();
+        assert_eq!(
+            gpu.available_frequencies,
+            vec![257000000, 427000000, 585000000]
+        );
+        assert_eq!(gpu.available_governors.len(), 3);
+        let stat = gpu.trans_stat.as_ref().unwrap();
+        assert_eq!(stat.current, Some(257000000));
+        assert_eq!(stat.total_transitions, Some(27));
+        assert_eq!(stat.residency[0].time_ms, 48000);
+        assert!((stat.residency[0].percent - 60.0).abs() < 0.01);
+
+        let llcc = devices
+            .iter()
+            .find(|d| d.kind == DevfreqKind::Llcc)
+            .unwrap();
+        assert_eq!(llcc.governor.as_deref(), Some("bw_hwmon"));
+        assert!(llcc.trans_stat.is_none());
+
+        assert_eq!(find_gpu_devfreq_at(&sys), Some(format!("{}/{}", sys, GPU)));
+    }
+
+    #[test]
+    fn test_set_devfreq() {
+        let sys = build("devfreq_set");
+        let name = "soc:qcom,cpu-cpu-llcc-bw";
+
+        let write = set_param_at(&sys, name, DevfreqParam::MaxFreq, "5000").unwrap();
+        assert_eq!(write.result.actual, "4577");
+        assert!(write.clamped);
+
+        assert!(set_param_at(&sys, name, DevfreqParam::Governor, "ondemand").is_err());
+        assert!(set_param_at(&sys, "../kgsl", DevfreqParam::Governor, "x").is_err());
+
+        // Raising above the current max writes max first.
+        let writes = set_range_at(&sys, name, 9155, 9155).unwrap();
+        assert_eq!(writes[0].param, DevfreqParam::MaxFreq);
+        assert!(writes.iter().all(|w| w.result.success && !w.clamped));
+    }
+}
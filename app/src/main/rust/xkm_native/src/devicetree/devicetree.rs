@@ -0,0 +1,93 @@
+use crate::utils;
+use std::collections::HashMap;
+
+const DT_ROOTS: [&str; 2] = ["/proc/device-tree", "/sys/firmware/devicetree/base"];
+
+pub fn dt_root() -> Option<&'static str> {
+    DT_ROOTS.into_iter().find(|p| utils::file_exists(p))
+}
+
+pub fn read_property(node: &str, name: &str) -> Option<Vec<u8>> {
+    std::fs::read(format!("{}/{}", node, name)).ok()
+}
+
+pub fn has_property(node: &str, name: &str) -> bool {
+    utils::file_exists(&format!("{}/{}", node, name))
+}
+
+/// Properties are stored as big-endian 32-bit cells; trailing bytes that do
+/// not form a full cell are ignored.
+pub fn read_cells(node: &str, name: &str) -> Vec<u32> {
+    read_property(node, name)
+        .map(|bytes| {
+            bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 64-bit values (such as `opp-hz`) are encoded as two cells, high first.
+pub fn read_u64_cells(node: &str, name: &str) -> Vec<u64> {
+    read_cells(node, name)
+        .chunks_exact(2)
+        .map(|c| ((c[0] as u64) << 32) | c[1] as u64)
+        .collect()
+}
+
+/// String lists (`compatible`, `clock-names`) are NUL-separated.
+pub fn read_strings(node: &str, name: &str) -> Vec<String> {
+    read_property(node, name)
+        .map(|bytes| {
+            bytes
+                .split(|&b| b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn read_phandle(node: &str) -> Option<u32> {
+    let cells = read_cells(node, "phandle");
+    if !cells.is_empty() {
+        return cells.first().copied();
+    }
+    read_cells(node, "linux,phandle").first().copied()
+}
+
+/// Every node below `root`, depth first. Nodes are directories; properties
+/// are plain files.
+pub fn walk_nodes(root: &str) -> Vec<String> {
+    let mut nodes = Vec::new();
+    let mut pending = vec![root.to_string()];
+
+    while let Some(node) = pending.pop() {
+        if let Ok(entries) = std::fs::read_dir(&node) {
+            let mut children: Vec<String> = entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                .map(|e| e.path().to_string_lossy().into_owned())
+                .collect();
+            children.sort();
+            pending.extend(children.into_iter().rev());
+        }
+        nodes.push(node);
+    }
+
+    nodes
+}
+
+pub fn phandle_index(nodes: &[String]) -> HashMap<u32, String> {
+    nodes
+        .iter()
+        .filter_map(|node| Some((read_phandle(node)?, node.clone())))
+        .collect()
+}
+
+pub fn is_compatible(node: &str, prefix: &str) -> bool {
+    read_strings(node, "compatible")
+        .iter()
+        .any(|c| c.starts_with(prefix))
+}
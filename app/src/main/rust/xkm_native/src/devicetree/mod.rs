@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod devicetree;
+pub mod opp;
+pub use opp::*;
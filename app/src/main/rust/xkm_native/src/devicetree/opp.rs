@@ -0,0 +1,373 @@
+use super::devicetree as dt;
+use crate::utils;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SYS_ROOT: &str = "/sys";
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+/// `compatible` prefixes of GPU nodes whose `operating-points-v2` table is
+/// reported as the GPU table.
+const GPU_COMPATIBLE: [&str; 6] = [
+    "qcom,adreno",
+    "qcom,kgsl-3d",
+    "arm,mali",
+    "arm,malit",
+    "samsung,xclipse",
+    "img,powervr",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OppTarget {
+    Cpu,
+    Gpu,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OppEntry {
+    pub freq_hz: u64,
+    /// Either a single target voltage or `<target min max>`, per supply.
+    pub microvolt: Vec<u32>,
+    pub supported_hw: Vec<u32>,
+    pub turbo: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OppTable {
+    pub node: String,
+    pub phandle: u32,
+    pub shared: bool,
+    pub target: OppTarget,
+    pub consumers: Vec<String>,
+    pub cpus: Vec<i32>,
+    /// cpufreq policy of the cluster (`related_cpus`) the CPUs belong to.
+    pub policy: Option<i32>,
+    /// GPU tables only: sysfs directory of the kgsl, Mali or devfreq device
+    /// whose `of_node` is the consumer.
+    pub device: Option<String>,
+    pub entries: Vec<OppEntry>,
+}
+
+static OPP_TABLES: OnceCell<Vec<OppTable>> = OnceCell::new();
+
+fn read_entries(table: &str) -> Vec<OppEntry> {
+    let mut entries: Vec<OppEntry> = dt::walk_nodes(table)
+        .iter()
+        .skip(1)
+        .filter_map(|node| {
+            let freq_hz = *dt::read_u64_cells(node, "opp-hz").first()?;
+            Some(OppEntry {
+                freq_hz,
+                microvolt: dt::read_cells(node, "opp-microvolt"),
+                supported_hw: dt::read_cells(node, "opp-supported-hw"),
+                turbo: dt::has_property(node, "turbo-mode"),
+            })
+        })
+        .collect();
+
+    entries.sort_by_key(|e| e.freq_hz);
+    entries
+}
+
+/// Device-tree node of each logical CPU. `cpuN/of_node` is authoritative;
+/// without it the `/cpus/cpu@` nodes are taken in `reg` order.
+pub(crate) fn cpu_nodes(dt_root: &str, cpu_root: &str) -> Vec<(i32, String)> {
+    let canonical_root = std::fs::canonicalize(dt_root).ok();
+    let mut mapped = Vec::new();
+
+    for cpu in 0..16 {
+        if !utils::file_exists(&format!("{}/cpu{}", cpu_root, cpu)) {
+            break;
+        }
+
+        let of_node = std::fs::canonicalize(format!("{}/cpu{}/of_node", cpu_root, cpu)).ok();
+        let relative = of_node.as_ref().and_then(|node| {
+            node.strip_prefix(canonical_root.as_ref()?)
+                .ok()
+                .map(|rel| format!("{}/{}", dt_root, rel.to_string_lossy()))
+        });
+
+        if let Some(node) = relative {
+            mapped.push((cpu, node));
+        }
+    }
+
+    if !mapped.is_empty() {
+        return mapped;
+    }
+
+    let mut nodes: Vec<String> = utils::list_dir(&format!("{}/cpus", dt_root))
+        .into_iter()
+        .filter(|name| name.starts_with("cpu@"))
+        .map(|name| format!("{}/cpus/{}", dt_root, name))
+        .collect();
+    nodes.sort_by_key(|node| dt::read_cells(node, "reg"));
+
+    nodes
+        .into_iter()
+        .enumerate()
+        .map(|(cpu, node)| (cpu as i32, node))
+        .collect()
+}
+
+pub(crate) fn read_opp_tables_at(dt_root: &str, cpu_root: &str) -> Vec<OppTable> {
+    let nodes = dt::walk_nodes(dt_root);
+    let phandles = dt::phandle_index(&nodes);
+
+    let mut consumers: HashMap<u32, Vec<String>> = HashMap::new();
+    for node in &nodes {
+        for phandle in dt::read_cells(node, "operating-points-v2") {
+            consumers.entry(phandle).or_default().push(node.clone());
+        }
+    }
+
+    let mut cpus_by_table: HashMap<u32, Vec<i32>> = HashMap::new();
+    for (cpu, node) in cpu_nodes(dt_root, cpu_root) {
+        if let Some(&phandle) = dt::read_cells(&node, "operating-points-v2").first() {
+            cpus_by_table.entry(phandle).or_default().push(cpu);
+        }
+    }
+
+    let topology = crate::cpu::cluster_topology(cpu_root);
+    let relative = |node: &str| node.strip_prefix(dt_root).unwrap_or(node).to_string();
+
+    let mut tables: Vec<OppTable> = phandles
+        .iter()
+        .filter(|(_, node)| dt::is_compatible(node, "operating-points-v2"))
+        .map(|(&phandle, node)| {
+            let users = consumers.remove(&phandle).unwrap_or_default();
+            let cpus = cpus_by_table.remove(&phandle).unwrap_or_default();
+
+            let target = if !cpus.is_empty() {
+                OppTarget::Cpu
+            } else if users
+                .iter()
+                .any(|u| GPU_COMPATIBLE.iter().any(|c| dt::is_compatible(u, c)))
+            {
+                OppTarget::Gpu
+            } else {
+                OppTarget::Other
+            };
+
+            let policy = topology
+                .iter()
+                .find(|cluster| cpus.iter().any(|cpu| cluster.contains(cpu)))
+                .and_then(|cluster| cluster.first().copied());
+
+            OppTable {
+                node: relative(node),
+                phandle,
+                shared: dt::has_property(node, "opp-shared"),
+                target,
+                consumers: users.iter().map(|u| relative(u)).collect(),
+                cpus,
+                policy,
+                device: None,
+                entries: read_entries(node),
+            }
+        })
+        .collect();
+
+    tables.sort_by(|a, b| a.node.cmp(&b.node));
+    tables
+}
+
+/// GPU devices that may consume a table, kgsl first.
+fn gpu_device_dirs(sys: &str) -> Vec<String> {
+    let mut dirs = vec![format!("{}/class/kgsl/kgsl-3d0", sys)];
+    for class in ["misc", "devfreq"] {
+        let root = format!("{}/class/{}", sys, class);
+        dirs.extend(
+            utils::list_dir(&root)
+                .into_iter()
+                .filter(|name| class == "devfreq" || name.starts_with("mali"))
+                .map(|name| format!("{}/{}", root, name)),
+        );
+    }
+    dirs.into_iter()
+        .filter(|dir| utils::file_exists(dir))
+        .collect()
+}
+
+/// Links each GPU table to the device whose `device/of_node` is one of its
+/// consumers. Without `of_node`, kgsl and Mali are matched by `compatible`.
+pub(crate) fn link_gpu_devices_at(tables: &mut [OppTable], dt_root: &str, sys: &str) {
+    let devices: Vec<(String, Option<std::path::PathBuf>)> = gpu_device_dirs(sys)
+        .into_iter()
+        .map(|dir| {
+            let of_node = std::fs::canonicalize(format!("{}/device/of_node", dir)).ok();
+            (dir, of_node)
+        })
+        .collect();
+
+    for table in tables.iter_mut().filter(|t| t.target == OppTarget::Gpu) {
+        let consumers: Vec<String> = table
+            .consumers
+            .iter()
+            .map(|c| format!("{}{}", dt_root, c))
+            .collect();
+        let by_of_node = devices.iter().find(|(_, of_node)| {
+            of_node.as_ref().is_some_and(|of_node| {
+                consumers
+                    .iter()
+                    .any(|c| std::fs::canonicalize(c).ok().as_ref() == Some(of_node))
+            })
+        });
+        let by_compatible = || {
+            devices.iter().find(|(dir, _)| {
+                let prefixes: &[&str] = if dir.contains("/class/kgsl/") {
+                    &["qcom,adreno", "qcom,kgsl-3d"]
+                } else if dir.contains("/class/misc/") {
+                    &["arm,mali"]
+                } else {
+                    &[]
+                };
+                consumers
+                    .iter()
+                    .any(|c| prefixes.iter().any(|p| dt::is_compatible(c, p)))
+            })
+        };
+        table.device = by_of_node
+            .or_else(by_compatible)
+            .map(|(dir, _)| dir.clone());
+    }
+}
+
+/// All `operating-points-v2` tables, with CPU tables mapped to their cpufreq
+/// policy and GPU tables linked to the kgsl/mali device that uses them.
+pub fn read_opp_tables() -> Vec<OppTable> {
+    OPP_TABLES
+        .get_or_init(|| match dt::dt_root() {
+            Some(root) => {
+                let mut tables = read_opp_tables_at(root, CPU_ROOT);
+                link_gpu_devices_at(&mut tables, root, SYS_ROOT);
+                tables
+            }
+            None => Vec::new(),
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn cells(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        format!("{}\0", value).into_bytes()
+    }
+
+    #[test]
+    fn test_read_opp_tables() {
+        let dt = fixture::build_bytes(
+            "opp_dt",
+            &[
+                ("dt/cpus/cpu@0/reg", cells(&[0, 0])),
+                ("dt/cpus/cpu@0/operating-points-v2", cells(&[0x10])),
+                ("dt/cpus/cpu@100/reg", cells(&[0, 0x100])),
+                ("dt/cpus/cpu@100/operating-points-v2", cells(&[0x10])),
+                ("dt/cpus/cpu@200/reg", cells(&[0, 0x200])),
+                ("dt/cpus/cpu@200/operating-points-v2", cells(&[0x20])),
+                (
+                    "dt/opp-table-cpu0/compatible",
+                    string("operating-points-v2"),
+                ),
+                ("dt/opp-table-cpu0/phandle", cells(&[0x10])),
+                ("dt/opp-table-cpu0/opp-shared", Vec::new()),
+                (
+                    "dt/opp-table-cpu0/opp-300000000/opp-hz",
+                    cells(&[0, 300_000_000]),
+                ),
+                (
+                    "dt/opp-table-cpu0/opp-300000000/opp-microvolt",
+                    cells(&[568_000]),
+                ),
+                (
+                    "dt/opp-table-cpu0/opp-1804800000/opp-hz",
+                    cells(&[0, 1_804_800_000]),
+                ),
+                (
+                    "dt/opp-table-cpu0/opp-1804800000/opp-microvolt",
+                    cells(&[824_000, 800_000, 900_000]),
+                ),
+                (
+                    "dt/opp-table-cpu2/compatible",
+                    string("operating-points-v2"),
+                ),
+                ("dt/opp-table-cpu2/phandle", cells(&[0x20])),
+                (
+                    "dt/opp-table-cpu2/opp-4400000000/opp-hz",
+                    cells(&[1, 105_032_704]),
+                ),
+                ("dt/opp-table-cpu2/opp-4400000000/turbo-mode", Vec::new()),
+                (
+                    "dt/opp-table-cpu2/opp-4400000000/opp-supported-hw",
+                    cells(&[0x3]),
+                ),
+                (
+                    "dt/soc/gpu@3d00000/compatible",
+                    string("qcom,adreno-43050a01"),
+                ),
+                ("dt/soc/gpu@3d00000/operating-points-v2", cells(&[0x30])),
+                (
+                    "dt/soc/gpu@3d00000/gpu-opp-table/compatible",
+                    string("operating-points-v2"),
+                ),
+                ("dt/soc/gpu@3d00000/gpu-opp-table/phandle", cells(&[0x30])),
+                (
+                    "dt/soc/gpu@3d00000/gpu-opp-table/opp-680000000/opp-hz",
+                    cells(&[0, 680_000_000]),
+                ),
+                ("cpu/cpufreq/policy0/related_cpus", b"0-1\n".to_vec()),
+                ("cpu/cpufreq/policy2/related_cpus", b"2\n".to_vec()),
+                (
+                    "sys/class/kgsl/kgsl-3d0/device/gpu_model",
+                    string("Adreno740v2"),
+                ),
+                ("cpu/cpu0/online", string("1")),
+                ("cpu/cpu1/online", string("1")),
+                ("cpu/cpu2/online", string("1")),
+            ],
+        );
+
+        std::os::unix::fs::symlink(
+            format!("{}/dt/soc/gpu@3d00000", dt),
+            format!("{}/sys/class/kgsl/kgsl-3d0/device/of_node", dt),
+        )
+        .unwrap();
+
+        let dt_root = format!("{}/dt", dt);
+        let mut tables = read_opp_tables_at(&dt_root, &format!("{}/cpu", dt));
+        link_gpu_devices_at(&mut tables, &dt_root, &format!("{}/sys", dt));
+        assert_eq!(tables.len(), 3);
+
+        let little = &tables[0];
+        assert_eq!(little.node, "/opp-table-cpu0");
+        assert!(little.shared);
+        assert_eq!(little.target, OppTarget::Cpu);
+        assert_eq!(little.cpus, vec![0, 1]);
+        assert_eq!(little.policy, Some(0));
+        assert_eq!(little.entries[0].freq_hz, 300_000_000);
+        assert_eq!(little.entries[1].microvolt, vec![824_000, 800_000, 900_000]);
+
+        let prime = &tables[1];
+        assert_eq!(prime.policy, Some(2));
+        assert_eq!(prime.entries[0].freq_hz, 4_400_000_000);
+        assert!(prime.entries[0].turbo);
+        assert_eq!(prime.entries[0].supported_hw, vec![0x3]);
+
+        let gpu = &tables[2];
+        assert_eq!(gpu.target, OppTarget::Gpu);
+        assert_eq!(gpu.consumers, vec!["/soc/gpu@3d00000".to_string()]);
+        assert_eq!(gpu.entries[0].freq_hz, 680_000_000);
+        assert_eq!(gpu.device, Some(format!("{}/sys/class/kgsl/kgsl-3d0", dt)));
+        assert_eq!(tables[0].device, None);
+    }
+}
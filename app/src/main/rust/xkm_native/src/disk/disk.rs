@@ -11,25 +11,35 @@ pub struct DiskStats {
     pub write_speed: u64,
 }
 
-struct DiskState {
+/// Sector counters from the previous read.
+pub struct DiskState {
     read_sectors: u64,
     write_sectors: u64,
     last_update: std::time::Instant,
 }
 
-static DISK_STATE: Lazy<Mutex<DiskState>> = Lazy::new(|| {
-    Mutex::new(DiskState {
-        read_sectors: 0,
-        write_sectors: 0,
-        last_update: std::time::Instant::now(),
-    })
-});
+impl Default for DiskState {
+    fn default() -> Self {
+        DiskState {
+            read_sectors: 0,
+            write_sectors: 0,
+            last_update: std::time::Instant::now(),
+        }
+    }
+}
+
+static DISK_STATE: Lazy<Mutex<DiskState>> = Lazy::new(|| Mutex::new(DiskState::default()));
 
 pub fn read_disk_stats() -> DiskStats {
+    read_disk_stats_with(&mut DISK_STATE.lock().unwrap())
+}
+
+/// Throughput since the read that last updated `state`.
+pub fn read_disk_stats_with(state: &mut DiskState) -> DiskStats {
     let devices = ["sda", "mmcblk0", "dm-0"];
 
     for dev in &devices {
-        if let Some(stats) = read_device_stats(dev) {
+        if let Some(stats) = read_device_stats(dev, state) {
             return stats;
         }
     }
@@ -42,7 +52,7 @@ pub fn read_disk_stats() -> DiskStats {
     }
 }
 
-fn read_device_stats(device: &str) -> Option<DiskStats> {
+fn read_device_stats(device: &str, state: &mut DiskState) -> Option<DiskStats> {
     let mut buf = [0u8; 4096];
     let bytes_read = utils::read_file_libc_buf("/proc/diskstats", &mut buf)?;
     let content = std::str::from_utf8(&buf[..bytes_read]).ok()?;
@@ -59,7 +69,6 @@ fn read_device_stats(device: &str) -> Option<DiskStats> {
                 let read_bytes = read_sectors * SECTOR_SIZE;
                 let write_bytes = write_sectors * SECTOR_SIZE;
 
-                let mut state = DISK_STATE.lock().unwrap();
                 let now = std::time::Instant::now();
                 let elapsed = now.duration_since(state.last_update).as_secs_f64();
 
@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod disk;
 pub use disk::*;
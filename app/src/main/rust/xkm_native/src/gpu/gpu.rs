@@ -1,13 +1,15 @@
+use crate::mtk::{self, is_mtk_platform};
 use crate::utils;
 use once_cell::sync::{Lazy, OnceCell};
-use std::process::Command;
+use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GpuVendor {
     Qualcomm,
     Mali,
     PowerVR,
+    Xclipse,
     Nvidia,
     Unknown,
 }
@@ -18,13 +20,15 @@ impl std::fmt::Display for GpuVendor {
             GpuVendor::Qualcomm => write!(f, "Qualcomm"),
             GpuVendor::Mali => write!(f, "ARM Mali"),
             GpuVendor::PowerVR => write!(f, "PowerVR"),
+            GpuVendor::Xclipse => write!(f, "Samsung Xclipse"),
             GpuVendor::Nvidia => write!(f, "NVIDIA"),
             GpuVendor::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
-struct GpuBusyStats {
+/// kgsl `gpubusy` counters from the previous read.
+pub struct GpuBusyStats {
     busy: i64,
     total: i64,
 }
@@ -33,190 +37,18 @@ static GPU_INFO: OnceCell<(GpuVendor, String)> = OnceCell::new();
 static LAST_GPU_BUSY: Lazy<Mutex<Option<GpuBusyStats>>> = Lazy::new(|| Mutex::new(None));
 
 fn detect_gpu() -> (GpuVendor, String) {
-    if utils::file_exists("/sys/class/kgsl/kgsl-3d0/gpuclk") {
-        if let Some(model) = detect_adreno_model() {
-            return (GpuVendor::Qualcomm, model);
-        }
-        return (GpuVendor::Qualcomm, "Adreno".to_string());
-    }
-
-    let mali_paths = [
-        "/sys/class/misc/mali0/device/gpuinfo",
-        "/sys/devices/platform/mali/gpuinfo",
-        "/dev/mali0",
-    ];
-
-    for path in &mali_paths {
-        if utils::file_exists(path) {
-            if let Some(model) = detect_mali_model() {
-                return (GpuVendor::Mali, model);
-            }
-            return (GpuVendor::Mali, "Mali".to_string());
-        }
-    }
-
-    if let Some((vendor, model)) = detect_via_surfaceflinger() {
-        return (vendor, model);
-    }
-
-    if let Some((vendor, model)) = detect_via_cpuinfo() {
-        return (vendor, model);
-    }
-
-    if let Some((vendor, model)) = detect_via_getprop() {
-        return (vendor, model);
-    }
-
-    (GpuVendor::Unknown, "Unknown GPU".to_string())
-}
-
-fn detect_adreno_model() -> Option<String> {
-    let paths = [
-        "/sys/class/kgsl/kgsl-3d0/gpu_model",
-        "/sys/class/kgsl/kgsl-3d0/devfreq/gpu_model",
-    ];
-
-    for path in &paths {
-        if let Some(model) = utils::read_sysfs_cached(path, 0) {
-            let model = model.trim();
-            if model.to_lowercase().starts_with("adreno") {
-                return Some(model.to_string());
-            }
-            return Some(format!("Adreno {}", model));
-        }
-    }
-
-    None
-}
-
-fn detect_mali_model() -> Option<String> {
-    let paths = [
-        "/sys/class/misc/mali0/device/gpuinfo",
-        "/sys/devices/platform/mali/gpuinfo",
-    ];
-
-    for path in &paths {
-        if let Some(model) = utils::read_sysfs_cached(path, 0) {
-            let model = model.trim();
-            if model.to_lowercase().starts_with("mali") {
-                return Some(model.to_string());
-            }
-            return Some(format!("Mali {}", model));
-        }
-    }
-
-    None
-}
-
-fn detect_via_surfaceflinger() -> Option<(GpuVendor, String)> {
-    let output = Command::new("dumpsys")
-        .arg("SurfaceFlinger")
-        .output()
-        .ok()?;
-
-    let content = String::from_utf8_lossy(&output.stdout);
-
-    for line in content.lines() {
-        let line_lower = line.to_lowercase();
-
-        if line_lower.contains("gles") || line_lower.contains("renderer") {
-            if line_lower.contains("adreno") {
-                if let Some(model) = extract_adreno_version(&line) {
-                    return Some((GpuVendor::Qualcomm, format!("Adreno {}", model)));
-                }
-                return Some((GpuVendor::Qualcomm, "Adreno".to_string()));
-            }
-
-            if line_lower.contains("mali") {
-                if let Some(model) = extract_mali_version(&line) {
-                    return Some((GpuVendor::Mali, format!("Mali {}", model)));
-                }
-                return Some((GpuVendor::Mali, "Mali".to_string()));
-            }
-
-            if line_lower.contains("powervr") {
-                return Some((GpuVendor::PowerVR, "PowerVR".to_string()));
-            }
-
-            if line_lower.contains("nvidia") || line_lower.contains("tegra") {
-                return Some((GpuVendor::Nvidia, "Tegra".to_string()));
-            }
-        }
-    }
-
-    None
-}
-
-fn detect_via_cpuinfo() -> Option<(GpuVendor, String)> {
-    let content = std::fs::read_to_string("/proc/cpuinfo").ok()?;
-    let content_lower = content.to_lowercase();
-
-    if content_lower.contains("adreno") {
-        return Some((GpuVendor::Qualcomm, "Adreno".to_string()));
-    }
-
-    if content_lower.contains("mali") {
-        return Some((GpuVendor::Mali, "Mali".to_string()));
-    }
-
-    None
+    super::identity::get_gpu_identity()
+        .map(|identity| (identity.vendor, identity.model))
+        .unwrap_or((GpuVendor::Unknown, "Unknown GPU".to_string()))
 }
 
-fn detect_via_getprop() -> Option<(GpuVendor, String)> {
-    if let Some(vulkan_hw) = utils::get_system_property("ro.hardware.vulkan") {
-        if vulkan_hw.to_lowercase().contains("adreno") {
-            return Some((GpuVendor::Qualcomm, "Adreno".to_string()));
-        }
-
-        if vulkan_hw.to_lowercase().contains("mali") {
-            return Some((GpuVendor::Mali, "Mali".to_string()));
-        }
-    }
-
-    None
-}
-
-fn extract_adreno_version(s: &str) -> Option<String> {
-    let words: Vec<&str> = s.split_whitespace().collect();
-
-    for (i, word) in words.iter().enumerate() {
-        if word.to_lowercase().contains("adreno") {
-            for j in 1..4 {
-                if i + j < words.len() {
-                    let candidate = words[i + j].trim_matches(|c: char| !c.is_numeric());
-
-                    if let Ok(num) = candidate.parse::<u32>() {
-                        if num >= 200 && num <= 900 {
-                            return Some(num.to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    None
-}
-
-fn extract_mali_version(s: &str) -> Option<String> {
-    let words: Vec<&str> = s.split(&[' ', '-'][..]).collect();
-
-    for (i, word) in words.iter().enumerate() {
-        if word.to_lowercase().contains("mali") {
-            if i + 1 < words.len() {
-                let version = words[i + 1].trim();
-                if !version.is_empty() {
-                    return Some(version.to_string());
-                }
-            }
-        }
-    }
-
-    None
+/// A node of the GPU's devfreq device, wherever the driver registered it.
+fn gpu_devfreq_node(node: &str) -> Option<String> {
+    crate::devfreq::gpu_devfreq_dir().map(|dir| format!("{}/{}", dir, node))
 }
 
 pub fn get_gpu_vendor() -> GpuVendor {
-    GPU_INFO.get_or_init(detect_gpu).0.clone()
+    GPU_INFO.get_or_init(detect_gpu).0
 }
 
 pub fn get_gpu_model() -> String {
@@ -229,56 +61,48 @@ pub fn read_gpu_freq() -> i32 {
     match vendor {
         GpuVendor::Qualcomm => read_adreno_freq(),
         GpuVendor::Mali => read_mali_freq(),
+        GpuVendor::Xclipse => super::xclipse::read_xclipse_cur_freq().unwrap_or(0),
+        GpuVendor::PowerVR => super::powervr::read_powervr_cur_freq().unwrap_or(0),
         _ => 0,
     }
 }
 
 fn read_adreno_freq() -> i32 {
-    let paths = [
-        "/sys/class/kgsl/kgsl-3d0/gpuclk",
-        "/sys/class/kgsl/kgsl-3d0/devfreq/cur_freq",
-        "/sys/class/kgsl/kgsl-3d0/clock_mhz",
-    ];
-
-    for path in &paths {
-        if let Some(freq) = utils::read_sysfs_int(path, 200) {
-            if freq > 1_000_000 {
-                return (freq / 1_000_000) as i32;
-            } else if freq > 1000 {
-                return (freq / 1000) as i32;
-            } else {
-                return freq as i32;
-            }
+    let paths = ["/sys/class/kgsl/kgsl-3d0/gpuclk".to_string()]
+        .into_iter()
+        .chain(gpu_devfreq_node("cur_freq"))
+        .chain(["/sys/class/kgsl/kgsl-3d0/clock_mhz".to_string()]);
+
+    for path in paths {
+        if let Some(freq) = utils::read_sysfs_int(&path, 200) {
+            return utils::to_mhz(freq.max(0) as u64);
         }
     }
 
     0
 }
 
+/// MediaTek Mali kernels often leave the devfreq node out, in which case
+/// gpufreq and GED have the clock.
 fn read_mali_freq() -> i32 {
-    let paths = [
-        "/sys/class/misc/mali0/device/clock",
-        "/sys/devices/platform/mali/clock",
-    ];
-
-    for path in &paths {
-        if let Some(freq) = utils::read_sysfs_int(path, 200) {
-            if freq > 1_000_000 {
-                return (freq / 1_000_000) as i32;
-            }
-            return freq as i32;
-        }
-    }
-
-    0
+    super::mali::read_mali_cur_freq()
+        .or_else(|| is_mtk_platform().then(mtk::read_mtk_gpu_cur_freq).flatten())
+        .unwrap_or(0)
 }
 
 pub fn read_gpu_busy() -> i32 {
+    read_gpu_busy_with(&mut LAST_GPU_BUSY.lock().unwrap())
+}
+
+/// Load since the read that last updated `last`.
+pub fn read_gpu_busy_with(last: &mut Option<GpuBusyStats>) -> i32 {
     let vendor = get_gpu_vendor();
 
     match vendor {
-        GpuVendor::Qualcomm => read_adreno_busy(),
+        GpuVendor::Qualcomm => read_adreno_busy(last),
         GpuVendor::Mali => read_mali_busy(),
+        GpuVendor::Xclipse => super::xclipse::read_xclipse_utilization().unwrap_or(0),
+        GpuVendor::PowerVR => super::powervr::read_powervr_utilization().unwrap_or(0),
         _ => 0,
     }
 }
@@ -288,102 +112,85 @@ pub fn reset_gpu_stats() {
     *last = None;
 }
 
-fn read_adreno_busy() -> i32 {
+fn read_adreno_busy(last_processed: &mut Option<GpuBusyStats>) -> i32 {
     if let Some(content) = utils::read_sysfs("/sys/class/kgsl/kgsl-3d0/gpubusy") {
         let parts: Vec<&str> = content.split_whitespace().collect();
-        if parts.len() >= 2 {
-            if let (Ok(curr_busy), Ok(curr_total)) =
+        if parts.len() >= 2
+            && let (Ok(curr_busy), Ok(curr_total)) =
                 (parts[0].parse::<i64>(), parts[1].parse::<i64>())
-            {
-                let mut last_processed = LAST_GPU_BUSY.lock().unwrap();
-
-                if let Some(last) = &*last_processed {
-                    let delta_busy;
-                    let delta_total;
-
-                    if curr_total < last.total {
-                        delta_busy = curr_busy;
-                        delta_total = curr_total;
-                    } else {
-                        delta_busy = curr_busy.saturating_sub(last.busy);
-                        delta_total = curr_total.saturating_sub(last.total);
-                    }
+        {
+            if let Some(last) = &*last_processed {
+                let delta_busy;
+                let delta_total;
+
+                if curr_total < last.total {
+                    delta_busy = curr_busy;
+                    delta_total = curr_total;
+                } else {
+                    delta_busy = curr_busy.saturating_sub(last.busy);
+                    delta_total = curr_total.saturating_sub(last.total);
+                }
 
-                    *last_processed = Some(GpuBusyStats {
-                        busy: curr_busy,
-                        total: curr_total,
-                    });
-
-                    if delta_total > 0 {
-                        let load = (delta_busy * 100) / delta_total;
-                        if load == 0 && delta_busy > 0 {
-                            eprintln!(
-                                "GPU: busy={}/{} delta={}/{} load=1 (bumped)",
-                                curr_busy, curr_total, delta_busy, delta_total
-                            );
-                            return 1;
-                        }
+                *last_processed = Some(GpuBusyStats {
+                    busy: curr_busy,
+                    total: curr_total,
+                });
+
+                if delta_total > 0 {
+                    let load = (delta_busy * 100) / delta_total;
+                    if load == 0 && delta_busy > 0 {
                         eprintln!(
-                            "GPU: busy={}/{} delta={}/{} load={}",
-                            curr_busy, curr_total, delta_busy, delta_total, load
+                            "GPU: busy={}/{} delta={}/{} load=1 (bumped)",
+                            curr_busy, curr_total, delta_busy, delta_total
                         );
-                        return load.min(100) as i32;
-                    } else {
-                        eprintln!("GPU: busy={}/{} delta=0/0 load=0", curr_busy, curr_total);
-                        return 0;
+                        return 1;
                     }
+                    eprintln!(
+                        "GPU: busy={}/{} delta={}/{} load={}",
+                        curr_busy, curr_total, delta_busy, delta_total, load
+                    );
+                    return load.min(100) as i32;
                 } else {
-                    *last_processed = Some(GpuBusyStats {
-                        busy: curr_busy,
-                        total: curr_total,
-                    });
+                    eprintln!("GPU: busy={}/{} delta=0/0 load=0", curr_busy, curr_total);
+                    return 0;
                 }
+            } else {
+                *last_processed = Some(GpuBusyStats {
+                    busy: curr_busy,
+                    total: curr_total,
+                });
             }
         }
     }
 
     if let Some(busy) = utils::read_sysfs_int("/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage", 0) {
-        return busy as i32;
+        return busy;
     }
 
     0
 }
 
 fn read_mali_busy() -> i32 {
-    let paths = [
-        "/sys/class/misc/mali0/device/utilization",
-        "/sys/devices/platform/mali/utilization",
-    ];
-
-    for path in &paths {
-        if let Some(busy) = utils::read_sysfs_int(path, 200) {
-            return busy as i32;
-        }
-    }
-
-    0
+    super::mali::read_mali_utilization()
+        .or_else(|| {
+            is_mtk_platform()
+                .then(mtk::read_mtk_gpu_utilization)
+                .flatten()
+        })
+        .unwrap_or(0)
 }
 
 pub fn get_gpu_available_frequencies() -> Vec<i32> {
-    let paths = [
-        "/sys/class/kgsl/kgsl-3d0/gpu_available_frequencies",
-        "/sys/class/kgsl/kgsl-3d0/devfreq/available_frequencies",
-    ];
+    let paths = ["/sys/class/kgsl/kgsl-3d0/gpu_available_frequencies".to_string()]
+        .into_iter()
+        .chain(gpu_devfreq_node("available_frequencies"));
 
-    for path in &paths {
-        if let Some(content) = utils::read_sysfs_cached(path, 0) {
+    for path in paths {
+        if let Some(content) = utils::read_sysfs_cached(&path, 0) {
             let freqs: Vec<i32> = content
                 .split_whitespace()
-                .filter_map(|s| s.parse::<i64>().ok())
-                .map(|hz| {
-                    if hz > 1_000_000 {
-                        (hz / 1_000_000) as i32
-                    } else if hz > 1000 {
-                        (hz / 1000) as i32
-                    } else {
-                        hz as i32
-                    }
-                })
+                .filter_map(|s| s.parse::<u64>().ok())
+                .map(utils::to_mhz)
                 .collect();
 
             if !freqs.is_empty() {
@@ -392,38 +199,61 @@ pub fn get_gpu_available_frequencies() -> Vec<i32> {
         }
     }
 
-    vec![]
+    match get_gpu_vendor() {
+        GpuVendor::Xclipse => super::xclipse::read_xclipse_state()
+            .map(|state| state.available_frequencies_mhz)
+            .unwrap_or_default(),
+        GpuVendor::PowerVR => super::powervr::read_powervr_state()
+            .map(|state| state.available_frequencies_mhz)
+            .unwrap_or_default(),
+        _ => super::mali::read_mali_state()
+            .map(|state| state.available_frequencies_mhz)
+            .filter(|freqs| !freqs.is_empty())
+            .unwrap_or_else(|| {
+                if is_mtk_platform() {
+                    mtk::read_mtk_gpu_frequencies()
+                } else {
+                    Vec::new()
+                }
+            }),
+    }
 }
 
 pub fn get_gpu_available_policies() -> Vec<String> {
-    let paths = [
-        "/sys/class/kgsl/kgsl-3d0/devfreq/available_governors",
-        "/sys/class/kgsl/kgsl-3d0/available_governors",
-    ];
-
-    for path in &paths {
-        if let Some(content) = utils::read_sysfs_cached(path, 0) {
-            return content
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect();
+    let paths = gpu_devfreq_node("available_governors")
+        .into_iter()
+        .chain(["/sys/class/kgsl/kgsl-3d0/available_governors".to_string()]);
+
+    for path in paths {
+        if let Some(content) = utils::read_sysfs_cached(&path, 0) {
+            return content.split_whitespace().map(|s| s.to_string()).collect();
         }
     }
 
-    vec![]
+    match get_gpu_vendor() {
+        GpuVendor::Xclipse => super::xclipse::read_xclipse_state()
+            .map(|state| state.available_governors)
+            .unwrap_or_default(),
+        GpuVendor::PowerVR => super::powervr::read_powervr_state()
+            .map(|state| state.available_governors)
+            .unwrap_or_default(),
+        _ => super::mali::read_mali_state()
+            .map(|state| state.available_governors)
+            .unwrap_or_default(),
+    }
 }
 
 pub fn get_gpu_driver_info() -> String {
-    let paths = [
-        "/sys/class/kgsl/kgsl-3d0/gpu_model",
-        "/sys/class/kgsl/kgsl-3d0/devfreq/name",
-    ];
-
-    for path in &paths {
-        if let Some(info) = utils::read_sysfs_cached(path, 0) {
-            if !info.is_empty() && info != "unknown" {
-                return info;
-            }
+    let paths = ["/sys/class/kgsl/kgsl-3d0/gpu_model".to_string()]
+        .into_iter()
+        .chain(gpu_devfreq_node("name"));
+
+    for path in paths {
+        if let Some(info) = utils::read_sysfs_cached(&path, 0)
+            && !info.is_empty()
+            && info != "unknown"
+        {
+            return info;
         }
     }
 
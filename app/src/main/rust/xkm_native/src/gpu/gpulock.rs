@@ -0,0 +1,680 @@
+use super::kgsl::{self, hz_to_mhz, levels_at};
+use crate::cpu::{LockEvent, LockEventKind};
+use crate::devfreq;
+use crate::utils::{self, to_mhz};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const SYS_ROOT: &str = "/sys";
+const KGSL_ROOT: &str = "/sys/class/kgsl/kgsl-3d0";
+const GUARD_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_EVENTS: usize = 64;
+
+/// Thresholds in °C against the hottest GPU zone, delays in ms. Between
+/// `restore_threshold` and `warning_threshold` the lock holds its step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuThermalPolicy {
+    pub critical_threshold: f32,
+    pub warning_threshold: f32,
+    pub restore_threshold: f32,
+    pub step_cooldown_ms: u64,
+    pub restore_delay_ms: u64,
+    /// Frequency levels removed from the ceiling at most.
+    pub max_steps: u32,
+}
+
+impl Default for GpuThermalPolicy {
+    fn default() -> Self {
+        Self {
+            critical_threshold: 90.0,
+            warning_threshold: 75.0,
+            restore_threshold: 68.0,
+            step_cooldown_ms: 3_000,
+            restore_delay_ms: 10_000,
+            max_steps: 3,
+        }
+    }
+}
+
+impl GpuThermalPolicy {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.critical_threshold > self.warning_threshold
+            && self.warning_threshold > self.restore_threshold)
+        {
+            return Err("thresholds must satisfy critical > warning > restore".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GpuLockRequest {
+    pub min_mhz: i32,
+    pub max_mhz: i32,
+    #[serde(default)]
+    pub policy: GpuThermalPolicy,
+    #[serde(default = "default_guard")]
+    pub thermal_guard: bool,
+}
+
+fn default_guard() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuLockState {
+    pub locked: bool,
+    pub thermal_guard: bool,
+    pub policy: GpuThermalPolicy,
+    pub min_mhz: i32,
+    pub max_mhz: i32,
+    /// Levels currently taken off the ceiling; 0 is the full lock.
+    pub step: u32,
+    pub applied_min_mhz: i32,
+    pub applied_max_mhz: i32,
+    /// Times the range was written back after something else moved it.
+    pub reapplied: u32,
+    pub last_temperature: f32,
+    pub last_change_ms: u64,
+    pub cool_since_ms: Option<u64>,
+    /// Raw min/max node contents from before the lock.
+    pub original_min: String,
+    pub original_max: String,
+    /// kgsl `min_pwrlevel` / `max_pwrlevel` from before the lock, which the
+    /// range setter moves along with devfreq. Empty on other targets.
+    pub original_min_pwrlevel: String,
+    pub original_max_pwrlevel: String,
+}
+
+/// Where the range is written. kgsl goes through its own range setter so
+/// the power levels follow the devfreq limits.
+#[derive(Debug, Clone, PartialEq)]
+enum LockTarget {
+    Kgsl(String),
+    Devfreq(String),
+    /// Samsung `/sys/kernel/gpu`, in kHz.
+    Xclipse(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transition {
+    StepDown,
+    StepUp,
+    Critical,
+}
+
+#[derive(Default)]
+struct Engine {
+    state: GpuLockState,
+    target: Option<LockTarget>,
+    events: VecDeque<LockEvent>,
+}
+
+static ENGINE: Lazy<Mutex<Engine>> = Lazy::new(|| Mutex::new(Engine::default()));
+static GUARD_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn detect_target(sys: &str, kgsl_dir: &str, devfreq_dir: Option<&str>) -> Option<LockTarget> {
+    let xclipse = format!("{}/kernel/gpu", sys);
+    if !levels_at(kgsl_dir).is_empty() {
+        Some(LockTarget::Kgsl(kgsl_dir.to_string()))
+    } else if let Some(dir) = devfreq_dir {
+        Some(LockTarget::Devfreq(dir.to_string()))
+    } else if utils::file_exists(&format!("{}/gpu_max_clock", xclipse)) {
+        Some(LockTarget::Xclipse(xclipse))
+    } else {
+        None
+    }
+}
+
+impl LockTarget {
+    /// (min node, max node), relative to the target directory.
+    fn nodes(&self) -> (&'static str, &'static str) {
+        match self {
+            LockTarget::Kgsl(_) | LockTarget::Devfreq(_) => ("min_freq", "max_freq"),
+            LockTarget::Xclipse(_) => ("gpu_min_clock", "gpu_max_clock"),
+        }
+    }
+
+    fn node_dir(&self) -> String {
+        match self {
+            LockTarget::Kgsl(dir) => format!("{}/devfreq", dir),
+            LockTarget::Devfreq(dir) | LockTarget::Xclipse(dir) => dir.clone(),
+        }
+    }
+
+    fn read(&self, node: &str) -> Option<String> {
+        utils::read_sysfs_full(&format!("{}/{}", self.node_dir(), node))
+    }
+
+    /// (MHz, raw value in the node's unit), slowest first.
+    fn levels(&self) -> Vec<(i32, u64)> {
+        let raw: Vec<u64> = match self {
+            LockTarget::Kgsl(dir) => levels_at(dir).into_iter().map(|hz| hz as u64).collect(),
+            LockTarget::Devfreq(dir) => {
+                utils::read_sysfs_full(&format!("{}/available_frequencies", dir))
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter_map(|f| f.parse().ok())
+                    .collect()
+            }
+            LockTarget::Xclipse(dir) => utils::read_sysfs_full(&format!("{}/gpu_freq_table", dir))
+                .unwrap_or_default()
+                .split_whitespace()
+                .filter_map(|f| f.parse().ok())
+                .collect(),
+        };
+        let mut levels: Vec<(i32, u64)> = raw
+            .into_iter()
+            .map(|value| match self {
+                LockTarget::Kgsl(_) => (hz_to_mhz(value as i64), value),
+                _ => (to_mhz(value), value),
+            })
+            .collect();
+        levels.sort_unstable();
+        levels.dedup_by_key(|(mhz, _)| *mhz);
+        levels
+    }
+
+    fn ceiling_mhz(&self) -> Option<i32> {
+        let raw: u64 = self.read(self.nodes().1)?.trim().parse().ok()?;
+        Some(match self {
+            LockTarget::Kgsl(_) => hz_to_mhz(raw as i64),
+            _ => to_mhz(raw),
+        })
+    }
+
+    /// Writes the range so min never passes max on the way.
+    fn write_range(&self, min: (i32, u64), max: (i32, u64)) -> bool {
+        if let LockTarget::Kgsl(dir) = self {
+            return kgsl::set_freq_range_at(dir, min.0, max.0).is_ok()
+                && self.ceiling_mhz() == Some(max.0);
+        }
+        let (min_node, max_node) = self.nodes();
+        let mut writes = [(min_node, min.1), (max_node, max.1)];
+        if self.ceiling_mhz().is_some_and(|current| min.0 > current) {
+            writes.reverse();
+        }
+        let dir = self.node_dir();
+        writes.iter().all(|(node, value)| {
+            utils::write_sysfs(&format!("{}/{}", dir, node), &value.to_string())
+        })
+    }
+
+    /// kgsl power levels as (`min_pwrlevel`, `max_pwrlevel`), raw.
+    fn pwrlevels(&self) -> (String, String) {
+        let LockTarget::Kgsl(dir) = self else {
+            return Default::default();
+        };
+        let read = |node: &str| utils::read_sysfs_full(&format!("{}/{}", dir, node));
+        (
+            read("min_pwrlevel").unwrap_or_default(),
+            read("max_pwrlevel").unwrap_or_default(),
+        )
+    }
+
+    /// Writes the pre-lock values back, kgsl power levels first.
+    fn restore(&self, state: &GpuLockState) -> bool {
+        let write = |dir: &str, node: &str, value: &str| {
+            value.is_empty() || utils::write_sysfs(&format!("{}/{}", dir, node), value.trim())
+        };
+
+        // Level 0 is the fastest, so the cap's index must not pass the
+        // floor's on the way back.
+        let levels_ok = match self {
+            LockTarget::Kgsl(dir) => {
+                let current_min: Option<i32> =
+                    utils::read_sysfs_num(&format!("{}/min_pwrlevel", dir));
+                let new_max: Option<i32> = state.original_max_pwrlevel.trim().parse().ok();
+                let mut order = [
+                    ("max_pwrlevel", &state.original_max_pwrlevel),
+                    ("min_pwrlevel", &state.original_min_pwrlevel),
+                ];
+                if new_max.zip(current_min).is_some_and(|(max, min)| max > min) {
+                    order.reverse();
+                }
+                order.iter().all(|(node, value)| write(dir, node, value))
+            }
+            _ => true,
+        };
+
+        let (min_node, max_node) = self.nodes();
+        let dir = self.node_dir();
+        // Lower the floor before the ceiling when the old ceiling sits
+        // under the current floor.
+        let current_min: Option<u64> = self.read(min_node).and_then(|v| v.trim().parse().ok());
+        let new_max: Option<u64> = state.original_max.trim().parse().ok();
+        let mut order = [
+            (max_node, &state.original_max),
+            (min_node, &state.original_min),
+        ];
+        if new_max.zip(current_min).is_some_and(|(max, min)| max < min) {
+            order.reverse();
+        }
+        order.iter().all(|(node, value)| write(&dir, node, value)) && levels_ok
+    }
+}
+
+/// Range for the lock at `step`: the ceiling drops one level per step and
+/// the floor follows it down if needed.
+fn stepped_range(
+    levels: &[(i32, u64)],
+    min_mhz: i32,
+    max_mhz: i32,
+    step: u32,
+) -> Option<((i32, u64), (i32, u64))> {
+    let top = levels
+        .iter()
+        .rposition(|(mhz, _)| *mhz <= max_mhz)
+        .unwrap_or(0);
+    let ceiling = levels.get(top.saturating_sub(step as usize))?;
+    let floor = levels
+        .iter()
+        .find(|(mhz, _)| *mhz >= min_mhz)
+        .unwrap_or(ceiling)
+        .min(ceiling);
+    Some((*floor, *ceiling))
+}
+
+fn apply_at(target: &LockTarget, state: &mut GpuLockState) -> bool {
+    let Some((floor, ceiling)) =
+        stepped_range(&target.levels(), state.min_mhz, state.max_mhz, state.step)
+    else {
+        return false;
+    };
+    state.applied_min_mhz = floor.0;
+    state.applied_max_mhz = ceiling.0;
+    target.write_range(floor, ceiling)
+}
+
+fn evaluate(state: &mut GpuLockState, temp: f32, now: u64) -> Option<Transition> {
+    let policy = &state.policy;
+    state.last_temperature = temp;
+
+    if temp > policy.restore_threshold {
+        state.cool_since_ms = None;
+    }
+    if temp >= policy.critical_threshold {
+        return Some(Transition::Critical);
+    }
+    if temp >= policy.warning_threshold {
+        let cooled_down = now.saturating_sub(state.last_change_ms) >= policy.step_cooldown_ms;
+        return (cooled_down && state.step < policy.max_steps).then_some(Transition::StepDown);
+    }
+    if temp > policy.restore_threshold {
+        return None;
+    }
+
+    let since = *state.cool_since_ms.get_or_insert(now);
+    (state.step > 0 && now.saturating_sub(since) >= policy.restore_delay_ms)
+        .then_some(Transition::StepUp)
+}
+
+fn event(kind: LockEventKind, state: &GpuLockState, message: String, now: u64) -> LockEvent {
+    LockEvent {
+        kind,
+        temperature: state.last_temperature,
+        step: state.step,
+        message,
+        timestamp_ms: now,
+    }
+}
+
+fn unlock_at(target: &LockTarget, state: &mut GpuLockState) -> bool {
+    let restored = target.restore(state);
+    let temp = state.last_temperature;
+    *state = GpuLockState {
+        last_temperature: temp,
+        ..Default::default()
+    };
+    restored
+}
+
+fn apply_transition_at(
+    target: &LockTarget,
+    state: &mut GpuLockState,
+    transition: Transition,
+    now: u64,
+) -> LockEvent {
+    let temp = state.last_temperature;
+    state.last_change_ms = now;
+    match transition {
+        Transition::StepDown => {
+            state.step += 1;
+            apply_at(target, state);
+            let message = format!(
+                "{:.1}°C: GPU ceiling stepped down to {} MHz",
+                temp, state.applied_max_mhz
+            );
+            event(LockEventKind::StepDown, state, message, now)
+        }
+        Transition::StepUp => {
+            state.step -= 1;
+            state.cool_since_ms = Some(now);
+            apply_at(target, state);
+            let message = format!(
+                "{:.1}°C: GPU ceiling stepped up to {} MHz",
+                temp, state.applied_max_mhz
+            );
+            event(LockEventKind::StepUp, state, message, now)
+        }
+        Transition::Critical => {
+            unlock_at(target, state);
+            let message = format!("{:.1}°C: GPU lock released at critical temperature", temp);
+            event(LockEventKind::Critical, state, message, now)
+        }
+    }
+}
+
+fn lock_at(
+    target: &LockTarget,
+    state: &mut GpuLockState,
+    request: GpuLockRequest,
+) -> Result<LockEvent, String> {
+    request.policy.validate()?;
+    if request.min_mhz > request.max_mhz {
+        return Err(format!(
+            "Min {} MHz is above max {} MHz",
+            request.min_mhz, request.max_mhz
+        ));
+    }
+    if target.levels().is_empty() {
+        return Err("GPU reports no frequency table".into());
+    }
+
+    // Re-locking keeps the values from before the first lock.
+    let (original_min, original_max, (original_min_pwrlevel, original_max_pwrlevel)) =
+        if state.locked {
+            (
+                std::mem::take(&mut state.original_min),
+                std::mem::take(&mut state.original_max),
+                (
+                    std::mem::take(&mut state.original_min_pwrlevel),
+                    std::mem::take(&mut state.original_max_pwrlevel),
+                ),
+            )
+        } else {
+            let (min_node, max_node) = target.nodes();
+            (
+                target.read(min_node).unwrap_or_default(),
+                target.read(max_node).unwrap_or_default(),
+                target.pwrlevels(),
+            )
+        };
+
+    *state = GpuLockState {
+        locked: true,
+        thermal_guard: request.thermal_guard,
+        policy: request.policy,
+        min_mhz: request.min_mhz,
+        max_mhz: request.max_mhz,
+        last_temperature: state.last_temperature,
+        original_min,
+        original_max,
+        original_min_pwrlevel,
+        original_max_pwrlevel,
+        ..Default::default()
+    };
+
+    if !apply_at(target, state) {
+        target.restore(state);
+        *state = GpuLockState::default();
+        return Err("failed to lock the GPU range".into());
+    }
+
+    let now = utils::now_ms();
+    state.last_change_ms = now;
+    let message = format!(
+        "GPU locked to {}-{} MHz",
+        state.applied_min_mhz, state.applied_max_mhz
+    );
+    Ok(event(LockEventKind::Locked, state, message, now))
+}
+
+/// One guard tick: write the range back if something moved the ceiling,
+/// then step on temperature.
+fn tick_at(
+    target: &LockTarget,
+    state: &mut GpuLockState,
+    temp: Option<f32>,
+    now: u64,
+) -> Option<LockEvent> {
+    if target.ceiling_mhz() != Some(state.applied_max_mhz) && apply_at(target, state) {
+        state.reapplied += 1;
+    }
+    let temp = temp.filter(|_| state.thermal_guard)?;
+    let transition = evaluate(state, temp, now)?;
+    Some(apply_transition_at(target, state, transition, now))
+}
+
+impl Engine {
+    fn push(&mut self, event: LockEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Hottest GPU zone, or the CPU sensor on devices without one.
+fn read_temperature() -> Option<f32> {
+    super::throttle::read_gpu_temperature()
+        .or_else(|| Some(crate::power::read_cpu_temperature()).filter(|t| *t > 0.0))
+}
+
+fn start_guard() {
+    if GUARD_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(|| {
+        loop {
+            std::thread::sleep(GUARD_INTERVAL);
+
+            let temp = read_temperature();
+            let mut engine = ENGINE.lock().unwrap();
+            let Some(target) = engine.target.clone().filter(|_| engine.state.locked) else {
+                // Cleared under the engine lock so a lock taken right now
+                // sees the flag down and starts a fresh guard.
+                GUARD_RUNNING.store(false, Ordering::SeqCst);
+                break;
+            };
+            if let Some(event) = tick_at(&target, &mut engine.state, temp, utils::now_ms()) {
+                engine.push(event);
+            }
+        }
+    });
+}
+
+/// Holds the GPU between `min_mhz` and `max_mhz`, writing the range back
+/// when something else moves it. With `thermal_guard` the ceiling backs off
+/// one level per warning step and the lock is released at the critical
+/// threshold.
+pub fn lock_gpu_frequency(request: GpuLockRequest) -> Result<GpuLockState, String> {
+    let mut engine = ENGINE.lock().unwrap();
+
+    let temp = read_temperature();
+    if request.thermal_guard && temp.is_some_and(|t| t >= request.policy.critical_threshold) {
+        return Err(format!(
+            "temperature {:.1}°C is above the critical threshold",
+            temp.unwrap_or_default()
+        ));
+    }
+
+    let target = match engine.target.clone().filter(|_| engine.state.locked) {
+        Some(target) => target,
+        None => detect_target(SYS_ROOT, KGSL_ROOT, devfreq::gpu_devfreq_dir())
+            .ok_or("No lockable GPU frequency interface")?,
+    };
+    engine.state.last_temperature = temp.unwrap_or_default();
+    let event = lock_at(&target, &mut engine.state, request)?;
+    engine.target = Some(target);
+    engine.push(event);
+    start_guard();
+
+    Ok(engine.state.clone())
+}
+
+/// Puts back the range from before the first lock.
+pub fn unlock_gpu_frequency() -> GpuLockState {
+    let mut engine = ENGINE.lock().unwrap();
+    let Some(target) = engine.target.clone().filter(|_| engine.state.locked) else {
+        return engine.state.clone();
+    };
+
+    let restored = unlock_at(&target, &mut engine.state);
+    let message = if restored {
+        "GPU unlocked".to_string()
+    } else {
+        "GPU unlocked, the original range was not restored".to_string()
+    };
+    let event = event(
+        LockEventKind::Unlocked,
+        &engine.state,
+        message,
+        utils::now_ms(),
+    );
+    engine.push(event);
+    engine.state.clone()
+}
+
+pub fn get_gpu_lock_state() -> GpuLockState {
+    ENGINE.lock().unwrap().state.clone()
+}
+
+/// Drains events raised since the last call.
+pub fn poll_gpu_lock_events() -> Vec<LockEvent> {
+    ENGINE.lock().unwrap().events.drain(..).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn request() -> GpuLockRequest {
+        GpuLockRequest {
+            min_mhz: 500,
+            max_mhz: 800,
+            policy: GpuThermalPolicy::default(),
+            thermal_guard: true,
+        }
+    }
+
+    #[test]
+    fn test_stepped_range() {
+        let levels = [(300, 3), (450, 4), (600, 6), (800, 8)];
+        assert_eq!(
+            stepped_range(&levels, 500, 800, 0),
+            Some(((600, 6), (800, 8)))
+        );
+        assert_eq!(
+            stepped_range(&levels, 500, 700, 0),
+            Some(((600, 6), (600, 6)))
+        );
+        // The floor follows the ceiling down.
+        assert_eq!(
+            stepped_range(&levels, 500, 800, 2),
+            Some(((450, 4), (450, 4)))
+        );
+        assert_eq!(
+            stepped_range(&levels, 500, 800, 9),
+            Some(((300, 3), (300, 3)))
+        );
+        assert_eq!(stepped_range(&[], 500, 800, 0), None);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut state = GpuLockState {
+            locked: true,
+            thermal_guard: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(&mut state, 76.0, 5_000),
+            Some(Transition::StepDown)
+        );
+        state.step = 1;
+        state.last_change_ms = 5_000;
+        assert_eq!(evaluate(&mut state, 76.0, 6_000), None);
+        assert_eq!(evaluate(&mut state, 70.0, 20_000), None);
+        assert_eq!(evaluate(&mut state, 60.0, 21_000), None);
+        assert_eq!(evaluate(&mut state, 60.0, 31_000), Some(Transition::StepUp));
+        assert_eq!(
+            evaluate(&mut state, 91.0, 32_000),
+            Some(Transition::Critical)
+        );
+    }
+
+    #[test]
+    fn test_devfreq_lock_cycle() {
+        let dir = fixture::build(
+            "gpu_lock",
+            &[
+                (
+                    "available_frequencies",
+                    "300000000 450000000 600000000 800000000",
+                ),
+                ("min_freq", "300000000"),
+                ("max_freq", "800000000"),
+            ],
+        );
+        let target = LockTarget::Devfreq(dir.clone());
+        let read = |node: &str| utils::read_sysfs_full(&format!("{}/{}", dir, node));
+
+        let mut state = GpuLockState::default();
+        lock_at(&target, &mut state, request()).unwrap();
+        assert_eq!(read("min_freq").as_deref(), Some("600000000"));
+        assert_eq!(read("max_freq").as_deref(), Some("800000000"));
+
+        state.last_temperature = 77.0;
+        apply_transition_at(&target, &mut state, Transition::StepDown, 1_000);
+        assert_eq!(read("max_freq").as_deref(), Some("600000000"));
+
+        // Something else raised the ceiling; the tick writes it back.
+        utils::write_sysfs(&format!("{}/max_freq", dir), "800000000");
+        assert!(tick_at(&target, &mut state, Some(72.0), 2_000).is_none());
+        assert_eq!(read("max_freq").as_deref(), Some("600000000"));
+        assert_eq!(state.reapplied, 1);
+
+        let event = tick_at(&target, &mut state, Some(95.0), 3_000).unwrap();
+        assert_eq!(event.kind, LockEventKind::Critical);
+        assert!(!state.locked);
+        assert_eq!(read("min_freq").as_deref(), Some("300000000"));
+        assert_eq!(read("max_freq").as_deref(), Some("800000000"));
+
+        // kgsl moves the power levels with the range; unlock puts them back.
+        let kgsl = fixture::build(
+            "gpu_lock_kgsl",
+            &[
+                (
+                    "gpu_available_frequencies",
+                    "800000000 600000000 450000000 300000000",
+                ),
+                ("max_pwrlevel", "0"),
+                ("min_pwrlevel", "3"),
+                ("devfreq/min_freq", "300000000"),
+                ("devfreq/max_freq", "800000000"),
+            ],
+        );
+        let target = LockTarget::Kgsl(kgsl.clone());
+        let read = |node: &str| utils::read_sysfs_full(&format!("{}/{}", kgsl, node));
+
+        let mut state = GpuLockState::default();
+        lock_at(&target, &mut state, request()).unwrap();
+        assert_eq!(read("min_pwrlevel").as_deref(), Some("1"));
+        assert_eq!(read("devfreq/min_freq").as_deref(), Some("600000000"));
+
+        assert!(unlock_at(&target, &mut state));
+        assert_eq!(read("max_pwrlevel").as_deref(), Some("0"));
+        assert_eq!(read("min_pwrlevel").as_deref(), Some("3"));
+        assert_eq!(read("devfreq/min_freq").as_deref(), Some("300000000"));
+        assert_eq!(read("devfreq/max_freq").as_deref(), Some("800000000"));
+    }
+}
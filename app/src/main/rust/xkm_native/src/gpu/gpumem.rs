@@ -0,0 +1,290 @@
+use crate::process;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SYS_ROOT: &str = "/sys";
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuMemorySource {
+    /// `/sys/class/kgsl/kgsl/proc/<pid>`.
+    Kgsl,
+    /// `/sys/kernel/debug/mali0/gpu_memory`, which needs debugfs.
+    Mali,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuProcessMemory {
+    pub pid: i32,
+    /// Package name for apps, as in the process list.
+    pub name: String,
+    pub uid: u32,
+    pub total_bytes: u64,
+    /// kgsl only: GPU memory also mapped into the CPU side.
+    pub mapped_bytes: Option<u64>,
+    pub unmapped_bytes: Option<u64>,
+    /// kgsl only: dma-bufs imported from other drivers.
+    pub imported_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmabufExporter {
+    pub name: String,
+    pub buffers: u32,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuMemoryReport {
+    pub source: Option<GpuMemorySource>,
+    /// Driver-wide GPU allocation: kgsl `page_alloc` + `coherent`, or the
+    /// Mali device's used pages.
+    pub total_bytes: Option<u64>,
+    pub kgsl_page_alloc_bytes: Option<u64>,
+    pub kgsl_coherent_bytes: Option<u64>,
+    /// All dma-bufs, which includes GPU, camera and display buffers.
+    pub dmabuf_total_bytes: Option<u64>,
+    /// Largest first.
+    pub dmabuf_exporters: Vec<DmabufExporter>,
+    /// Largest first, at most `limit`.
+    pub processes: Vec<GpuProcessMemory>,
+}
+
+/// Raw per-PID counters before the process join.
+struct PidMemory {
+    pid: i32,
+    total: u64,
+    mapped: Option<u64>,
+    unmapped: Option<u64>,
+    imported: Option<u64>,
+}
+
+fn kgsl_dir(sys: &str) -> String {
+    format!("{}/class/kgsl/kgsl", sys)
+}
+
+fn read_kgsl_processes(sys: &str) -> Vec<PidMemory> {
+    let root = format!("{}/proc", kgsl_dir(sys));
+    utils::list_dir(&root)
+        .into_iter()
+        .filter_map(|name| {
+            let pid: i32 = name.parse().ok()?;
+            let read =
+                |node: &str| utils::read_sysfs_num::<u64>(&format!("{}/{}/{}", root, name, node));
+            let mapped = read("gpumem_mapped");
+            let unmapped = read("gpumem_unmapped");
+            let imported = read("imported_mem");
+            // Kernels without the split only have `gpumem`.
+            let total = match (mapped, unmapped) {
+                (None, None) => read("gpumem")?,
+                _ => mapped.unwrap_or(0) + unmapped.unwrap_or(0),
+            };
+            Some(PidMemory {
+                pid,
+                total: total + imported.unwrap_or(0),
+                mapped,
+                unmapped,
+                imported,
+            })
+        })
+        .collect()
+}
+
+/// `mali0  123456` for the device, then one `  kctx-0x...  pages  tgid`
+/// row per context. Counts are pages; a process may hold several contexts.
+fn parse_mali_gpu_memory(text: &str) -> (Option<u64>, Vec<PidMemory>) {
+    let mut device_pages = None;
+    let mut per_pid: HashMap<i32, u64> = HashMap::new();
+
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) && fields[0].starts_with("kctx") {
+            let (Some(pages), Some(tgid)) = (
+                fields[1].parse::<u64>().ok(),
+                fields.get(2).and_then(|v| v.parse::<i32>().ok()),
+            ) else {
+                continue;
+            };
+            *per_pid.entry(tgid).or_default() += pages;
+        } else if device_pages.is_none() {
+            device_pages = fields[1].parse::<u64>().ok();
+        }
+    }
+
+    let processes = per_pid
+        .into_iter()
+        .map(|(pid, pages)| PidMemory {
+            pid,
+            total: pages * utils::page_size(),
+            mapped: None,
+            unmapped: None,
+            imported: None,
+        })
+        .collect();
+    (device_pages.map(|p| p * utils::page_size()), processes)
+}
+
+fn read_mali_gpu_memory(sys: &str) -> Option<(Option<u64>, Vec<PidMemory>)> {
+    let debug = format!("{}/kernel/debug", sys);
+    utils::list_dir(&debug)
+        .into_iter()
+        .filter(|name| name.starts_with("mali"))
+        .find_map(|name| utils::read_sysfs_full(&format!("{}/{}/gpu_memory", debug, name)))
+        .map(|text| parse_mali_gpu_memory(&text))
+}
+
+/// `/sys/kernel/dmabuf/buffers/<inode>/{size,exporter_name}`, grouped by
+/// exporter.
+fn read_dmabuf_at(sys: &str) -> Option<(u64, Vec<DmabufExporter>)> {
+    let root = format!("{}/kernel/dmabuf/buffers", sys);
+    if !utils::file_exists(&root) {
+        return None;
+    }
+
+    let mut exporters: Vec<DmabufExporter> = Vec::new();
+    for inode in utils::list_dir(&root) {
+        let dir = format!("{}/{}", root, inode);
+        let Some(size) = utils::read_sysfs_num::<u64>(&format!("{}/size", dir)) else {
+            continue;
+        };
+        let name = utils::read_sysfs_full(&format!("{}/exporter_name", dir))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        match exporters.iter_mut().find(|e| e.name == name) {
+            Some(exporter) => {
+                exporter.buffers += 1;
+                exporter.bytes += size;
+            }
+            None => exporters.push(DmabufExporter {
+                name,
+                buffers: 1,
+                bytes: size,
+            }),
+        }
+    }
+
+    exporters.sort_by_key(|e| std::cmp::Reverse(e.bytes));
+    Some((exporters.iter().map(|e| e.bytes).sum(), exporters))
+}
+
+fn read_gpu_memory_at(sys: &str, proc: &str, limit: usize) -> GpuMemoryReport {
+    let kgsl = kgsl_dir(sys);
+    let page_alloc = utils::read_sysfs_num::<u64>(&format!("{}/page_alloc", kgsl));
+    let coherent = utils::read_sysfs_num::<u64>(&format!("{}/coherent", kgsl));
+
+    let (source, device_total, mut raw) = if utils::file_exists(&format!("{}/proc", kgsl)) {
+        let total = page_alloc
+            .or(coherent)
+            .map(|_| page_alloc.unwrap_or(0) + coherent.unwrap_or(0));
+        (Some(GpuMemorySource::Kgsl), total, read_kgsl_processes(sys))
+    } else if let Some((total, processes)) = read_mali_gpu_memory(sys) {
+        (Some(GpuMemorySource::Mali), total, processes)
+    } else {
+        (None, None, Vec::new())
+    };
+
+    raw.sort_by(|a, b| b.total.cmp(&a.total).then(a.pid.cmp(&b.pid)));
+    let processes = raw
+        .into_iter()
+        .filter(|m| m.total > 0)
+        .take(limit)
+        .map(|m| {
+            let (name, uid) = process::process_name_at(proc, m.pid)
+                .unwrap_or_else(|| (format!("pid {}", m.pid), 0));
+            GpuProcessMemory {
+                pid: m.pid,
+                name,
+                uid,
+                total_bytes: m.total,
+                mapped_bytes: m.mapped,
+                unmapped_bytes: m.unmapped,
+                imported_bytes: m.imported,
+            }
+        })
+        .collect();
+
+    let dmabuf = read_dmabuf_at(sys);
+    GpuMemoryReport {
+        source,
+        total_bytes: device_total,
+        kgsl_page_alloc_bytes: page_alloc,
+        kgsl_coherent_bytes: coherent,
+        dmabuf_total_bytes: dmabuf.as_ref().map(|(total, _)| *total),
+        dmabuf_exporters: dmabuf.map(|(_, exporters)| exporters).unwrap_or_default(),
+        processes,
+    }
+}
+
+/// Total GPU memory and the top `limit` consumers by PID.
+pub fn read_gpu_memory(limit: usize) -> GpuMemoryReport {
+    read_gpu_memory_at(SYS_ROOT, PROC_ROOT, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_kgsl_and_dmabuf() {
+        let root = fixture::build(
+            "gpumem_kgsl",
+            &[
+                ("sys/class/kgsl/kgsl/page_alloc", "300000000"),
+                ("sys/class/kgsl/kgsl/coherent", "4096"),
+                ("sys/class/kgsl/kgsl/proc/1200/gpumem_mapped", "1000"),
+                ("sys/class/kgsl/kgsl/proc/1200/gpumem_unmapped", "5000"),
+                ("sys/class/kgsl/kgsl/proc/1200/imported_mem", "2000"),
+                ("sys/class/kgsl/kgsl/proc/900/gpumem", "90000"),
+                ("sys/class/kgsl/kgsl/proc/5/gpumem_mapped", "0"),
+                ("sys/kernel/dmabuf/buffers/101/size", "8192"),
+                ("sys/kernel/dmabuf/buffers/101/exporter_name", "kgsl-3d0"),
+                ("sys/kernel/dmabuf/buffers/102/size", "4096"),
+                ("sys/kernel/dmabuf/buffers/102/exporter_name", "system"),
+                ("sys/kernel/dmabuf/buffers/103/size", "4096"),
+                ("sys/kernel/dmabuf/buffers/103/exporter_name", "kgsl-3d0"),
+                ("proc/1200/status", "Name:\tUnityMain\nUid:\t10234\t10234\n"),
+                ("proc/1200/cmdline", "com.example.game\0"),
+            ],
+        );
+        let (sys, proc) = (format!("{}/sys", root), format!("{}/proc", root));
+
+        let report = read_gpu_memory_at(&sys, &proc, 10);
+        assert_eq!(report.source, Some(GpuMemorySource::Kgsl));
+        assert_eq!(report.total_bytes, Some(300004096));
+        assert_eq!(report.processes.len(), 2);
+        assert_eq!(report.processes[0].pid, 900);
+        assert_eq!(report.processes[0].name, "pid 900");
+        let game = &report.processes[1];
+        assert_eq!(game.name, "com.example.game");
+        assert_eq!(game.uid, 10234);
+        assert_eq!(game.total_bytes, 8000);
+        assert_eq!(game.imported_bytes, Some(2000));
+
+        assert_eq!(report.dmabuf_total_bytes, Some(16384));
+        assert_eq!(report.dmabuf_exporters[0].name, "kgsl-3d0");
+        assert_eq!(report.dmabuf_exporters[0].buffers, 2);
+
+        assert_eq!(read_gpu_memory_at(&sys, &proc, 1).processes.len(), 1);
+    }
+
+    #[test]
+    fn test_mali_gpu_memory() {
+        let (total, mut processes) = parse_mali_gpu_memory(
+            "mali0                   51200\n\
+             \x20 kctx-0x00000000deadbeef       1024       2310\n\
+             \x20 kctx-0x00000000feedface        512       2310\n\
+             \x20 kctx-0x0000000012345678        256        811\n",
+        );
+        assert_eq!(total, Some(51200 * utils::page_size()));
+        processes.sort_by_key(|m| m.pid);
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[1].pid, 2310);
+        assert_eq!(processes[1].total, 1536 * utils::page_size());
+    }
+}
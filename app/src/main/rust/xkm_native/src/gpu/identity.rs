@@ -0,0 +1,445 @@
+use super::GpuVendor;
+use crate::devicetree::devicetree as dt;
+use crate::utils;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentitySource {
+    Kgsl,
+    Kbase,
+    DeviceTree,
+    Driver,
+    Properties,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuIdentity {
+    pub vendor: GpuVendor,
+    /// `Adreno 740`, `Mali-G710`, `Xclipse 920`, or just the family name when
+    /// the exact part is unknown.
+    pub model: String,
+    /// `Adreno 7xx`, `Valhall`, `RDNA 2`...
+    pub generation: Option<String>,
+    pub cores: Option<u32>,
+    /// Raw chip or product id, in hex.
+    pub id: Option<String>,
+    pub source: IdentitySource,
+}
+
+static IDENTITY: OnceCell<Option<GpuIdentity>> = OnceCell::new();
+
+/// Adreno 7xx and later chip ids no longer spell out the model.
+const ADRENO_CHIP_IDS: [(u32, u32); 5] = [
+    (0x43050a00, 740),
+    (0x43050a01, 740),
+    (0x43051401, 750),
+    (0x44050000, 830),
+    (0x44050001, 830),
+];
+
+/// kbase product ids (`gpu_id >> 16`, masked to arch major and product
+/// major).
+const MALI_PRODUCT_IDS: [(u32, &str); 15] = [
+    (0x6000, "Mali-G71"),
+    (0x6001, "Mali-G72"),
+    (0x7000, "Mali-G51"),
+    (0x7001, "Mali-G76"),
+    (0x7002, "Mali-G52"),
+    (0x7003, "Mali-G31"),
+    (0x9000, "Mali-G77"),
+    (0x9001, "Mali-G57"),
+    (0x9002, "Mali-G78"),
+    (0x9005, "Mali-G68"),
+    (0xa002, "Mali-G710"),
+    (0xa003, "Mali-G610"),
+    (0xa004, "Mali-G510"),
+    (0xb002, "Mali-G715"),
+    (0xc000, "Mali-G720"),
+];
+
+/// Exynos parts that ship each Xclipse, with its RDNA generation.
+const XCLIPSE_MODELS: [(&str, &str, &str); 3] = [
+    ("s5e9925", "Xclipse 920", "RDNA 2"),
+    ("s5e9935", "Xclipse 940", "RDNA 3"),
+    ("s5e9945", "Xclipse 950", "RDNA 3"),
+];
+
+fn adreno_generation(model: u32) -> String {
+    format!("Adreno {}xx", model / 100)
+}
+
+fn adreno(model: Option<u32>, id: Option<u32>, source: IdentitySource) -> GpuIdentity {
+    GpuIdentity {
+        vendor: GpuVendor::Qualcomm,
+        model: model.map_or_else(|| "Adreno".to_string(), |m| format!("Adreno {}", m)),
+        generation: model.map(adreno_generation),
+        cores: None,
+        id: id.map(|id| format!("0x{:08x}", id)),
+        source,
+    }
+}
+
+/// Legacy chip ids are `core.major.minor.patch`, one byte each: 0x06050002
+/// is Adreno 650.
+fn adreno_from_chip_id(id: u32) -> Option<u32> {
+    if let Some((_, model)) = ADRENO_CHIP_IDS.iter().find(|(chip, _)| *chip == id) {
+        return Some(*model);
+    }
+    let (core, major, minor) = (id >> 24, (id >> 16) & 0xff, (id >> 8) & 0xff);
+    ((3..=7).contains(&core) && major < 10 && minor < 10).then_some(core * 100 + major * 10 + minor)
+}
+
+/// `Adreno740v2`, `Adreno (TM) 650`, `740`.
+fn adreno_from_name(name: &str) -> Option<u32> {
+    let lower = name.to_lowercase();
+    let rest = lower
+        .split_once("adreno")
+        .map_or(lower.as_str(), |(_, r)| r);
+    let digits: String = rest
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|m| (200..1000).contains(m))
+}
+
+fn parse_id(text: &str) -> Option<u32> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn identify_kgsl(sys: &str) -> Option<GpuIdentity> {
+    let dir = format!("{}/class/kgsl/kgsl-3d0", sys);
+    if !utils::file_exists(&dir) {
+        return None;
+    }
+    let read = |node: &str| utils::read_sysfs_full(&format!("{}/{}", dir, node));
+
+    let chip_id = ["gpu_chip_id", "gpu_id"]
+        .iter()
+        .find_map(|node| parse_id(&read(node)?));
+    let model = read("gpu_model")
+        .and_then(|name| adreno_from_name(&name))
+        .or_else(|| {
+            let id = chip_id?;
+            if id < 1000 {
+                Some(id).filter(|m| *m >= 200)
+            } else {
+                adreno_from_chip_id(id)
+            }
+        });
+
+    Some(adreno(
+        model,
+        chip_id.filter(|id| *id >= 1000),
+        IdentitySource::Kgsl,
+    ))
+}
+
+fn mali_generation(model: &str) -> Option<String> {
+    let name = model.rsplit('-').next()?.to_uppercase();
+    let number: u32 = name.get(1..)?.parse().ok()?;
+    let generation = match (name.chars().next()?, number) {
+        ('T', _) => "Midgard",
+        ('G', 31 | 51 | 52 | 71 | 72 | 76) => "Bifrost",
+        ('G', 57 | 68 | 77 | 78 | 310 | 510 | 610 | 710 | 615 | 715) => "Valhall",
+        ('G', 620 | 720 | 625 | 725 | 925) => "5th Gen",
+        _ => return None,
+    };
+    Some(generation.to_string())
+}
+
+/// `Mali-G710 10 cores r0p0 0xA862`: name, core count, then the product id.
+fn parse_gpuinfo(text: &str) -> Option<GpuIdentity> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let product = words
+        .iter()
+        .rev()
+        .find_map(|w| w.strip_prefix("0x").or_else(|| w.strip_prefix("0X")))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+    let model = words
+        .iter()
+        .find(|w| {
+            let lower = w.to_lowercase();
+            lower.starts_with("mali-") || lower.starts_with("immortalis-")
+        })
+        .map(|w| w.to_string())
+        .or_else(|| {
+            let masked = product? & 0xf00f;
+            MALI_PRODUCT_IDS
+                .iter()
+                .find(|(id, _)| *id == masked)
+                .map(|(_, name)| name.to_string())
+        })?;
+
+    Some(GpuIdentity {
+        vendor: GpuVendor::Mali,
+        generation: mali_generation(&model),
+        model,
+        cores: words
+            .iter()
+            .position(|w| *w == "cores" || *w == "core")
+            .and_then(|i| words.get(i.checked_sub(1)?)?.parse().ok()),
+        id: product.map(|id| format!("0x{:04x}", id)),
+        source: IdentitySource::Kbase,
+    })
+}
+
+/// A kbase device without `gpuinfo` still pins the vendor.
+fn identify_mali(sys: &str) -> Option<GpuIdentity> {
+    let device = super::mali::find_device(sys)?;
+    utils::read_sysfs_full(&format!("{}/gpuinfo", device))
+        .and_then(|text| parse_gpuinfo(&text))
+        .or(Some(GpuIdentity {
+            vendor: GpuVendor::Mali,
+            model: "Mali".to_string(),
+            generation: None,
+            cores: None,
+            id: None,
+            source: IdentitySource::Kbase,
+        }))
+}
+
+/// The model comes from the SoC, since sgpu exposes no product id.
+fn xclipse(soc: &str, source: IdentitySource) -> GpuIdentity {
+    let soc = soc.to_lowercase();
+    let known = XCLIPSE_MODELS
+        .iter()
+        .find(|(part, _, _)| soc.contains(part));
+    GpuIdentity {
+        vendor: GpuVendor::Xclipse,
+        model: known.map_or("Xclipse", |(_, model, _)| model).to_string(),
+        generation: known.map(|(_, _, generation)| generation.to_string()),
+        cores: None,
+        id: None,
+        source,
+    }
+}
+
+/// `qcom,adreno-640`, `qcom,adreno-gpu-gen7-0-0`, `arm,mali-valhall`,
+/// `img,powervr-6xt`, `samsung,xclipse`.
+fn from_compatible(compatible: &str, soc: &str) -> Option<GpuIdentity> {
+    let source = IdentitySource::DeviceTree;
+    if let Some(rest) = compatible.strip_prefix("qcom,adreno") {
+        let mut identity = adreno(adreno_from_name(rest), None, source);
+        if identity.generation.is_none()
+            && let Some(generation) = rest.split('-').find_map(|part| part.strip_prefix("gen"))
+        {
+            identity.generation = Some(format!("Adreno {}xx", generation));
+        }
+        return Some(identity);
+    }
+    if compatible == "qcom,kgsl-3d0" {
+        return Some(adreno(None, None, source));
+    }
+    if let Some(rest) = compatible.strip_prefix("arm,mali-") {
+        let generation = match rest {
+            "midgard" | "bifrost" | "valhall" => {
+                let mut g = rest.to_string();
+                g[..1].make_ascii_uppercase();
+                Some(g)
+            }
+            _ => mali_generation(rest),
+        };
+        return Some(GpuIdentity {
+            vendor: GpuVendor::Mali,
+            model: if rest.starts_with(['g', 't']) && rest[1..].parse::<u32>().is_ok() {
+                format!("Mali-{}", rest.to_uppercase())
+            } else {
+                "Mali".to_string()
+            },
+            generation,
+            cores: None,
+            id: None,
+            source,
+        });
+    }
+    if let Some(rest) = compatible.strip_prefix("img,") {
+        let rest = rest.strip_prefix("powervr-").unwrap_or(rest);
+        return Some(GpuIdentity {
+            vendor: GpuVendor::PowerVR,
+            model: format!("PowerVR {}", rest.to_uppercase()),
+            generation: None,
+            cores: None,
+            id: None,
+            source,
+        });
+    }
+    if compatible.starts_with("samsung,xclipse") || compatible.starts_with("samsung,sgpu") {
+        return Some(xclipse(soc, source));
+    }
+    None
+}
+
+fn is_gpu_node(name: &str) -> bool {
+    ["gpu", "qcom,kgsl-3d0", "mali", "sgpu", "powervr", "img"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// GPU nodes sit at the top level or directly under `soc`.
+fn identify_device_tree(dt_root: &str) -> Option<GpuIdentity> {
+    let soc = dt::read_strings(dt_root, "compatible").join(" ");
+    let mut parents = vec![dt_root.to_string()];
+    parents.extend(
+        utils::list_dir(dt_root)
+            .into_iter()
+            .filter(|name| name.starts_with("soc"))
+            .map(|name| format!("{}/{}", dt_root, name)),
+    );
+
+    parents.iter().find_map(|parent| {
+        utils::list_dir(parent)
+            .into_iter()
+            .filter(|name| is_gpu_node(name))
+            .find_map(|name| {
+                dt::read_strings(&format!("{}/{}", parent, name), "compatible")
+                    .iter()
+                    .find_map(|c| from_compatible(c, &soc))
+            })
+    })
+}
+
+/// `ro.hardware.egl` / `ro.hardware.vulkan` only name the driver family.
+fn identify_properties(getprop: &dyn Fn(&str) -> Option<String>) -> Option<GpuIdentity> {
+    let soc = getprop("ro.soc.model").unwrap_or_default();
+    ["ro.hardware.egl", "ro.hardware.vulkan"]
+        .iter()
+        .filter_map(|key| getprop(key))
+        .find_map(|value| {
+            let value = value.to_lowercase();
+            let source = IdentitySource::Properties;
+            let family = |vendor, model: &str| GpuIdentity {
+                vendor,
+                model: model.to_string(),
+                generation: None,
+                cores: None,
+                id: None,
+                source,
+            };
+            if value.contains("adreno") {
+                Some(family(GpuVendor::Qualcomm, "Adreno"))
+            } else if value.contains("mali") {
+                Some(family(GpuVendor::Mali, "Mali"))
+            } else if value.contains("powervr") || value.contains("pvr") {
+                Some(family(GpuVendor::PowerVR, "PowerVR"))
+            } else if value.contains("xclipse") || value.contains("sgpu") {
+                Some(xclipse(&soc, source))
+            } else {
+                None
+            }
+        })
+}
+
+/// PowerVR and Xclipse drivers expose no id of their own, but their
+/// modules and Samsung's `kernel/gpu` interface still pin the vendor.
+fn identify_drivers(sys: &str, getprop: &dyn Fn(&str) -> Option<String>) -> Option<GpuIdentity> {
+    let exists = |rel: &str| utils::file_exists(&format!("{}/{}", sys, rel));
+    if exists("module/pvrsrvkm") || exists("kernel/debug/pvr") {
+        return Some(GpuIdentity {
+            vendor: GpuVendor::PowerVR,
+            model: "PowerVR".to_string(),
+            generation: None,
+            cores: None,
+            id: None,
+            source: IdentitySource::Driver,
+        });
+    }
+    if exists("module/sgpu") || exists("kernel/gpu/gpu_clock") {
+        let soc = getprop("ro.soc.model").unwrap_or_default();
+        return Some(xclipse(&soc, IdentitySource::Driver));
+    }
+    None
+}
+
+pub(crate) fn identify_at(
+    sys: &str,
+    dt_root: Option<&str>,
+    getprop: &dyn Fn(&str) -> Option<String>,
+) -> Option<GpuIdentity> {
+    identify_kgsl(sys)
+        .or_else(|| identify_mali(sys))
+        .or_else(|| identify_device_tree(dt_root?))
+        .or_else(|| identify_drivers(sys, getprop))
+        .or_else(|| identify_properties(getprop))
+}
+
+/// Vendor, exact model and generation from sysfs, the device tree and
+/// system properties, looked up once.
+pub fn get_gpu_identity() -> Option<GpuIdentity> {
+    IDENTITY
+        .get_or_init(|| identify_at(SYS_ROOT, dt::dt_root(), &utils::get_system_property))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn no_props(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_adreno_ids() {
+        assert_eq!(adreno_from_chip_id(0x43050a01), Some(740));
+        assert_eq!(adreno_from_chip_id(0x06050002), Some(650));
+        assert_eq!(adreno_from_chip_id(0x06010800), Some(618));
+        assert_eq!(adreno_from_name("Adreno740v2"), Some(740));
+        assert_eq!(adreno_from_name("Adreno (TM) 650"), Some(650));
+
+        let sys = fixture::build(
+            "gpu_identity_kgsl",
+            &[("class/kgsl/kgsl-3d0/gpu_chip_id", "0x43050a01")],
+        );
+        let identity = identify_at(&sys, None, &no_props).unwrap();
+        assert_eq!(identity.vendor, GpuVendor::Qualcomm);
+        assert_eq!(identity.model, "Adreno 740");
+        assert_eq!(identity.generation.as_deref(), Some("Adreno 7xx"));
+        assert_eq!(identity.id.as_deref(), Some("0x43050a01"));
+    }
+
+    #[test]
+    fn test_mali_gpuinfo() {
+        let identity = parse_gpuinfo("Mali-G710 10 cores r0p0 0xA862").unwrap();
+        assert_eq!(identity.model, "Mali-G710");
+        assert_eq!(identity.cores, Some(10));
+        assert_eq!(identity.generation.as_deref(), Some("Valhall"));
+
+        let identity = parse_gpuinfo("7 cores r1p0 0x7212").unwrap();
+        assert_eq!(identity.model, "Mali-G52");
+        assert_eq!(identity.generation.as_deref(), Some("Bifrost"));
+    }
+
+    #[test]
+    fn test_device_tree_and_properties() {
+        let dt = fixture::build_bytes(
+            "gpu_identity_dt",
+            &[
+                ("compatible", b"samsung,s5e9925\0".to_vec()),
+                (
+                    "soc/sgpu@10300000/compatible",
+                    b"samsung,xclipse\0".to_vec(),
+                ),
+            ],
+        );
+        let sys = fixture::build("gpu_identity_empty", &[]);
+        let identity = identify_at(&sys, Some(&dt), &no_props).unwrap();
+        assert_eq!(identity.vendor, GpuVendor::Xclipse);
+        assert_eq!(identity.model, "Xclipse 920");
+        assert_eq!(identity.source, IdentitySource::DeviceTree);
+
+        let props = |key: &str| (key == "ro.hardware.egl").then(|| "mali".to_string());
+        let identity = identify_at(&sys, None, &props).unwrap();
+        assert_eq!(identity.vendor, GpuVendor::Mali);
+        assert_eq!(identity.source, IdentitySource::Properties);
+    }
+}
@@ -0,0 +1,442 @@
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+
+const KGSL_ROOT: &str = "/sys/class/kgsl/kgsl-3d0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KgslParam {
+    MinFreq,
+    MaxFreq,
+    MaxPwrlevel,
+    MinPwrlevel,
+    DefaultPwrlevel,
+    Governor,
+    Throttling,
+    ForceClkOn,
+    ForceBusOn,
+    IdleTimer,
+}
+
+impl KgslParam {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    fn node(self) -> &'static str {
+        match self {
+            KgslParam::MinFreq => "devfreq/min_freq",
+            KgslParam::MaxFreq => "devfreq/max_freq",
+            KgslParam::MaxPwrlevel => "max_pwrlevel",
+            KgslParam::MinPwrlevel => "min_pwrlevel",
+            KgslParam::DefaultPwrlevel => "default_pwrlevel",
+            KgslParam::Governor => "devfreq/governor",
+            KgslParam::Throttling => "throttling",
+            KgslParam::ForceClkOn => "force_clk_on",
+            KgslParam::ForceBusOn => "force_bus_on",
+            KgslParam::IdleTimer => "idle_timer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KgslState {
+    /// `gpu_available_frequencies` in power-level order: level 0 is the
+    /// fastest.
+    pub levels_mhz: Vec<i32>,
+    pub min_freq_mhz: Option<i32>,
+    pub max_freq_mhz: Option<i32>,
+    pub max_pwrlevel: Option<i32>,
+    pub min_pwrlevel: Option<i32>,
+    pub default_pwrlevel: Option<i32>,
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+    pub throttling: Option<bool>,
+    pub force_clk_on: Option<bool>,
+    pub force_bus_on: Option<bool>,
+    pub idle_timer_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KgslWrite {
+    pub param: KgslParam,
+    pub requested: String,
+    pub result: WriteResult,
+    /// Frequency the setting ended up at, for the frequency and power-level
+    /// params.
+    pub freq_mhz: Option<i32>,
+    /// The level table or the kernel moved the value away from the request.
+    pub clamped: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KgslRangeWrite {
+    pub writes: Vec<KgslWrite>,
+    /// Nodes that could not be written, as `node: reason`.
+    pub errors: Vec<String>,
+}
+
+pub(crate) fn hz_to_mhz(hz: i64) -> i32 {
+    (hz / 1_000_000) as i32
+}
+
+/// Level table in Hz. kgsl lists it fastest first, and that order is the
+/// power-level index.
+pub(crate) fn levels_at(dir: &str) -> Vec<i64> {
+    utils::read_sysfs_full(&format!("{}/gpu_available_frequencies", dir))
+        .map(|v| {
+            v.split_whitespace()
+                .filter_map(|f| f.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Level whose frequency honours `mhz` as a cap (fastest level not above it)
+/// or as a floor (slowest level not below it). Out-of-range requests take
+/// the nearest end of the table.
+fn level_for(levels: &[i64], mhz: i32, cap: bool) -> Option<usize> {
+    let fits = |hz: &i64| {
+        if cap {
+            hz_to_mhz(*hz) <= mhz
+        } else {
+            hz_to_mhz(*hz) >= mhz
+        }
+    };
+    let mut ranked: Vec<(usize, &i64)> = levels.iter().enumerate().collect();
+    ranked.sort_by_key(|(_, hz)| **hz);
+
+    let pick = if cap {
+        ranked
+            .iter()
+            .rev()
+            .find(|(_, hz)| fits(hz))
+            .or(ranked.first())
+    } else {
+        ranked.iter().find(|(_, hz)| fits(hz)).or(ranked.last())
+    };
+    pick.map(|(level, _)| *level)
+}
+
+fn nearest_level(levels: &[i64], mhz: i32) -> Option<usize> {
+    levels
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, hz)| (hz_to_mhz(**hz) - mhz).abs())
+        .map(|(level, _)| level)
+}
+
+pub(crate) fn read_kgsl_state_at(dir: &str) -> Option<KgslState> {
+    if !utils::file_exists(dir) {
+        return None;
+    }
+    let flag =
+        |node: &str| utils::read_sysfs_num::<i64>(&format!("{}/{}", dir, node)).map(|v| v != 0);
+
+    Some(KgslState {
+        levels_mhz: levels_at(dir).into_iter().map(hz_to_mhz).collect(),
+        min_freq_mhz: utils::read_sysfs_num::<i64>(&format!("{}/devfreq/min_freq", dir))
+            .map(hz_to_mhz),
+        max_freq_mhz: utils::read_sysfs_num::<i64>(&format!("{}/devfreq/max_freq", dir))
+            .map(hz_to_mhz),
+        max_pwrlevel: utils::read_sysfs_num::<i64>(&format!("{}/max_pwrlevel", dir))
+            .map(|v| v as i32),
+        min_pwrlevel: utils::read_sysfs_num::<i64>(&format!("{}/min_pwrlevel", dir))
+            .map(|v| v as i32),
+        default_pwrlevel: utils::read_sysfs_num::<i64>(&format!("{}/default_pwrlevel", dir))
+            .map(|v| v as i32),
+        governor: utils::read_sysfs_full(&format!("{}/devfreq/governor", dir)),
+        available_governors: utils::read_sysfs_full(&format!(
+            "{}/devfreq/available_governors",
+            dir
+        ))
+        .map(|v| v.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default(),
+        throttling: flag("throttling"),
+        force_clk_on: flag("force_clk_on"),
+        force_bus_on: flag("force_bus_on"),
+        idle_timer_ms: utils::read_sysfs_num::<i64>(&format!("{}/idle_timer", dir)),
+    })
+}
+
+/// Frequency and power-level params take MHz. Frequencies snap to the level
+/// table; power levels are resolved from it.
+fn set_freq_at(dir: &str, param: KgslParam, mhz: i32) -> Result<KgslWrite, String> {
+    if mhz <= 0 {
+        return Err(format!("Invalid frequency: {} MHz", mhz));
+    }
+    let levels = levels_at(dir);
+    let path = format!("{}/{}", dir, param.node());
+    if !utils::file_exists(&path) {
+        return Err(format!("{} is not available", param.node()));
+    }
+
+    let (value, target) = match param {
+        KgslParam::MinFreq | KgslParam::MaxFreq => {
+            let cap = param == KgslParam::MaxFreq;
+            match level_for(&levels, mhz, cap) {
+                Some(level) => (levels[level].to_string(), hz_to_mhz(levels[level])),
+                None => ((mhz as i64 * 1_000_000).to_string(), mhz),
+            }
+        }
+        KgslParam::MaxPwrlevel | KgslParam::MinPwrlevel | KgslParam::DefaultPwrlevel => {
+            let level = match param {
+                KgslParam::MaxPwrlevel => level_for(&levels, mhz, true),
+                KgslParam::MinPwrlevel => level_for(&levels, mhz, false),
+                _ => nearest_level(&levels, mhz),
+            }
+            .ok_or("gpu_available_frequencies is missing; power levels cannot be resolved")?;
+            (level.to_string(), hz_to_mhz(levels[level]))
+        }
+        _ => return Err(format!("{:?} does not take a frequency", param)),
+    };
+
+    let result = utils::write_sysfs_verified(&path, &value);
+    let actual: Option<i64> = result.actual.parse().ok();
+    let freq_mhz = match param {
+        KgslParam::MinFreq | KgslParam::MaxFreq => actual.map(hz_to_mhz),
+        _ => actual
+            .and_then(|level| levels.get(level as usize))
+            .map(|hz| hz_to_mhz(*hz)),
+    };
+
+    Ok(KgslWrite {
+        param,
+        requested: mhz.to_string(),
+        clamped: target != mhz || freq_mhz != Some(target),
+        freq_mhz,
+        result,
+    })
+}
+
+fn set_plain_at(dir: &str, param: KgslParam, value: &str) -> Result<KgslWrite, String> {
+    let value = value.trim();
+    let path = format!("{}/{}", dir, param.node());
+    if !utils::file_exists(&path) {
+        return Err(format!("{} is not available", param.node()));
+    }
+
+    let written = match param {
+        KgslParam::Governor => {
+            let available: Vec<String> =
+                utils::read_sysfs_full(&format!("{}/devfreq/available_governors", dir))
+                    .map(|v| v.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default();
+            if !available.is_empty() && !available.iter().any(|g| g == value) {
+                return Err(format!(
+                    "Governor {} is not one of: {}",
+                    value,
+                    available.join(" ")
+                ));
+            }
+            value.to_string()
+        }
+        KgslParam::Throttling | KgslParam::ForceClkOn | KgslParam::ForceBusOn => match value {
+            "1" | "true" => "1".to_string(),
+            "0" | "false" => "0".to_string(),
+            _ => return Err(format!("{} expects 0 or 1", param.node())),
+        },
+        KgslParam::IdleTimer => {
+            let ms: u32 = value
+                .parse()
+                .map_err(|_| format!("Invalid idle_timer: {}", value))?;
+            ms.to_string()
+        }
+        _ => return Err(format!("{:?} takes a frequency in MHz", param)),
+    };
+
+    let result = utils::write_sysfs_verified(&path, &written);
+    Ok(KgslWrite {
+        param,
+        requested: value.to_string(),
+        clamped: result.actual != written,
+        freq_mhz: None,
+        result,
+    })
+}
+
+pub(crate) fn set_kgsl_param_at(
+    dir: &str,
+    param: KgslParam,
+    value: &str,
+) -> Result<KgslWrite, String> {
+    match param {
+        KgslParam::MinFreq
+        | KgslParam::MaxFreq
+        | KgslParam::MaxPwrlevel
+        | KgslParam::MinPwrlevel
+        | KgslParam::DefaultPwrlevel => {
+            let mhz = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid frequency: {}", value))?;
+            set_freq_at(dir, param, mhz)
+        }
+        _ => set_plain_at(dir, param, value),
+    }
+}
+
+/// Caps and floors the GPU through devfreq and the matching power levels.
+/// The side that moves away from the current range is written first so the
+/// kernel never sees min above max. Fails only when no node was written.
+pub(crate) fn set_freq_range_at(
+    dir: &str,
+    min_mhz: i32,
+    max_mhz: i32,
+) -> Result<KgslRangeWrite, String> {
+    if min_mhz > max_mhz {
+        return Err(format!("Min {} MHz is above max {} MHz", min_mhz, max_mhz));
+    }
+
+    let current_max = utils::read_sysfs_num::<i64>(&format!("{}/devfreq/max_freq", dir))
+        .map(hz_to_mhz)
+        .unwrap_or(i32::MAX);
+    let raising = min_mhz > current_max;
+    let mut order = vec![
+        (KgslParam::MinFreq, min_mhz),
+        (KgslParam::MinPwrlevel, min_mhz),
+        (KgslParam::MaxFreq, max_mhz),
+        (KgslParam::MaxPwrlevel, max_mhz),
+    ];
+    if raising {
+        order.reverse();
+    }
+
+    let has_levels = !levels_at(dir).is_empty();
+    let mut report = KgslRangeWrite {
+        writes: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (param, mhz) in order
+        .into_iter()
+        .filter(|(param, _)| has_levels || matches!(param, KgslParam::MinFreq | KgslParam::MaxFreq))
+    {
+        match set_freq_at(dir, param, mhz) {
+            Ok(write) => report.writes.push(write),
+            Err(error) => report.errors.push(format!("{}: {}", param.node(), error)),
+        }
+    }
+
+    if report.writes.is_empty() {
+        return Err(format!(
+            "No kgsl frequency nodes are writable ({})",
+            report.errors.join("; ")
+        ));
+    }
+    Ok(report)
+}
+
+pub fn read_kgsl_state() -> Option<KgslState> {
+    read_kgsl_state_at(KGSL_ROOT)
+}
+
+/// Writes one kgsl param and reads it back. Frequencies and power levels
+/// take MHz, flags `0`/`1`, `idle_timer` milliseconds.
+pub fn set_kgsl_param(param: KgslParam, value: &str) -> Result<KgslWrite, String> {
+    set_kgsl_param_at(KGSL_ROOT, param, value)
+}
+
+pub fn set_kgsl_freq_range(min_mhz: i32, max_mhz: i32) -> Result<KgslRangeWrite, String> {
+    set_freq_range_at(KGSL_ROOT, min_mhz, max_mhz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn build(name: &str) -> String {
+        fixture::build(
+            name,
+            &[
+                (
+                    "gpu_available_frequencies",
+                    "900000000 680000000 500000000 305000000",
+                ),
+                ("max_pwrlevel", "0"),
+                ("min_pwrlevel", "3"),
+                ("default_pwrlevel", "2"),
+                ("throttling", "1"),
+                ("idle_timer", "80"),
+                ("devfreq/min_freq", "305000000"),
+                ("devfreq/max_freq", "900000000"),
+                ("devfreq/governor", "msm-adreno-tz"),
+                (
+                    "devfreq/available_governors",
+                    "msm-adreno-tz performance powersave",
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_level_mapping() {
+        let levels = [900_000_000, 680_000_000, 500_000_000, 305_000_000];
+        assert_eq!(level_for(&levels, 700, true), Some(1));
+        assert_eq!(level_for(&levels, 680, true), Some(1));
+        assert_eq!(level_for(&levels, 100, true), Some(3));
+        assert_eq!(level_for(&levels, 400, false), Some(2));
+        assert_eq!(level_for(&levels, 2000, false), Some(0));
+        assert_eq!(nearest_level(&levels, 520), Some(2));
+    }
+
+    #[test]
+    fn test_set_kgsl_params() {
+        let dir = build("kgsl_params");
+
+        let cap = set_kgsl_param_at(&dir, KgslParam::MaxFreq, "700").unwrap();
+        assert_eq!(cap.result.actual, "680000000");
+        assert_eq!(cap.freq_mhz, Some(680));
+        assert!(cap.clamped);
+
+        let level = set_kgsl_param_at(&dir, KgslParam::MinPwrlevel, "500").unwrap();
+        assert_eq!(level.result.actual, "2");
+        assert_eq!(level.freq_mhz, Some(500));
+        assert!(!level.clamped);
+
+        assert!(set_kgsl_param_at(&dir, KgslParam::Governor, "ondemand").is_err());
+        assert!(
+            set_kgsl_param_at(&dir, KgslParam::Throttling, "false")
+                .unwrap()
+                .result
+                .success
+        );
+        assert!(set_kgsl_param_at(&dir, KgslParam::ForceClkOn, "1").is_err());
+
+        let state = read_kgsl_state_at(&dir).unwrap();
+        assert_eq!(state.levels_mhz, vec![900, 680, 500, 305]);
+        assert_eq!(state.max_freq_mhz, Some(680));
+        assert_eq!(state.min_pwrlevel, Some(2));
+        assert_eq!(state.throttling, Some(false));
+    }
+
+    #[test]
+    fn test_set_freq_range() {
+        let dir = build("kgsl_range");
+        set_freq_range_at(&dir, 305, 500).unwrap();
+
+        // Raising the floor above the current cap writes the cap first.
+        let report = set_freq_range_at(&dir, 680, 900).unwrap();
+        assert_eq!(report.writes[0].param, KgslParam::MaxPwrlevel);
+        assert_eq!(report.writes.len(), 4);
+        assert!(report.errors.is_empty());
+
+        let state = read_kgsl_state_at(&dir).unwrap();
+        assert_eq!(
+            (state.min_freq_mhz, state.max_freq_mhz),
+            (Some(680), Some(900))
+        );
+        assert_eq!((state.max_pwrlevel, state.min_pwrlevel), (Some(0), Some(1)));
+    }
+
+    #[test]
+    fn test_set_freq_range_reports_missing_nodes() {
+        let dir = build("kgsl_range_missing");
+        std::fs::remove_file(format!("{}/max_pwrlevel", dir)).unwrap();
+
+        let report = set_freq_range_at(&dir, 305, 680).unwrap();
+        assert_eq!(report.writes.len(), 3);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("max_pwrlevel:"));
+    }
+}
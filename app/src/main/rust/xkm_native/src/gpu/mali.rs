@@ -0,0 +1,593 @@
+use crate::utils::{self, WriteResult, from_mhz, to_mhz};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaliParam {
+    MinFreq,
+    MaxFreq,
+    Governor,
+    DvfsPeriod,
+    CoreMask,
+    PowerPolicy,
+}
+
+impl MaliParam {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaliCoreMask {
+    /// Mask in use (JS0 on job-manager GPUs, the desired mask on CSF ones).
+    pub current: Option<u64>,
+    pub available: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaliState {
+    /// kbase platform device directory.
+    pub device: String,
+    /// devfreq directory, when the vendor registers one.
+    pub devfreq: Option<String>,
+    pub gpuinfo: Option<String>,
+    /// Ascending.
+    pub available_frequencies_mhz: Vec<i32>,
+    pub cur_freq_mhz: Option<i32>,
+    pub min_freq_mhz: Option<i32>,
+    pub max_freq_mhz: Option<i32>,
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+    pub dvfs_period_ms: Option<i64>,
+    pub core_mask: Option<MaliCoreMask>,
+    pub power_policy: Option<String>,
+    pub available_power_policies: Vec<String>,
+    pub utilization: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaliWrite {
+    pub param: MaliParam,
+    pub requested: String,
+    pub result: WriteResult,
+    /// Frequency the setting ended up at, for the frequency params.
+    pub freq_mhz: Option<i32>,
+    /// The frequency table or the kernel moved the value away from the
+    /// request.
+    pub clamped: bool,
+}
+
+/// Where each readable attribute lives on this device. Vendors split them
+/// between devfreq and the kbase device and disagree on names and units.
+#[derive(Debug, Clone, Default)]
+struct MaliNodes {
+    device: String,
+    devfreq: Option<String>,
+    frequencies: Option<String>,
+    cur_freq: Option<String>,
+    min_freq: Option<String>,
+    max_freq: Option<String>,
+    governor: Option<String>,
+    available_governors: Option<String>,
+}
+
+static MALI_NODES: OnceCell<Option<MaliNodes>> = OnceCell::new();
+
+fn has_mali_name(name: &str) -> bool {
+    name.to_lowercase().contains("mali")
+}
+
+/// kbase device: the `mali0` misc device's parent, else a platform device
+/// named after the GPU (`1c500000.mali`, `13000000.mali`, `mali`).
+pub(crate) fn find_device(sys: &str) -> Option<String> {
+    let misc = format!("{}/class/misc/mali0/device", sys);
+    if utils::file_exists(&misc) {
+        return Some(misc);
+    }
+
+    ["devices/platform", "devices/platform/soc"]
+        .iter()
+        .find_map(|parent| {
+            let parent = format!("{}/{}", sys, parent);
+            utils::list_dir(&parent)
+                .into_iter()
+                .find(|name| {
+                    has_mali_name(name)
+                        && utils::file_exists(&format!("{}/{}/gpuinfo", parent, name))
+                })
+                .or_else(|| {
+                    utils::list_dir(&parent)
+                        .into_iter()
+                        .find(|name| has_mali_name(name))
+                })
+                .map(|name| format!("{}/{}", parent, name))
+        })
+}
+
+fn find_devfreq(sys: &str, device: &str) -> Option<String> {
+    if let Some(dir) = crate::devfreq::find_devfreq_at(sys, has_mali_name) {
+        return Some(dir);
+    }
+
+    let nested = format!("{}/devfreq", device);
+    utils::list_dir(&nested)
+        .into_iter()
+        .next()
+        .map(|name| format!("{}/{}", nested, name))
+}
+
+fn discover_at(sys: &str) -> Option<MaliNodes> {
+    let device = find_device(sys)?;
+    let devfreq = find_devfreq(sys, &device);
+
+    let pick = |names: &[(&str, bool)]| {
+        names.iter().find_map(|(name, on_devfreq)| {
+            let dir = if *on_devfreq {
+                devfreq.as_deref()?
+            } else {
+                device.as_str()
+            };
+            let path = format!("{}/{}", dir, name);
+            utils::file_exists(&path).then_some(path)
+        })
+    };
+
+    Some(MaliNodes {
+        frequencies: pick(&[
+            ("available_frequencies", true),
+            ("available_frequencies", false),
+            ("dvfs_table", false),
+        ]),
+        cur_freq: pick(&[("cur_freq", true), ("cur_freq", false), ("clock", false)]),
+        min_freq: pick(&[
+            ("min_freq", true),
+            ("scaling_min_freq", false),
+            ("dvfs_min_lock", false),
+        ]),
+        max_freq: pick(&[
+            ("max_freq", true),
+            ("scaling_max_freq", false),
+            ("dvfs_max_lock", false),
+        ]),
+        governor: pick(&[
+            ("governor", true),
+            ("governor", false),
+            ("dvfs_governor", false),
+        ]),
+        available_governors: pick(&[
+            ("available_governors", true),
+            ("available_governors", false),
+        ]),
+        device,
+        devfreq,
+    })
+}
+
+fn nodes() -> Option<&'static MaliNodes> {
+    MALI_NODES.get_or_init(|| discover_at(SYS_ROOT)).as_ref()
+}
+
+fn read_raw(path: Option<&String>) -> Option<String> {
+    utils::read_sysfs_full(path?)
+}
+
+fn read_number(path: Option<&String>) -> Option<u64> {
+    read_raw(path)?.split_whitespace().next()?.parse().ok()
+}
+
+/// Every number in the table, in its native unit. Exynos prints
+/// `dvfs_table` on one line; other vendors put a row per level with extra
+/// columns, and then only the first number of each row counts.
+fn read_table(path: Option<&String>) -> Vec<u64> {
+    let Some(text) = read_raw(path) else {
+        return Vec::new();
+    };
+    let per_row = path.is_some_and(|p| p.ends_with("dvfs_table"))
+        && text.lines().filter(|line| !line.trim().is_empty()).count() > 1;
+    let mut values: Vec<u64> = if per_row {
+        text.lines()
+            .filter_map(|line| line.split_whitespace().next()?.parse().ok())
+            .collect()
+    } else {
+        text.split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect()
+    };
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+/// `[coarse_demand] always_on`: the bracketed entry is active.
+pub(crate) fn parse_bracketed(text: &str) -> (Option<String>, Vec<String>) {
+    let mut current = None;
+    let all = text
+        .split_whitespace()
+        .map(|word| {
+            let name = word.trim_start_matches('[').trim_end_matches(']');
+            if word.starts_with('[') {
+                current = Some(name.to_string());
+            }
+            name.to_string()
+        })
+        .collect();
+    (current, all)
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    let hex = text.trim();
+    u64::from_str_radix(hex.strip_prefix("0x").unwrap_or(hex), 16).ok()
+}
+
+/// `Current core mask (JS0) : 0x1f` / `Current desired core mask : 0xff`
+/// lines, then `Available core mask : 0x1f`.
+fn parse_core_mask(text: &str) -> MaliCoreMask {
+    let value = |line: &str| parse_hex(line.rsplit(':').next()?);
+    let lines: Vec<&str> = text.lines().collect();
+    let current = lines
+        .iter()
+        .find(|line| line.starts_with("Current desired") || line.starts_with("Current core mask"))
+        .and_then(|line| value(line))
+        .or_else(|| {
+            if lines.len() == 1 {
+                parse_hex(lines[0])
+            } else {
+                None
+            }
+        });
+
+    MaliCoreMask {
+        current,
+        available: lines
+            .iter()
+            .find(|line| line.starts_with("Available"))
+            .and_then(|line| value(line)),
+    }
+}
+
+fn read_state(nodes: &MaliNodes) -> MaliState {
+    let device = |name: &str| format!("{}/{}", nodes.device, name);
+    let freq = |path: Option<&String>| read_number(path).map(to_mhz);
+    let (power_policy, available_power_policies) = utils::read_sysfs_full(&device("power_policy"))
+        .map(|text| parse_bracketed(&text))
+        .unwrap_or_default();
+
+    MaliState {
+        device: nodes.device.clone(),
+        devfreq: nodes.devfreq.clone(),
+        gpuinfo: utils::read_sysfs_full(&device("gpuinfo")),
+        available_frequencies_mhz: read_table(nodes.frequencies.as_ref())
+            .into_iter()
+            .map(to_mhz)
+            .collect(),
+        cur_freq_mhz: freq(nodes.cur_freq.as_ref()),
+        min_freq_mhz: freq(nodes.min_freq.as_ref()),
+        max_freq_mhz: freq(nodes.max_freq.as_ref()),
+        governor: read_raw(nodes.governor.as_ref()),
+        available_governors: read_raw(nodes.available_governors.as_ref())
+            .map(|v| v.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        dvfs_period_ms: utils::read_sysfs_full(&device("dvfs_period"))
+            .and_then(|v| v.trim().parse().ok()),
+        core_mask: utils::read_sysfs_full(&device("core_mask")).map(|text| parse_core_mask(&text)),
+        power_policy,
+        available_power_policies,
+        utilization: utils::read_sysfs_full(&device("utilization"))
+            .and_then(|v| v.trim().parse().ok()),
+    }
+}
+
+/// Snaps to the table (highest entry not above a cap, lowest not below a
+/// floor) and writes it in the node's own unit.
+fn set_freq(nodes: &MaliNodes, param: MaliParam, mhz: i32) -> Result<MaliWrite, String> {
+    let cap = param == MaliParam::MaxFreq;
+    let path = if cap {
+        &nodes.max_freq
+    } else {
+        &nodes.min_freq
+    }
+    .as_ref()
+    .ok_or("This Mali driver exposes no frequency limit node")?;
+    if mhz <= 0 {
+        return Err(format!("Invalid frequency: {} MHz", mhz));
+    }
+
+    let table = read_table(nodes.frequencies.as_ref());
+    let snapped = if cap {
+        table
+            .iter()
+            .rev()
+            .find(|f| to_mhz(**f) <= mhz)
+            .or(table.first())
+    } else {
+        table.iter().find(|f| to_mhz(**f) >= mhz).or(table.last())
+    };
+    let sample = snapped
+        .copied()
+        .or_else(|| read_number(Some(path)))
+        .unwrap_or(0);
+    let value = snapped.copied().unwrap_or_else(|| from_mhz(mhz, sample));
+
+    let result = utils::write_sysfs_verified(path, &value.to_string());
+    let freq_mhz = result
+        .actual
+        .split_whitespace()
+        .next()
+        .and_then(|v| v.parse().ok())
+        .map(to_mhz);
+
+    Ok(MaliWrite {
+        param,
+        requested: mhz.to_string(),
+        clamped: freq_mhz != Some(mhz),
+        freq_mhz,
+        result,
+    })
+}
+
+fn set_param(nodes: &MaliNodes, param: MaliParam, value: &str) -> Result<MaliWrite, String> {
+    let value = value.trim();
+    let state = read_state(nodes);
+    let device = |name: &str| format!("{}/{}", nodes.device, name);
+
+    let (path, written) = match param {
+        MaliParam::MinFreq | MaliParam::MaxFreq => {
+            let mhz = value
+                .parse()
+                .map_err(|_| format!("Invalid frequency: {}", value))?;
+            return set_freq(nodes, param, mhz);
+        }
+        MaliParam::Governor => {
+            let path = nodes
+                .governor
+                .clone()
+                .ok_or("This Mali driver has no governor node")?;
+            if !state.available_governors.is_empty()
+                && !state.available_governors.iter().any(|g| g == value)
+            {
+                return Err(format!(
+                    "Governor {} is not one of: {}",
+                    value,
+                    state.available_governors.join(" ")
+                ));
+            }
+            (path, value.to_string())
+        }
+        MaliParam::DvfsPeriod => {
+            let ms: u32 = value
+                .parse()
+                .map_err(|_| format!("Invalid dvfs_period: {}", value))?;
+            if ms == 0 {
+                return Err("dvfs_period must be at least 1 ms".to_string());
+            }
+            (device("dvfs_period"), ms.to_string())
+        }
+        MaliParam::CoreMask => {
+            let mask = parse_hex(value)
+                .filter(|m| *m != 0)
+                .ok_or(format!("Invalid core mask: {}", value))?;
+            if let Some(available) = state.core_mask.as_ref().and_then(|m| m.available)
+                && mask & !available != 0
+            {
+                return Err(format!(
+                    "Core mask {:#x} is outside the available {:#x}",
+                    mask, available
+                ));
+            }
+            (device("core_mask"), format!("{:#x}", mask))
+        }
+        MaliParam::PowerPolicy => {
+            if !state.available_power_policies.iter().any(|p| p == value) {
+                return Err(format!(
+                    "Power policy {} is not one of: {}",
+                    value,
+                    state.available_power_policies.join(" ")
+                ));
+            }
+            (device("power_policy"), value.to_string())
+        }
+    };
+
+    if !utils::file_exists(&path) {
+        return Err(format!("{} is not available", path));
+    }
+    let mut result = utils::write_sysfs_verified(&path, &written);
+
+    // core_mask and power_policy read back in their own formats.
+    let kept = match param {
+        MaliParam::CoreMask => parse_core_mask(&result.actual).current == parse_hex(&written),
+        MaliParam::PowerPolicy => {
+            parse_bracketed(&result.actual)
+                .0
+                .as_deref()
+                .unwrap_or(&result.actual)
+                == value
+        }
+        _ => result.success,
+    };
+    result.success = kept;
+
+    Ok(MaliWrite {
+        param,
+        requested: value.to_string(),
+        clamped: !kept,
+        freq_mhz: None,
+        result,
+    })
+}
+
+/// Current clock without reading the rest of the state, for polling.
+pub fn read_mali_cur_freq() -> Option<i32> {
+    read_number(nodes()?.cur_freq.as_ref()).map(to_mhz)
+}
+
+pub fn read_mali_utilization() -> Option<i32> {
+    utils::read_sysfs_full(&format!("{}/utilization", nodes()?.device))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub fn read_mali_state() -> Option<MaliState> {
+    nodes().map(read_state)
+}
+
+/// Writes one Mali param and reads it back. Frequencies take MHz and snap
+/// to the table, `core_mask` a hex mask within the available cores.
+pub fn set_mali_param(param: MaliParam, value: &str) -> Result<MaliWrite, String> {
+    set_param(nodes().ok_or("No Mali GPU found")?, param, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_tensor_gs101() {
+        let dev = "devices/platform/1c500000.mali";
+        let files: Vec<(String, &str)> = vec![
+            (format!("{}/gpuinfo", dev), "Mali-G78 20 cores r1p1 0x9086"),
+            (
+                format!("{}/available_frequencies", dev),
+                "151000 202000 251000 302000 351000 400000 471000 510000 572000 701000 762000 848000",
+            ),
+            (format!("{}/cur_freq", dev), "400000"),
+            (format!("{}/scaling_min_freq", dev), "151000"),
+            (format!("{}/scaling_max_freq", dev), "848000"),
+            (format!("{}/governor", dev), "interactive"),
+            (
+                format!("{}/available_governors", dev),
+                "interactive quickstep basic",
+            ),
+            (format!("{}/dvfs_period", dev), "20"),
+            (
+                format!("{}/core_mask", dev),
+                "Current core mask (JS0) : 0xfffff\nCurrent core mask (JS1) : 0xfffff\nCurrent core mask (JS2) : 0xfffff\nAvailable core mask : 0xfffff",
+            ),
+            (
+                format!("{}/power_policy", dev),
+                "[adaptive] coarse_demand always_on",
+            ),
+            (format!("{}/utilization", dev), "37"),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        let sys = fixture::build("mali_gs101", &files);
+
+        let nodes = discover_at(&sys).unwrap();
+        assert!(nodes.device.ends_with("1c500000.mali"));
+        assert_eq!(nodes.devfreq, None);
+
+        let state = read_state(&nodes);
+        assert_eq!(state.available_frequencies_mhz.first(), Some(&151));
+        assert_eq!(state.available_frequencies_mhz.last(), Some(&848));
+        assert_eq!(state.cur_freq_mhz, Some(400));
+        assert_eq!(state.governor.as_deref(), Some("interactive"));
+        assert_eq!(state.core_mask.as_ref().unwrap().current, Some(0xfffff));
+        assert_eq!(state.power_policy.as_deref(), Some("adaptive"));
+        assert_eq!(state.utilization, Some(37));
+
+        let cap = set_param(&nodes, MaliParam::MaxFreq, "750").unwrap();
+        assert_eq!(cap.result.actual, "701000");
+        assert_eq!(cap.freq_mhz, Some(701));
+        assert!(cap.clamped);
+
+        assert!(set_param(&nodes, MaliParam::CoreMask, "0x1fffff").is_err());
+        assert!(set_param(&nodes, MaliParam::PowerPolicy, "demand").is_err());
+        assert!(
+            set_param(&nodes, MaliParam::Governor, "quickstep")
+                .unwrap()
+                .result
+                .success
+        );
+    }
+
+    #[test]
+    fn test_dimensity_mt6893() {
+        let dev = "devices/platform/13000000.mali";
+        let devfreq = "class/devfreq/13000000.mali";
+        let files: Vec<(String, &str)> = vec![
+            (format!("{}/gpuinfo", dev), "Mali-G77 9 cores r0p1 0x0901"),
+            (format!("{}/dvfs_period", dev), "100"),
+            (
+                format!("{}/core_mask", dev),
+                "Current core mask (JS0) : 0x1ff\nAvailable core mask : 0x1ff",
+            ),
+            (format!("{}/power_policy", dev), "[coarse_demand] always_on"),
+            (
+                format!("{}/available_frequencies", devfreq),
+                "886000000 831000000 776000000 666000000 551000000 458000000 351000000 218000000",
+            ),
+            (format!("{}/cur_freq", devfreq), "551000000"),
+            (format!("{}/min_freq", devfreq), "218000000"),
+            (format!("{}/max_freq", devfreq), "886000000"),
+            (format!("{}/governor", devfreq), "simple_ondemand"),
+            (
+                format!("{}/available_governors", devfreq),
+                "userspace powersave performance simple_ondemand",
+            ),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        let sys = fixture::build("mali_mt6893", &files);
+
+        let nodes = discover_at(&sys).unwrap();
+        assert!(nodes.devfreq.as_deref().unwrap().ends_with("13000000.mali"));
+
+        let state = read_state(&nodes);
+        assert_eq!(
+            state.available_frequencies_mhz,
+            vec![218, 351, 458, 551, 666, 776, 831, 886]
+        );
+        assert_eq!(state.cur_freq_mhz, Some(551));
+        assert_eq!(state.max_freq_mhz, Some(886));
+        assert_eq!(state.available_governors.len(), 4);
+        assert_eq!(state.dvfs_period_ms, Some(100));
+        assert_eq!(state.utilization, None);
+
+        let floor = set_param(&nodes, MaliParam::MinFreq, "400").unwrap();
+        assert_eq!(floor.result.actual, "458000000");
+        assert_eq!(floor.freq_mhz, Some(458));
+
+        let mask = set_param(&nodes, MaliParam::CoreMask, "0xff").unwrap();
+        // A plain file keeps the raw value, which still parses as the mask.
+        assert!(mask.result.success);
+        assert!(set_param(&nodes, MaliParam::DvfsPeriod, "0").is_err());
+        assert!(
+            set_param(&nodes, MaliParam::PowerPolicy, "always_on")
+                .unwrap()
+                .result
+                .success
+        );
+    }
+
+    #[test]
+    fn test_exynos_dvfs_table() {
+        let dev = "devices/platform/18500000.mali";
+        let files: Vec<(String, &str)> = vec![
+            (format!("{}/gpuinfo", dev), "Mali-G78 14 cores r1p1 0x9086"),
+            (
+                format!("{}/dvfs_table", dev),
+                "848000 762000 702000 572000 450000 302000 151000\n",
+            ),
+            (format!("{}/clock", dev), "572000"),
+            (format!("{}/dvfs_max_lock", dev), "848000"),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, v)| (p.as_str(), *v)).collect();
+        let sys = fixture::build("mali_exynos", &files);
+
+        let nodes = discover_at(&sys).unwrap();
+        let state = read_state(&nodes);
+        assert_eq!(
+            state.available_frequencies_mhz,
+            vec![151, 302, 450, 572, 702, 762, 848]
+        );
+        assert_eq!(state.cur_freq_mhz, Some(572));
+
+        let cap = set_param(&nodes, MaliParam::MaxFreq, "600").unwrap();
+        assert_eq!(cap.result.actual, "572000");
+    }
+}
@@ -1,2 +1,21 @@
+#[allow(clippy::module_inception)]
 mod gpu;
+pub mod gpulock;
+pub mod gpumem;
+pub mod identity;
+pub mod kgsl;
+pub mod mali;
+pub mod powervr;
+pub mod residency;
+pub mod throttle;
+pub mod xclipse;
 pub use gpu::*;
+pub use gpulock::*;
+pub use gpumem::*;
+pub use identity::*;
+pub use kgsl::*;
+pub use mali::*;
+pub use powervr::*;
+pub use residency::*;
+pub use throttle::*;
+pub use xclipse::*;
@@ -0,0 +1,166 @@
+use crate::devfreq::{self, DevfreqKind};
+use crate::mtk;
+use crate::utils::{self, to_mhz};
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerVrSource {
+    /// Unisoc registers the GPU with devfreq.
+    Devfreq,
+    /// MediaTek drives it through its own gpufreq driver.
+    MtkGpufreq,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerVrState {
+    pub source: PowerVrSource,
+    pub cur_freq_mhz: Option<i32>,
+    /// Ascending.
+    pub available_frequencies_mhz: Vec<i32>,
+    pub utilization: Option<i32>,
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+}
+
+fn find_devfreq(sys: &str) -> Option<String> {
+    devfreq::find_devfreq_at(sys, |name| DevfreqKind::from_name(name) == DevfreqKind::Gpu)
+}
+
+/// MediaTek GED first; the PowerVR debugfs status has a
+/// `GPU Utilisation: 23%` line.
+fn read_utilization_at(sys: &str) -> Option<i32> {
+    let ged = mtk::read_ged_at(sys).0;
+    ged.or_else(|| {
+        utils::read_sysfs_full(&format!("{}/kernel/debug/pvr/status", sys))?
+            .lines()
+            .find(|line| line.contains("Utilisation") || line.contains("Utilization"))
+            .and_then(|line| line.split_once(':'))
+            .and_then(|(_, value)| value.trim().trim_end_matches('%').trim().parse().ok())
+    })
+    .map(|v| v.clamp(0, 100))
+}
+
+pub(crate) fn read_powervr_state_at(sys: &str, proc: &str) -> Option<PowerVrState> {
+    let utilization = read_utilization_at(sys);
+
+    if let Some(dir) = find_devfreq(sys) {
+        let read = |node: &str| utils::read_sysfs_full(&format!("{}/{}", dir, node));
+        let mut available_frequencies_mhz: Vec<i32> = read("available_frequencies")
+            .map(|text| {
+                text.split_whitespace()
+                    .filter_map(|v| v.parse().ok())
+                    .map(to_mhz)
+                    .collect()
+            })
+            .unwrap_or_default();
+        available_frequencies_mhz.sort_unstable();
+
+        return Some(PowerVrState {
+            source: PowerVrSource::Devfreq,
+            cur_freq_mhz: read("cur_freq")
+                .and_then(|v| v.trim().parse().ok())
+                .map(to_mhz),
+            available_frequencies_mhz,
+            utilization,
+            governor: read("governor"),
+            available_governors: read("available_governors")
+                .map(|v| v.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+        });
+    }
+
+    let gpufreq = mtk::read_mtk_gpu_state_at(sys, proc)?;
+    let mut available_frequencies_mhz: Vec<i32> = gpufreq
+        .opps
+        .iter()
+        .map(|opp| to_mhz(opp.freq_khz))
+        .collect();
+    available_frequencies_mhz.sort_unstable();
+    available_frequencies_mhz.dedup();
+
+    Some(PowerVrState {
+        source: PowerVrSource::MtkGpufreq,
+        cur_freq_mhz: gpufreq.cur_freq_mhz,
+        available_frequencies_mhz,
+        utilization,
+        governor: None,
+        available_governors: Vec::new(),
+    })
+}
+
+pub fn read_powervr_state() -> Option<PowerVrState> {
+    read_powervr_state_at(SYS_ROOT, PROC_ROOT)
+}
+
+pub fn read_powervr_cur_freq() -> Option<i32> {
+    read_powervr_state()?.cur_freq_mhz
+}
+
+pub fn read_powervr_utilization() -> Option<i32> {
+    read_utilization_at(SYS_ROOT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_mediatek_gpufreq() {
+        let root = fixture::build(
+            "powervr_mtk",
+            &[
+                ("sys/kernel/ged/hal/gpu_utilization", "42 58 0"),
+                (
+                    "proc/gpufreq/gpufreq_var_dump",
+                    "g_cur_gpu_freq = 570000, g_cur_gpu_volt = 70000\n",
+                ),
+                (
+                    "proc/gpufreq/gpufreq_opp_dump",
+                    "[0] freq = 680000, volt = 80000, vsram = 90000\n\
+                     [1] freq = 570000, volt = 70000, vsram = 90000\n\
+                     [2] freq = 390000, volt = 65000, vsram = 90000\n",
+                ),
+            ],
+        );
+
+        let state =
+            read_powervr_state_at(&format!("{}/sys", root), &format!("{}/proc", root)).unwrap();
+        assert_eq!(state.source, PowerVrSource::MtkGpufreq);
+        assert_eq!(state.cur_freq_mhz, Some(570));
+        assert_eq!(state.available_frequencies_mhz, vec![390, 570, 680]);
+        assert_eq!(state.utilization, Some(42));
+    }
+
+    #[test]
+    fn test_unisoc_devfreq() {
+        let root = fixture::build(
+            "powervr_unisoc",
+            &[
+                ("sys/class/devfreq/60000000.gpu/cur_freq", "614400000"),
+                (
+                    "sys/class/devfreq/60000000.gpu/available_frequencies",
+                    "384000000 512000000 614400000 850000000",
+                ),
+                ("sys/class/devfreq/60000000.gpu/governor", "simple_ondemand"),
+                (
+                    "sys/class/devfreq/60000000.gpu/available_governors",
+                    "userspace performance simple_ondemand",
+                ),
+                ("sys/kernel/debug/pvr/status", "GPU Utilisation: 23%\n"),
+            ],
+        );
+
+        let state =
+            read_powervr_state_at(&format!("{}/sys", root), &format!("{}/proc", root)).unwrap();
+        assert_eq!(state.source, PowerVrSource::Devfreq);
+        assert_eq!(state.cur_freq_mhz, Some(614));
+        assert_eq!(state.available_frequencies_mhz.len(), 4);
+        assert_eq!(state.governor.as_deref(), Some("simple_ondemand"));
+        assert_eq!(state.utilization, Some(23));
+    }
+}
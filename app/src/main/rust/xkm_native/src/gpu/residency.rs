@@ -0,0 +1,255 @@
+use super::kgsl::{hz_to_mhz, levels_at};
+use crate::devfreq;
+use crate::utils;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const KGSL_ROOT: &str = "/sys/class/kgsl/kgsl-3d0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidencySource {
+    /// devfreq `trans_stat`: wall time at each frequency, idle included.
+    DevfreqTransStat,
+    /// kgsl `gpu_clock_stats`: busy time only, per power level.
+    KgslClockStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuFreqResidency {
+    pub freq_mhz: i32,
+    /// Since boot, or since the last reset.
+    pub time_ms: u64,
+    pub percent: f32,
+    /// Since the previous read.
+    pub delta_ms: u64,
+    pub delta_percent: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuResidencyReport {
+    pub source: ResidencySource,
+    pub current_mhz: Option<i32>,
+    pub total_ms: u64,
+    pub delta_total_ms: u64,
+    pub since_reset: bool,
+    /// Slowest first.
+    pub frequencies: Vec<GpuFreqResidency>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    source: ResidencySource,
+    current_hz: Option<u64>,
+    /// (Hz, ms), slowest first.
+    rows: Vec<(u64, u64)>,
+}
+
+#[derive(Default)]
+struct ResidencyHistory {
+    last: Option<Snapshot>,
+    baseline: Option<Snapshot>,
+}
+
+static HISTORY: Lazy<Mutex<ResidencyHistory>> =
+    Lazy::new(|| Mutex::new(ResidencyHistory::default()));
+
+fn read_devfreq_snapshot(devfreq_dir: &str) -> Option<Snapshot> {
+    let text = utils::read_sysfs_full(&format!("{}/trans_stat", devfreq_dir))?;
+    let stat = devfreq::parse_trans_stat(&text)?;
+    let mut rows: Vec<(u64, u64)> = stat
+        .residency
+        .iter()
+        .map(|row| (row.freq, row.time_ms))
+        .collect();
+    rows.sort_by_key(|(hz, _)| *hz);
+
+    Some(Snapshot {
+        source: ResidencySource::DevfreqTransStat,
+        current_hz: stat.current,
+        rows,
+    })
+}
+
+/// `gpu_clock_stats` holds one busy-time counter in microseconds per power
+/// level, in the same fastest-first order as `gpu_available_frequencies`.
+fn read_kgsl_snapshot(kgsl_dir: &str) -> Option<Snapshot> {
+    let text = utils::read_sysfs_full(&format!("{}/gpu_clock_stats", kgsl_dir))?;
+    let levels = levels_at(kgsl_dir);
+    let times: Vec<u64> = text
+        .split_whitespace()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    if levels.is_empty() || times.len() < levels.len() {
+        return None;
+    }
+    let mut rows: Vec<(u64, u64)> = levels
+        .iter()
+        .zip(&times)
+        .map(|(hz, us)| (*hz as u64, us / 1000))
+        .collect();
+    rows.sort_by_key(|(hz, _)| *hz);
+
+    Some(Snapshot {
+        source: ResidencySource::KgslClockStats,
+        current_hz: utils::read_sysfs_full(&format!("{}/gpuclk", kgsl_dir))
+            .and_then(|v| v.trim().parse().ok()),
+        rows,
+    })
+}
+
+fn read_snapshot_at(kgsl_dir: &str, devfreq_dir: Option<&str>) -> Option<Snapshot> {
+    devfreq_dir
+        .and_then(read_devfreq_snapshot)
+        .or_else(|| read_kgsl_snapshot(kgsl_dir))
+}
+
+/// Per-row `current - earlier`, or `None` when the table changed shape or a
+/// counter went backwards (the kernel reset it).
+fn subtract(current: &Snapshot, earlier: Option<&Snapshot>) -> Option<Vec<u64>> {
+    let earlier = earlier?;
+    if earlier.source != current.source || earlier.rows.len() != current.rows.len() {
+        return None;
+    }
+    current
+        .rows
+        .iter()
+        .zip(&earlier.rows)
+        .map(|((hz, now), (then_hz, then))| (hz == then_hz && now >= then).then(|| now - then))
+        .collect()
+}
+
+fn percent(part: u64, total: u64) -> f32 {
+    if total > 0 {
+        part as f32 * 100.0 / total as f32
+    } else {
+        0.0
+    }
+}
+
+fn build_report(
+    current: &Snapshot,
+    last: Option<&Snapshot>,
+    baseline: Option<&Snapshot>,
+) -> GpuResidencyReport {
+    let since_reset = subtract(current, baseline);
+    let times: Vec<u64> = since_reset
+        .clone()
+        .unwrap_or_else(|| current.rows.iter().map(|(_, ms)| *ms).collect());
+    let deltas = subtract(current, last).unwrap_or_else(|| vec![0; current.rows.len()]);
+    let total_ms: u64 = times.iter().sum();
+    let delta_total_ms: u64 = deltas.iter().sum();
+
+    GpuResidencyReport {
+        source: current.source,
+        current_mhz: current.current_hz.map(|hz| hz_to_mhz(hz as i64)),
+        total_ms,
+        delta_total_ms,
+        since_reset: since_reset.is_some(),
+        frequencies: current
+            .rows
+            .iter()
+            .zip(times.iter().zip(&deltas))
+            .map(|((hz, _), (time_ms, delta_ms))| GpuFreqResidency {
+                freq_mhz: hz_to_mhz(*hz as i64),
+                time_ms: *time_ms,
+                percent: percent(*time_ms, total_ms),
+                delta_ms: *delta_ms,
+                delta_percent: percent(*delta_ms, delta_total_ms),
+            })
+            .collect(),
+    }
+}
+
+/// Time at each GPU frequency, from devfreq `trans_stat` when the kernel
+/// keeps one, else kgsl `gpu_clock_stats`. Deltas are against the previous
+/// call.
+pub fn read_gpu_residency() -> Option<GpuResidencyReport> {
+    let current = read_snapshot_at(KGSL_ROOT, devfreq::gpu_devfreq_dir())?;
+    let mut history = HISTORY.lock().unwrap();
+    let report = build_report(&current, history.last.as_ref(), history.baseline.as_ref());
+    history.last = Some(current);
+    Some(report)
+}
+
+/// Counts residency from now on. The kernel counters are left alone.
+pub fn reset_gpu_residency() {
+    let current = read_snapshot_at(KGSL_ROOT, devfreq::gpu_devfreq_dir());
+    let mut history = HISTORY.lock().unwrap();
+    history.baseline = current.clone();
+    history.last = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_kgsl_clock_stats() {
+        let dir = fixture::build(
+            "gpu_residency",
+            &[
+                ("gpu_available_frequencies", "585000000 427000000 257000000"),
+                ("gpu_clock_stats", "2000000 1000000 7000000"),
+                ("gpuclk", "257000000"),
+            ],
+        );
+
+        let first = read_snapshot_at(&dir, None).unwrap();
+        assert_eq!(first.source, ResidencySource::KgslClockStats);
+        assert_eq!(
+            first.rows,
+            vec![(257000000, 7000), (427000000, 1000), (585000000, 2000)]
+        );
+        let report = build_report(&first, None, None);
+        assert_eq!(report.total_ms, 10000);
+        assert_eq!(report.current_mhz, Some(257));
+        assert!((report.frequencies[2].percent - 20.0).abs() < 0.01);
+
+        let later = Snapshot {
+            rows: vec![(257000000, 7000), (427000000, 1500), (585000000, 3500)],
+            ..first.clone()
+        };
+        let report = build_report(&later, Some(&first), Some(&first));
+        assert!(report.since_reset);
+        assert_eq!(report.total_ms, 2000);
+        assert_eq!(report.delta_total_ms, 2000);
+        assert!((report.frequencies[2].delta_percent - 75.0).abs() < 0.01);
+
+        // A kernel-side reset drops the baseline instead of underflowing.
+        let reset = Snapshot {
+            rows: vec![(257000000, 10), (427000000, 0), (585000000, 0)],
+            ..first.clone()
+        };
+        let report = build_report(&reset, Some(&later), Some(&first));
+        assert!(!report.since_reset);
+        assert_eq!(report.total_ms, 10);
+        assert_eq!(report.delta_total_ms, 0);
+    }
+
+    #[test]
+    fn test_devfreq_preferred() {
+        let dir = fixture::build(
+            "gpu_residency_devfreq",
+            &[
+                ("gpu_available_frequencies", "585000000 257000000"),
+                ("gpu_clock_stats", "1 2"),
+                (
+                    "devfreq/trans_stat",
+                    "     From  :   To\n\
+                     :  257000000  585000000   time(ms)\n\
+                     * 257000000:         0         3      9000\n\
+                     585000000:         3         0      1000\n\
+                     Total transition : 6\n",
+                ),
+            ],
+        );
+
+        let snapshot = read_snapshot_at(&dir, Some(&format!("{}/devfreq", dir))).unwrap();
+        assert_eq!(snapshot.source, ResidencySource::DevfreqTransStat);
+        assert_eq!(snapshot.current_hz, Some(257000000));
+        assert_eq!(snapshot.rows, vec![(257000000, 9000), (585000000, 1000)]);
+    }
+}
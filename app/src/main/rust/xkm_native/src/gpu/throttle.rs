@@ -0,0 +1,320 @@
+use super::kgsl::{hz_to_mhz, levels_at};
+use crate::devfreq;
+use crate::utils::{self, to_mhz};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const SYS_ROOT: &str = "/sys";
+const KGSL_ROOT: &str = "/sys/class/kgsl/kgsl-3d0";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuThrottleSource {
+    /// `thermal_pwrlevel`, `max_gpuclk` or the cooling device type.
+    pub source: String,
+    pub path: String,
+    pub value: String,
+    /// Ceiling this source imposes, when it maps to a frequency.
+    pub freq_mhz: Option<i32>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuThermalZone {
+    pub zone: i32,
+    pub name: String,
+    pub temp_c: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuThrottleReport {
+    pub throttled: bool,
+    /// Fastest frequency in the GPU's table.
+    pub max_freq_mhz: Option<i32>,
+    /// Lowest ceiling among the active sources.
+    pub allowed_max_mhz: Option<i32>,
+    /// Share of `max_freq_mhz` taken away by the ceiling.
+    pub throttle_percent: f32,
+    pub sources: Vec<GpuThrottleSource>,
+    pub zones: Vec<GpuThermalZone>,
+    pub max_temp_c: Option<f32>,
+    /// Start of the current throttling episode, unix ms.
+    pub throttled_since_ms: Option<u64>,
+    pub throttled_for_ms: u64,
+    /// Episodes seen since the app started.
+    pub episodes: u32,
+}
+
+#[derive(Default)]
+struct ThrottleHistory {
+    since: Option<u64>,
+    episodes: u32,
+}
+
+static HISTORY: Lazy<Mutex<ThrottleHistory>> = Lazy::new(|| Mutex::new(ThrottleHistory::default()));
+
+fn is_gpu_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["gpu", "kgsl", "mali", "g3d", "sgpu"]
+        .iter()
+        .any(|key| name.contains(key))
+}
+
+/// Thermal zones whose type names the GPU (`gpuss-0`, `gpu_therm`, `G3D`...).
+pub(crate) fn gpu_zones_at(sys: &str) -> Vec<GpuThermalZone> {
+    let thermal = format!("{}/class/thermal", sys);
+    utils::list_dir(&thermal)
+        .into_iter()
+        .filter_map(|name| {
+            let zone: i32 = name.strip_prefix("thermal_zone")?.parse().ok()?;
+            let dir = format!("{}/{}", thermal, name);
+            let zone_type = utils::read_sysfs_full(&format!("{}/type", dir))?;
+            if !is_gpu_name(&zone_type) {
+                return None;
+            }
+            let temp = utils::read_sysfs_num::<i64>(&format!("{}/temp", dir))? as f32;
+            let temp_c = if temp > 1000.0 { temp / 1000.0 } else { temp };
+            (temp_c > 0.0 && temp_c < 150.0).then_some(GpuThermalZone {
+                zone,
+                name: zone_type,
+                temp_c,
+            })
+        })
+        .collect()
+}
+
+/// Frequency table in MHz, fastest first: kgsl power levels, else devfreq.
+fn freq_table_desc(kgsl_dir: &str, devfreq_dir: Option<&str>) -> Vec<i32> {
+    let mut table: Vec<i32> = levels_at(kgsl_dir).into_iter().map(hz_to_mhz).collect();
+    if table.is_empty()
+        && let Some(dir) = devfreq_dir
+    {
+        table = utils::read_sysfs_full(&format!("{}/available_frequencies", dir))
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|f| f.parse().ok())
+            .map(to_mhz)
+            .collect();
+    }
+    table.sort_unstable_by(|a, b| b.cmp(a));
+    table.dedup();
+    table
+}
+
+fn kgsl_sources(kgsl_dir: &str, devfreq_dir: Option<&str>) -> Vec<GpuThrottleSource> {
+    let mut sources = Vec::new();
+
+    // kgsl's own thermal cap, as a power level (0 is the fastest).
+    let path = format!("{}/thermal_pwrlevel", kgsl_dir);
+    if let Some(level) = utils::read_sysfs_num::<i64>(&path) {
+        sources.push(GpuThrottleSource {
+            source: "thermal_pwrlevel".to_string(),
+            value: level.to_string(),
+            freq_mhz: levels_at(kgsl_dir)
+                .get(level.max(0) as usize)
+                .map(|hz| hz_to_mhz(*hz)),
+            active: level > 0,
+            path,
+        });
+    }
+
+    // `max_gpuclk` is the ceiling kgsl actually enforces; below the devfreq
+    // `max_freq` the user set, something else is capping it.
+    let path = format!("{}/max_gpuclk", kgsl_dir);
+    if let Some(hz) = utils::read_sysfs_num::<i64>(&path) {
+        let requested = devfreq_dir
+            .and_then(|dir| utils::read_sysfs_num::<i64>(&format!("{}/max_freq", dir)))
+            .map(hz_to_mhz);
+        let mhz = hz_to_mhz(hz);
+        sources.push(GpuThrottleSource {
+            source: "max_gpuclk".to_string(),
+            value: hz.to_string(),
+            freq_mhz: Some(mhz),
+            active: requested.is_some_and(|max| mhz < max),
+            path,
+        });
+    }
+
+    sources
+}
+
+/// A `thermal-devfreq-N` cooler can sit on any devfreq device (bus, UFS,
+/// DSP); it is the GPU's only when its `device` link resolves to the GPU
+/// devfreq node or the device that owns it.
+fn cools_devfreq(cooling_dir: &str, devfreq_dir: Option<&str>) -> bool {
+    let Some(devfreq) = devfreq_dir.and_then(|dir| std::fs::canonicalize(dir).ok()) else {
+        return false;
+    };
+    std::fs::canonicalize(format!("{}/device", cooling_dir))
+        .is_ok_and(|device| device == devfreq || devfreq.parent() == Some(device.as_path()))
+}
+
+/// devfreq cooling devices of the GPU (`thermal-devfreq-N` linked to its
+/// devfreq node, or the GPU device's name on newer kernels). The state
+/// indexes the frequency table from the top.
+fn cooling_sources(
+    sys: &str,
+    devfreq_dir: Option<&str>,
+    table_desc: &[i32],
+) -> Vec<GpuThrottleSource> {
+    let thermal = format!("{}/class/thermal", sys);
+    utils::list_dir(&thermal)
+        .into_iter()
+        .filter(|name| name.starts_with("cooling_device"))
+        .filter_map(|name| {
+            let dir = format!("{}/{}", thermal, name);
+            let cooling_type = utils::read_sysfs_full(&format!("{}/type", dir))?;
+            let gpu_cooler = is_gpu_name(&cooling_type)
+                || (cooling_type.starts_with("thermal-devfreq")
+                    && cools_devfreq(&dir, devfreq_dir));
+            if !gpu_cooler {
+                return None;
+            }
+            let path = format!("{}/cur_state", dir);
+            let state = utils::read_sysfs_num::<i64>(&path).unwrap_or(0);
+            let max_state =
+                utils::read_sysfs_num::<i64>(&format!("{}/max_state", dir)).unwrap_or(0);
+            let freq_mhz = (state > 0)
+                .then(|| {
+                    table_desc
+                        .get(state as usize)
+                        .or(table_desc.last())
+                        .copied()
+                })
+                .flatten();
+            Some(GpuThrottleSource {
+                source: cooling_type,
+                path,
+                value: format!("{}/{}", state, max_state),
+                freq_mhz,
+                active: state > 0,
+            })
+        })
+        .collect()
+}
+
+fn build_report(sys: &str, kgsl_dir: &str, devfreq_dir: Option<&str>) -> GpuThrottleReport {
+    let table = freq_table_desc(kgsl_dir, devfreq_dir);
+    let mut sources = kgsl_sources(kgsl_dir, devfreq_dir);
+    sources.extend(cooling_sources(sys, devfreq_dir, &table));
+
+    let max_freq_mhz = table.first().copied();
+    let allowed_max_mhz = sources
+        .iter()
+        .filter(|s| s.active)
+        .filter_map(|s| s.freq_mhz)
+        .min()
+        .or(max_freq_mhz);
+    let throttle_percent = match (max_freq_mhz, allowed_max_mhz) {
+        (Some(max), Some(allowed)) if max > 0 && allowed < max => {
+            (max - allowed) as f32 * 100.0 / max as f32
+        }
+        _ => 0.0,
+    };
+
+    let zones = gpu_zones_at(sys);
+    GpuThrottleReport {
+        throttled: sources.iter().any(|s| s.active),
+        max_freq_mhz,
+        allowed_max_mhz,
+        throttle_percent,
+        sources,
+        max_temp_c: zones.iter().map(|z| z.temp_c).reduce(f32::max),
+        zones,
+        throttled_since_ms: None,
+        throttled_for_ms: 0,
+        episodes: 0,
+    }
+}
+
+fn track(history: &mut ThrottleHistory, report: &mut GpuThrottleReport, now: u64) {
+    if report.throttled {
+        if history.since.is_none() {
+            history.since = Some(now);
+            history.episodes += 1;
+        }
+    } else {
+        history.since = None;
+    }
+    report.throttled_since_ms = history.since;
+    report.throttled_for_ms = history.since.map_or(0, |since| now.saturating_sub(since));
+    report.episodes = history.episodes;
+}
+
+/// Whether the GPU is being throttled, by what and how far. Episode timing
+/// is tracked across calls, so poll this at a steady rate.
+pub fn read_gpu_throttle() -> GpuThrottleReport {
+    let mut report = build_report(SYS_ROOT, KGSL_ROOT, devfreq::gpu_devfreq_dir());
+    track(&mut HISTORY.lock().unwrap(), &mut report, utils::now_ms());
+    report
+}
+
+/// Hottest GPU thermal zone, in °C.
+pub fn read_gpu_temperature() -> Option<f32> {
+    gpu_zones_at(SYS_ROOT)
+        .into_iter()
+        .map(|z| z.temp_c)
+        .reduce(f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_kgsl_throttle() {
+        let sys = fixture::build(
+            "gpu_throttle",
+            &[
+                (
+                    "class/kgsl/kgsl-3d0/gpu_available_frequencies",
+                    "900000000 680000000 450000000 300000000",
+                ),
+                ("class/kgsl/kgsl-3d0/thermal_pwrlevel", "1"),
+                ("class/kgsl/kgsl-3d0/max_gpuclk", "680000000"),
+                ("class/kgsl/kgsl-3d0/devfreq/max_freq", "900000000"),
+                ("class/thermal/cooling_device3/type", "thermal-devfreq-0"),
+                ("class/thermal/cooling_device3/cur_state", "2"),
+                ("class/thermal/cooling_device3/max_state", "3"),
+                ("class/thermal/cooling_device4/type", "thermal-cpufreq-0"),
+                ("class/thermal/cooling_device4/cur_state", "1"),
+                ("class/thermal/cooling_device5/type", "thermal-devfreq-1"),
+                ("class/thermal/cooling_device5/cur_state", "3"),
+                ("class/thermal/cooling_device5/max_state", "3"),
+                ("class/thermal/thermal_zone20/type", "gpuss-0"),
+                ("class/thermal/thermal_zone20/temp", "71200"),
+                ("class/thermal/thermal_zone21/type", "gpuss-1"),
+                ("class/thermal/thermal_zone21/temp", "73400"),
+                ("class/thermal/thermal_zone1/type", "cpu-0-0"),
+                ("class/thermal/thermal_zone1/temp", "80000"),
+            ],
+        );
+        let kgsl = format!("{}/class/kgsl/kgsl-3d0", sys);
+        std::os::unix::fs::symlink(
+            format!("{}/devfreq", kgsl),
+            format!("{}/class/thermal/cooling_device3/device", sys),
+        )
+        .unwrap();
+
+        let mut report = build_report(&sys, &kgsl, Some(&format!("{}/devfreq", kgsl)));
+        assert!(report.throttled);
+        assert_eq!(report.sources.len(), 3);
+        assert_eq!(report.max_freq_mhz, Some(900));
+        assert_eq!(report.allowed_max_mhz, Some(450));
+        assert!((report.throttle_percent - 50.0).abs() < 0.01);
+        assert_eq!(report.zones.len(), 2);
+        assert_eq!(report.max_temp_c, Some(73.4));
+
+        let mut history = ThrottleHistory::default();
+        track(&mut history, &mut report, 1_000);
+        track(&mut history, &mut report, 4_000);
+        assert_eq!(report.throttled_since_ms, Some(1_000));
+        assert_eq!(report.throttled_for_ms, 3_000);
+        assert_eq!(report.episodes, 1);
+
+        report.throttled = false;
+        track(&mut history, &mut report, 5_000);
+        assert_eq!(report.throttled_since_ms, None);
+    }
+}
@@ -0,0 +1,138 @@
+use super::mali::parse_bracketed;
+use crate::utils::{self, to_mhz};
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XclipseState {
+    pub cur_freq_mhz: Option<i32>,
+    pub min_freq_mhz: Option<i32>,
+    pub max_freq_mhz: Option<i32>,
+    /// Ascending.
+    pub available_frequencies_mhz: Vec<i32>,
+    pub utilization: Option<i32>,
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+}
+
+/// Samsung's GPU interface, shared by sgpu and Exynos kbase builds.
+fn gpu_dir(sys: &str) -> String {
+    format!("{}/kernel/gpu", sys)
+}
+
+fn read_node(sys: &str, node: &str) -> Option<String> {
+    utils::read_sysfs_full(&format!("{}/{}", gpu_dir(sys), node))
+}
+
+/// Leading number of a node; `gpu_busy` reads `37 %` on some builds.
+fn read_number(sys: &str, node: &str) -> Option<u64> {
+    let text = read_node(sys, node)?;
+    let digits: String = text
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn read_cur_freq_at(sys: &str) -> Option<i32> {
+    read_number(sys, "gpu_clock").map(to_mhz)
+}
+
+fn read_utilization_at(sys: &str) -> Option<i32> {
+    read_number(sys, "gpu_busy").map(|v| v.min(100) as i32)
+}
+
+pub(crate) fn read_xclipse_state_at(sys: &str) -> Option<XclipseState> {
+    if !utils::file_exists(&format!("{}/gpu_clock", gpu_dir(sys))) {
+        return None;
+    }
+    let freq = |node: &str| read_number(sys, node).map(to_mhz);
+
+    let mut available_frequencies_mhz: Vec<i32> = read_node(sys, "gpu_freq_table")
+        .map(|text| {
+            text.split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .map(to_mhz)
+                .collect()
+        })
+        .unwrap_or_default();
+    available_frequencies_mhz.sort_unstable();
+    available_frequencies_mhz.dedup();
+
+    // `gpu_governor` is either the bare name or the full list with the
+    // active one bracketed.
+    let (governor, mut available_governors) = read_node(sys, "gpu_governor")
+        .map(|text| match parse_bracketed(&text) {
+            (Some(current), all) => (Some(current), all),
+            (None, _) => (Some(text.trim().to_string()), Vec::new()),
+        })
+        .unwrap_or_default();
+    if available_governors.is_empty() {
+        available_governors = read_node(sys, "gpu_available_governor")
+            .map(|text| text.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+    }
+
+    Some(XclipseState {
+        cur_freq_mhz: read_cur_freq_at(sys),
+        min_freq_mhz: freq("gpu_min_clock"),
+        max_freq_mhz: freq("gpu_max_clock"),
+        available_frequencies_mhz,
+        utilization: read_utilization_at(sys),
+        governor,
+        available_governors,
+    })
+}
+
+pub fn read_xclipse_cur_freq() -> Option<i32> {
+    read_cur_freq_at(SYS_ROOT)
+}
+
+pub fn read_xclipse_utilization() -> Option<i32> {
+    read_utilization_at(SYS_ROOT)
+}
+
+pub fn read_xclipse_state() -> Option<XclipseState> {
+    read_xclipse_state_at(SYS_ROOT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_exynos_2200() {
+        let sys = fixture::build(
+            "xclipse",
+            &[
+                ("kernel/gpu/gpu_clock", "555000"),
+                ("kernel/gpu/gpu_busy", "37 %"),
+                ("kernel/gpu/gpu_min_clock", "175000"),
+                ("kernel/gpu/gpu_max_clock", "1306000"),
+                (
+                    "kernel/gpu/gpu_freq_table",
+                    "1306000 1210000 1110000 1001000 750000 555000 175000",
+                ),
+                ("kernel/gpu/gpu_governor", "Interactive"),
+                (
+                    "kernel/gpu/gpu_available_governor",
+                    "Default Interactive Static Booster",
+                ),
+            ],
+        );
+
+        let state = read_xclipse_state_at(&sys).unwrap();
+        assert_eq!(state.cur_freq_mhz, Some(555));
+        assert_eq!(state.utilization, Some(37));
+        assert_eq!(state.max_freq_mhz, Some(1306));
+        assert_eq!(state.available_frequencies_mhz.first(), Some(&175));
+        assert_eq!(state.available_frequencies_mhz.len(), 7);
+        assert_eq!(state.governor.as_deref(), Some("Interactive"));
+        assert_eq!(state.available_governors.len(), 4);
+
+        assert!(read_xclipse_state_at(&format!("{}/missing", sys)).is_none());
+    }
+}
@@ -1,11 +1,19 @@
 mod cpu;
+mod devfreq;
+mod devicetree;
+mod disk;
 mod gpu;
 mod memory;
+mod mtk;
 mod power;
+mod process;
+mod sampler;
+mod sched;
+mod trace;
 mod utils;
 
 use jni::objects::{JClass, JString};
-use jni::sys::{jfloat, jint, jlong, jstring};
+use jni::sys::{jboolean, jfloat, jint, jlong, jstring};
 use jni::JNIEnv;
 
 #[unsafe(no_mangle)]
@@ -367,3 +375,782 @@ pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_get
     let json = serde_json::to_string(&algos).unwrap_or_else(|_| "[]".to_string());
     create_jstring_safe(&env, json)
 }
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getGovernorTunablesNative(
+    env: JNIEnv,
+    _class: JClass,
+    policy: jint,
+) -> jstring {
+    let json = cpu::read_governor_tunables(policy)
+        .and_then(|t| serde_json::to_string(&t).ok())
+        .unwrap_or_else(|| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setGovernorTunableNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    policy: jint,
+    name: JString,
+    value: JString,
+) -> jstring {
+    let name: String = env.get_string(&name).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+    let json = match cpu::write_governor_tunable(policy, &name, &value) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_snapshotGovernorTunablesNative(
+    env: JNIEnv,
+    _class: JClass,
+    policy: jint,
+) -> jstring {
+    let json = cpu::snapshot_governor_tunables(policy)
+        .and_then(|t| serde_json::to_string(&t).ok())
+        .unwrap_or_else(|| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_restoreGovernorTunablesNative(
+    env: JNIEnv,
+    _class: JClass,
+    policy: jint,
+) -> jstring {
+    let results = cpu::restore_governor_tunables(policy);
+    let json = serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_switchCpuGovernorNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    policy: jint,
+    governor: JString,
+) -> jstring {
+    let governor: String = env.get_string(&governor).map(|s| s.into()).unwrap_or_default();
+    let json = cpu::switch_governor(policy, &governor)
+        .and_then(|r| serde_json::to_string(&r).ok())
+        .unwrap_or_else(|| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readCpuIdleStatesNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let states = cpu::read_cpuidle_states();
+    let json = serde_json::to_string(&states).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setClusterIdleStateNative(
+    env: JNIEnv,
+    _class: JClass,
+    cluster: jint,
+    state: jint,
+    disable: jboolean,
+) -> jstring {
+    let results = cpu::set_cluster_idle_state(cluster, state, disable != 0);
+    let json = serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getOppTablesNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let tables = devicetree::read_opp_tables();
+    let json = serde_json::to_string(&tables).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getSocInfoNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let soc = cpu::get_soc_identity();
+    let json = serde_json::to_string(&soc).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getCoreThermalMappingNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let mapping = cpu::get_core_thermal_mapping();
+    let json = serde_json::to_string(&mapping).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setCoresOnlineNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    cores: JString,
+    online: jboolean,
+    allow_empty_cluster: jboolean,
+) -> jstring {
+    let cores: String = env.get_string(&cores).map(|s| s.into()).unwrap_or_default();
+    let report = cpu::set_cores_online(&cpu::parse_cpu_list(&cores), online != 0, allow_empty_cluster != 0);
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getCoreCtlNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let states = cpu::read_core_ctl();
+    let json = serde_json::to_string(&states).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setCoreCtlNative(
+    env: JNIEnv,
+    _class: JClass,
+    cpu: jint,
+    enable: jint,
+    min_cpus: jint,
+    max_cpus: jint,
+) -> jstring {
+    // Negative values leave the node untouched.
+    let enable = (enable >= 0).then_some(enable != 0);
+    let min_cpus = (min_cpus >= 0).then_some(min_cpus);
+    let max_cpus = (max_cpus >= 0).then_some(max_cpus);
+    let results = cpu::set_core_ctl(cpu, enable, min_cpus, max_cpus);
+    let json = serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_initFreqLockNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    state_dir: JString,
+) -> jstring {
+    let state_dir: String = env.get_string(&state_dir).map(|s| s.into()).unwrap_or_default();
+    let state = cpu::init_freq_lock(&state_dir);
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_lockCpuFrequenciesNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    request: JString,
+) -> jstring {
+    let request: String = env.get_string(&request).map(|s| s.into()).unwrap_or_default();
+    let result = serde_json::from_str::<cpu::LockRequest>(&request)
+        .map_err(|e| e.to_string())
+        .and_then(cpu::lock_cpu_frequencies);
+    let json = match result {
+        Ok(state) => serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_unlockCpuFrequenciesNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = cpu::unlock_cpu_frequencies();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getFreqLockStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = cpu::get_freq_lock_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_pollFreqLockEventsNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let events = cpu::poll_freq_lock_events();
+    let json = serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_explainFrequencyLimitsNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    requested: JString,
+) -> jstring {
+    let requested: String = env.get_string(&requested).map(|s| s.into()).unwrap_or_default();
+    let report = cpu::explain_frequency_limits(&cpu::parse_cpu_freq_map(&requested));
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readCgroupsNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = sched::read_cgroups();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_writeCgroupNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    controller: JString,
+    group: JString,
+    param: JString,
+    value: JString,
+) -> jstring {
+    let controller: String = env.get_string(&controller).map(|s| s.into()).unwrap_or_default();
+    let group: String = env.get_string(&group).map(|s| s.into()).unwrap_or_default();
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+    let json = match sched::write_cgroup(&controller, &group, &param, &value) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_applySchedProfileNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    mode: JString,
+) -> jstring {
+    let mode: String = env.get_string(&mode).map(|s| s.into()).unwrap_or_default();
+    let json = match sched::apply_sched_profile(&mode) {
+        Ok(report) => serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readSchedSysctlsNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let sysctls = sched::read_sched_sysctls();
+    let json = serde_json::to_string(&sysctls).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_writeSchedSysctlNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    source: JString,
+    name: JString,
+    value: JString,
+) -> jstring {
+    let source: String = env.get_string(&source).map(|s| s.into()).unwrap_or_default();
+    let name: String = env.get_string(&name).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+    let json = match sched::write_sched_sysctl(&source, &name, &value) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readInputBoostNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let drivers = cpu::read_input_boost();
+    let json = serde_json::to_string(&drivers).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_writeInputBoostNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    driver: JString,
+    param: JString,
+    value: JString,
+) -> jstring {
+    let driver: String = env.get_string(&driver).map(|s| s.into()).unwrap_or_default();
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+    let json = match cpu::write_input_boost(&driver, &param, &value) {
+        Ok(result) => serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_sampleProcessesNative(
+    env: JNIEnv,
+    _class: JClass,
+    limit: jint,
+) -> jstring {
+    let processes = process::sample_processes(limit.max(0) as usize);
+    let json = serde_json::to_string(&processes).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_sampleThreadsNative(
+    env: JNIEnv,
+    _class: JClass,
+    pid: jint,
+    limit: jint,
+) -> jstring {
+    let threads = process::sample_threads(pid, limit.max(0) as usize);
+    let json = serde_json::to_string(&threads).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_sampleUidCpuNative(
+    env: JNIEnv,
+    _class: JClass,
+    window_secs: jint,
+) -> jstring {
+    let report = power::sample_uid_cpu(std::time::Duration::from_secs(window_secs.max(0) as u64));
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_measurePerfCountersNative(
+    env: JNIEnv,
+    _class: JClass,
+    interval_ms: jint,
+) -> jstring {
+    let interval = std::time::Duration::from_millis(interval_ms.clamp(10, 10_000) as u64);
+    let report = cpu::measure_perf_counters(interval);
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_startFreqTraceNative(
+    env: JNIEnv,
+    _class: JClass,
+    rate_hz: jint,
+) -> jstring {
+    let json = match trace::start_freq_trace(rate_hz.max(1) as u32) {
+        Ok(summary) => serde_json::to_string(&summary).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_stopFreqTraceNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let summary = trace::stop_freq_trace();
+    let json = serde_json::to_string(&summary).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getFreqTraceSummaryNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let summary = trace::get_freq_trace_summary();
+    let json = serde_json::to_string(&summary).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readKgslStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::read_kgsl_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setKgslParamNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    param: JString,
+    value: JString,
+) -> jstring {
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+
+    let result = match gpu::KgslParam::parse(&param) {
+        Some(param) => gpu::set_kgsl_param(param, &value),
+        None => Err(format!("Unknown kgsl param: {}", param)),
+    };
+    let json = match result {
+        Ok(write) => serde_json::to_string(&write).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setKgslFreqRangeNative(
+    env: JNIEnv,
+    _class: JClass,
+    min_mhz: jint,
+    max_mhz: jint,
+) -> jstring {
+    let json = match gpu::set_kgsl_freq_range(min_mhz, max_mhz) {
+        Ok(report) => serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readMaliStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::read_mali_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setMaliParamNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    param: JString,
+    value: JString,
+) -> jstring {
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+
+    let result = match gpu::MaliParam::parse(&param) {
+        Some(param) => gpu::set_mali_param(param, &value),
+        None => Err(format!("Unknown Mali param: {}", param)),
+    };
+    let json = match result {
+        Ok(write) => serde_json::to_string(&write).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_listDevfreqNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let devices = devfreq::list_devfreq();
+    let json = serde_json::to_string(&devices).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setDevfreqParamNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    device: JString,
+    param: JString,
+    value: JString,
+) -> jstring {
+    let device: String = env.get_string(&device).map(|s| s.into()).unwrap_or_default();
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+
+    let result = match devfreq::DevfreqParam::parse(&param) {
+        Some(param) => devfreq::set_devfreq_param(&device, param, &value),
+        None => Err(format!("Unknown devfreq param: {}", param)),
+    };
+    let json = match result {
+        Ok(write) => serde_json::to_string(&write).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setDevfreqRangeNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    device: JString,
+    min_freq: jlong,
+    max_freq: jlong,
+) -> jstring {
+    let device: String = env.get_string(&device).map(|s| s.into()).unwrap_or_default();
+
+    let json = if min_freq < 0 || max_freq < 0 {
+        serde_json::json!({ "error": "Frequencies must not be negative" }).to_string()
+    } else {
+        match devfreq::set_devfreq_range(&device, min_freq as u64, max_freq as u64) {
+            Ok(writes) => serde_json::to_string(&writes).unwrap_or_else(|_| "[]".to_string()),
+            Err(error) => serde_json::json!({ "error": error }).to_string(),
+        }
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readGpuResidencyNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let report = gpu::read_gpu_residency();
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_resetGpuResidencyNative(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    gpu::reset_gpu_residency();
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readDiskStatsNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let stats = disk::read_disk_stats();
+    let json = serde_json::to_string(&stats).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_createSamplerNative(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jlong {
+    sampler::create_sampler_handle()
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_releaseSamplerNative(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    unsafe { sampler::release_sampler_handle(handle) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerReadCpuLoadNative(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jfloat {
+    unsafe { sampler::with_sampler(handle, |s| s.cpu_load().total_load) }.unwrap_or(0.0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerReadCoreLoadsNative(
+    env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jstring {
+    let json = unsafe { sampler::with_sampler(handle, |s| s.cpu_load()) }
+        .and_then(|load| serde_json::to_string(&load).ok())
+        .unwrap_or_else(|| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerReadGpuBusyNative(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jint {
+    unsafe { sampler::with_sampler(handle, |s| s.gpu_busy()) }.unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerReadDiskStatsNative(
+    env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jstring {
+    let json = unsafe { sampler::with_sampler(handle, |s| s.disk_stats()) }
+        .and_then(|stats| serde_json::to_string(&stats).ok())
+        .unwrap_or_else(|| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerReadCpuIdleResidencyNative(
+    env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jstring {
+    let json = unsafe { sampler::with_sampler(handle, |s| s.cpuidle_residency()) }
+        .and_then(|residency| serde_json::to_string(&residency).ok())
+        .unwrap_or_else(|| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_samplerResetNative(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    unsafe { sampler::with_sampler(handle, |s| s.reset()) };
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getGpuIdentityNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let identity = gpu::get_gpu_identity();
+    let json = serde_json::to_string(&identity).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readXclipseStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::read_xclipse_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readPowerVrStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::read_powervr_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "null".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readMtkPlatformNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let platform = mtk::read_mtk_platform();
+    let json = serde_json::to_string(&platform).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_setMtkParamNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    param: JString,
+    target: jint,
+    value: JString,
+) -> jstring {
+    let param: String = env.get_string(&param).map(|s| s.into()).unwrap_or_default();
+    let value: String = env.get_string(&value).map(|s| s.into()).unwrap_or_default();
+
+    let result = match mtk::MtkParam::parse(&param) {
+        Some(param) => mtk::set_mtk_param(param, target, &value),
+        None => Err(format!("Unknown MediaTek param: {}", param)),
+    };
+    let json = match result {
+        Ok(writes) => serde_json::to_string(&writes).unwrap_or_else(|_| "[]".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readGpuMemoryNative(
+    env: JNIEnv,
+    _class: JClass,
+    limit: jint,
+) -> jstring {
+    let report = gpu::read_gpu_memory(limit.max(0) as usize);
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_readGpuThrottleNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let report = gpu::read_gpu_throttle();
+    let json = serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_lockGpuFrequencyNative(
+    mut env: JNIEnv,
+    _class: JClass,
+    request: JString,
+) -> jstring {
+    let request: String = env.get_string(&request).map(|s| s.into()).unwrap_or_default();
+    let result = serde_json::from_str::<gpu::GpuLockRequest>(&request)
+        .map_err(|e| e.to_string())
+        .and_then(gpu::lock_gpu_frequency);
+    let json = match result {
+        Ok(state) => serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string()),
+        Err(error) => serde_json::json!({ "error": error }).to_string(),
+    };
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_unlockGpuFrequencyNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::unlock_gpu_frequency();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_getGpuLockStateNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let state = gpu::get_gpu_lock_state();
+    let json = serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string());
+    create_jstring_safe(&env, json)
+}
+
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_id_xms_xtrakernelmanager_domain_native_NativeLib_pollGpuLockEventsNative(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let events = gpu::poll_gpu_lock_events();
+    let json = serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string());
+    create_jstring_safe(&env, json)
+}
@@ -43,21 +43,21 @@ pub fn read_memory_info() -> MemoryInfo {
     let mut buffers = 0i64;
 
     let mut buf = [0u8; 4096];
-    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/meminfo", &mut buf) {
-        if let Ok(content) = std::str::from_utf8(&buf[..bytes_read]) {
-            for line in content.lines() {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    if let Ok(value) = parts[1].parse::<i64>() {
-                        match parts[0] {
-                            "MemTotal:" => total = value,
-                            "MemAvailable:" => available = value,
-                            "MemFree:" => free = value,
-                            "Cached:" => cached = value,
-                            "Buffers:" => buffers = value,
-                            _ => {}
-                        }
-                    }
+    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/meminfo", &mut buf)
+        && let Ok(content) = std::str::from_utf8(&buf[..bytes_read])
+    {
+        for line in content.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2
+                && let Ok(value) = parts[1].parse::<i64>()
+            {
+                match parts[0] {
+                    "MemTotal:" => total = value,
+                    "MemAvailable:" => available = value,
+                    "MemFree:" => free = value,
+                    "Cached:" => cached = value,
+                    "Buffers:" => buffers = value,
+                    _ => {}
                 }
             }
         }
@@ -78,19 +78,19 @@ pub fn read_swap_info() -> SwapInfo {
     let mut cached = 0i64;
 
     let mut buf = [0u8; 4096];
-    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/meminfo", &mut buf) {
-        if let Ok(content) = std::str::from_utf8(&buf[..bytes_read]) {
-            for line in content.lines() {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    if let Ok(value) = parts[1].parse::<i64>() {
-                        match parts[0] {
-                            "SwapTotal:" => total = value,
-                            "SwapFree:" => free = value,
-                            "SwapCached:" => cached = value,
-                            _ => {}
-                        }
-                    }
+    if let Some(bytes_read) = utils::read_file_libc_buf("/proc/meminfo", &mut buf)
+        && let Ok(content) = std::str::from_utf8(&buf[..bytes_read])
+    {
+        for line in content.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2
+                && let Ok(value) = parts[1].parse::<i64>()
+            {
+                match parts[0] {
+                    "SwapTotal:" => total = value,
+                    "SwapFree:" => free = value,
+                    "SwapCached:" => cached = value,
+                    _ => {}
                 }
             }
         }
@@ -136,7 +136,7 @@ pub fn read_zram_stats() -> ZramStats {
 }
 
 pub fn read_swappiness() -> i32 {
-    utils::read_sysfs_int("/proc/sys/vm/swappiness", 1000).unwrap_or(60) as i32
+    utils::read_sysfs_int("/proc/sys/vm/swappiness", 1000).unwrap_or(60)
 }
 
 pub fn read_memory_info_detailed() -> MemInfoDetailed {
@@ -164,12 +164,11 @@ pub fn get_available_zram_algorithms() -> Vec<String> {
 pub fn get_current_zram_algorithm() -> String {
     let path = "/sys/block/zram0/comp_algorithm";
 
-    if let Some(content) = utils::read_sysfs_cached(path, 1000) {
-        if let Some(start) = content.find('[') {
-            if let Some(end) = content.find(']') {
-                return content[start + 1..end].to_string();
-            }
-        }
+    if let Some(content) = utils::read_sysfs_cached(path, 1000)
+        && let Some(start) = content.find('[')
+        && let Some(end) = content.find(']')
+    {
+        return content[start + 1..end].to_string();
     }
 
     "unknown".to_string()
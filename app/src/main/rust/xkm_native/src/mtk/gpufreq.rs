@@ -0,0 +1,334 @@
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpufreqVersion {
+    /// `/proc/gpufreq`, Helio and early Dimensity.
+    V1,
+    /// `/proc/gpufreqv2`, Dimensity 9000 and later.
+    V2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtkGpuOpp {
+    pub index: i32,
+    pub freq_khz: u64,
+    /// gpufreq reports volts in 10 µV steps.
+    pub volt_mv: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtkGpuState {
+    pub version: GpufreqVersion,
+    /// As the driver lists them: index 0 is the fastest.
+    pub opps: Vec<MtkGpuOpp>,
+    pub cur_freq_mhz: Option<i32>,
+    pub cur_opp: Option<i32>,
+    /// OPP the GPU is pinned to, `None` while DVFS runs freely.
+    pub fixed_opp: Option<i32>,
+    /// GED `gpu_utilization`, percent.
+    pub utilization: Option<i32>,
+    /// GED `current_freqency`.
+    pub ged_freq_mhz: Option<i32>,
+}
+
+/// First integer after `key` on the line: `g_cur_gpu_freq = 570000,`,
+/// `[02] freq: 880000`, `fix target opp index: -1`.
+pub(crate) fn value_after(line: &str, key: &str) -> Option<i64> {
+    let rest = &line[line.find(key)? + key.len()..];
+    let start = rest.find(|c: char| c.is_ascii_digit())?;
+    let negative = rest[..start].ends_with('-');
+    let digits: String = rest[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let value: i64 = digits.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+fn khz_to_mhz(khz: i64) -> i32 {
+    (khz / 1000) as i32
+}
+
+fn gpufreq_dir(proc: &str) -> Option<(GpufreqVersion, String)> {
+    [
+        ("gpufreqv2", GpufreqVersion::V2),
+        ("gpufreq", GpufreqVersion::V1),
+    ]
+    .into_iter()
+    .map(|(name, version)| (version, format!("{}/{}", proc, name)))
+    .find(|(_, dir)| utils::file_exists(dir))
+}
+
+fn read_node(dir: &str, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| utils::read_sysfs_full(&format!("{}/{}", dir, name)))
+}
+
+/// `[0] freq = 902000, volt = 80000, vsram = 90000` (v1) or
+/// `[05*] freq: 560000, volt: 62500, vsram: 75000` (v2, `*` marks the
+/// current OPP). Returns the table and the marked index.
+fn parse_opp_table(text: &str) -> (Vec<MtkGpuOpp>, Option<i32>) {
+    let mut current = None;
+    let opps = text
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (index, rest) = line.strip_prefix('[')?.split_once(']')?;
+            let marked = index.ends_with('*');
+            let index: i32 = index.trim_end_matches('*').trim().parse().ok()?;
+            if marked {
+                current = Some(index);
+            }
+            Some(MtkGpuOpp {
+                index,
+                freq_khz: value_after(rest, "freq")?.try_into().ok()?,
+                volt_mv: value_after(rest, "volt").map(|v| (v / 100) as i32),
+            })
+        })
+        .collect();
+    (opps, current)
+}
+
+/// v1 `gpufreq_var_dump` / v2 `gpufreq_status`: the current clock and OPP.
+fn parse_status(text: &str) -> (Option<i32>, Option<i32>) {
+    let mut freq = None;
+    let mut opp = None;
+    for line in text.lines() {
+        let lower = line.to_lowercase();
+        if freq.is_none() {
+            freq = value_after(&lower, "g_cur_gpu_freq")
+                .or_else(|| {
+                    (lower.contains("cur") && lower.contains("freq"))
+                        .then(|| value_after(&lower, "freq"))
+                        .flatten()
+                })
+                .map(khz_to_mhz);
+        }
+        if opp.is_none() {
+            opp = value_after(&lower, "g_cur_gpu_oppidx")
+                .or_else(|| {
+                    (lower.contains("cur") && lower.contains("opp"))
+                        .then(|| {
+                            value_after(&lower, "index").or_else(|| value_after(&lower, "idx"))
+                        })
+                        .flatten()
+                })
+                .map(|v| v as i32);
+        }
+    }
+    (freq, opp)
+}
+
+/// v1 `gpufreq_opp_freq` names the fixed frequency, v2
+/// `fix_target_opp_index` the index; both say `disabled` or -1 when free.
+fn parse_fixed(text: &str, version: GpufreqVersion, opps: &[MtkGpuOpp]) -> Option<i32> {
+    let lower = text.to_lowercase();
+    if lower.contains("disable") {
+        return None;
+    }
+    match version {
+        GpufreqVersion::V1 => {
+            let khz = value_after(&lower, "freq").or_else(|| value_after(&lower, ""))?;
+            opps.iter()
+                .find(|opp| opp.freq_khz as i64 == khz)
+                .map(|opp| opp.index)
+        }
+        GpufreqVersion::V2 => value_after(&lower, "index")
+            .or_else(|| value_after(&lower, ""))
+            .filter(|index| *index >= 0)
+            .map(|index| index as i32),
+    }
+}
+
+/// GED prints `loading idle block`, or a bare percentage on older builds.
+pub(crate) fn read_ged_at(sys: &str) -> (Option<i32>, Option<i32>) {
+    let dir = format!("{}/kernel/ged/hal", sys);
+    let utilization = utils::read_sysfs_full(&format!("{}/gpu_utilization", dir))
+        .and_then(|text| text.split_whitespace().next()?.parse::<i32>().ok())
+        .map(|v| v.clamp(0, 100));
+    // `current_freqency` (sic) is `<opp index> <kHz>`.
+    let freq = utils::read_sysfs_full(&format!("{}/current_freqency", dir))
+        .and_then(|text| text.split_whitespace().last()?.parse::<i64>().ok())
+        .map(khz_to_mhz);
+    (utilization, freq)
+}
+
+pub(crate) fn read_mtk_gpu_state_at(sys: &str, proc: &str) -> Option<MtkGpuState> {
+    let (version, dir) = gpufreq_dir(proc)?;
+    let (opps, marked) = read_node(&dir, &["gpu_working_opp_table", "gpufreq_opp_dump"])
+        .map(|text| parse_opp_table(&text))
+        .unwrap_or_default();
+    let (cur_freq_mhz, cur_opp) = read_node(&dir, &["gpufreq_status", "gpufreq_var_dump"])
+        .map(|text| parse_status(&text))
+        .unwrap_or_default();
+    let fixed_opp = read_node(&dir, &["fix_target_opp_index", "gpufreq_opp_freq"])
+        .and_then(|text| parse_fixed(&text, version, &opps));
+    let (utilization, ged_freq_mhz) = read_ged_at(sys);
+
+    Some(MtkGpuState {
+        version,
+        cur_opp: cur_opp.or(marked),
+        cur_freq_mhz: cur_freq_mhz.or(ged_freq_mhz),
+        opps,
+        fixed_opp,
+        utilization,
+        ged_freq_mhz,
+    })
+}
+
+/// Pins the GPU to the fastest OPP not above `mhz`, or hands it back to
+/// DVFS when `mhz` is `None`. `actual` is the OPP read back afterwards.
+fn set_fixed_freq_at(sys: &str, proc: &str, mhz: Option<i32>) -> Result<WriteResult, String> {
+    let state = read_mtk_gpu_state_at(sys, proc).ok_or("MediaTek gpufreq is not available")?;
+    let (_, dir) = gpufreq_dir(proc).ok_or("MediaTek gpufreq is not available")?;
+
+    let target = match mhz {
+        None => None,
+        Some(mhz) if mhz <= 0 => return Err(format!("Invalid frequency: {} MHz", mhz)),
+        Some(mhz) => {
+            let khz = mhz as u64 * 1000;
+            let opp = state
+                .opps
+                .iter()
+                .filter(|opp| opp.freq_khz <= khz)
+                .max_by_key(|opp| opp.freq_khz)
+                .or_else(|| state.opps.iter().min_by_key(|opp| opp.freq_khz))
+                .ok_or("gpufreq reports no OPP table")?;
+            Some(opp.clone())
+        }
+    };
+
+    let (node, value) = match (state.version, &target) {
+        (GpufreqVersion::V2, Some(opp)) => ("fix_target_opp_index", opp.index.to_string()),
+        (GpufreqVersion::V2, None) => ("fix_target_opp_index", "-1".to_string()),
+        (GpufreqVersion::V1, Some(opp)) => ("gpufreq_opp_freq", opp.freq_khz.to_string()),
+        (GpufreqVersion::V1, None) => ("gpufreq_opp_freq", "0".to_string()),
+    };
+    let path = format!("{}/{}", dir, node);
+    let written = utils::write_sysfs(&path, &value);
+    let fixed = read_mtk_gpu_state_at(sys, proc).and_then(|s| s.fixed_opp);
+
+    Ok(WriteResult {
+        path,
+        requested: value,
+        actual: fixed.map_or_else(|| "dvfs".to_string(), |opp| opp.to_string()),
+        success: written && fixed == target.map(|opp| opp.index),
+    })
+}
+
+pub fn read_mtk_gpu_state() -> Option<MtkGpuState> {
+    read_mtk_gpu_state_at(SYS_ROOT, PROC_ROOT)
+}
+
+/// GED first, since it is a single cheap read, then gpufreq.
+pub fn read_mtk_gpu_cur_freq() -> Option<i32> {
+    read_ged_at(SYS_ROOT)
+        .1
+        .or_else(|| read_mtk_gpu_state()?.cur_freq_mhz)
+}
+
+pub fn read_mtk_gpu_utilization() -> Option<i32> {
+    read_ged_at(SYS_ROOT).0
+}
+
+/// Ascending, in MHz.
+pub fn read_mtk_gpu_frequencies() -> Vec<i32> {
+    let mut freqs: Vec<i32> = read_mtk_gpu_state()
+        .map(|state| {
+            state
+                .opps
+                .iter()
+                .map(|opp| khz_to_mhz(opp.freq_khz as i64))
+                .collect()
+        })
+        .unwrap_or_default();
+    freqs.sort_unstable();
+    freqs.dedup();
+    freqs
+}
+
+pub fn set_mtk_gpu_fixed_freq(mhz: Option<i32>) -> Result<WriteResult, String> {
+    set_fixed_freq_at(SYS_ROOT, PROC_ROOT, mhz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_gpufreq_v1() {
+        let root = fixture::build(
+            "mtk_gpufreq_v1",
+            &[
+                (
+                    "proc/gpufreq/gpufreq_opp_dump",
+                    "[0] freq = 902000, volt = 80000, vsram = 90000\n\
+                     [1] freq = 750000, volt = 72500, vsram = 90000\n\
+                     [2] freq = 390000, volt = 65000, vsram = 90000\n",
+                ),
+                (
+                    "proc/gpufreq/gpufreq_var_dump",
+                    "g_cur_gpu_freq = 750000, g_cur_gpu_volt = 72500\ng_cur_gpu_OPPidx = 1\n",
+                ),
+                (
+                    "proc/gpufreq/gpufreq_opp_freq",
+                    "[GPU-DVFS] fixed OPP is disabled\n",
+                ),
+                ("sys/kernel/ged/hal/gpu_utilization", "61 39 0"),
+                ("sys/kernel/ged/hal/current_freqency", "1 750000"),
+            ],
+        );
+        let (sys, proc) = (format!("{}/sys", root), format!("{}/proc", root));
+
+        let state = read_mtk_gpu_state_at(&sys, &proc).unwrap();
+        assert_eq!(state.version, GpufreqVersion::V1);
+        assert_eq!(state.opps.len(), 3);
+        assert_eq!(state.opps[0].volt_mv, Some(800));
+        assert_eq!(state.cur_freq_mhz, Some(750));
+        assert_eq!(state.cur_opp, Some(1));
+        assert_eq!(state.fixed_opp, None);
+        assert_eq!(state.utilization, Some(61));
+        assert_eq!(state.ged_freq_mhz, Some(750));
+
+        // The fixture node echoes the written kHz, which maps back to OPP 1.
+        let result = set_fixed_freq_at(&sys, &proc, Some(800)).unwrap();
+        assert_eq!(result.requested, "750000");
+        assert!(result.success);
+    }
+
+    #[test]
+    fn test_gpufreq_v2() {
+        let root = fixture::build(
+            "mtk_gpufreq_v2",
+            &[
+                (
+                    "proc/gpufreqv2/gpu_working_opp_table",
+                    "[00] freq: 886000, volt: 75000, vsram: 75000\n\
+                     [01*] freq: 879000, volt: 74375, vsram: 75000\n\
+                     [02] freq: 265000, volt: 55000, vsram: 75000\n",
+                ),
+                ("proc/gpufreqv2/fix_target_opp_index", "-1"),
+            ],
+        );
+        let (sys, proc) = (format!("{}/sys", root), format!("{}/proc", root));
+
+        let state = read_mtk_gpu_state_at(&sys, &proc).unwrap();
+        assert_eq!(state.version, GpufreqVersion::V2);
+        assert_eq!(state.cur_opp, Some(1));
+        assert_eq!(state.fixed_opp, None);
+
+        let result = set_fixed_freq_at(&sys, &proc, Some(300)).unwrap();
+        assert_eq!(result.requested, "2");
+        assert!(result.success);
+        let result = set_fixed_freq_at(&sys, &proc, None).unwrap();
+        assert_eq!(result.actual, "dvfs");
+        assert!(result.success);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod gpufreq;
+pub mod platform;
+pub mod ppm;
+pub use gpufreq::*;
+pub use platform::*;
+pub use ppm::*;
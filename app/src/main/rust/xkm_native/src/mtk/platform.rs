@@ -0,0 +1,129 @@
+use super::gpufreq::{self, MtkGpuState};
+use super::ppm::{self, MtkCpuState};
+use crate::utils::{self, WriteResult};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+const SYS_ROOT: &str = "/sys";
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtkPlatform {
+    pub detected: bool,
+    /// `ro.board.platform` / `ro.hardware`, e.g. `mt6893`.
+    pub platform: Option<String>,
+    pub gpu: Option<MtkGpuState>,
+    pub cpu: Option<MtkCpuState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MtkParam {
+    /// MHz, 0 hands the GPU back to DVFS.
+    GpuFixedFreq,
+    PpmEnabled,
+    /// Target is the policy index; value 0 or 1.
+    PpmPolicy,
+    /// Target is the PPM cluster; kHz, -1 lifts the limit.
+    PpmMinFreq,
+    PpmMaxFreq,
+    /// Target is the domain's position in `/proc/cpufreq`; kHz, 0 releases.
+    CpuFixedFreq,
+    CpuPowerMode,
+}
+
+impl MtkParam {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+}
+
+fn platform_property() -> Option<String> {
+    ["ro.board.platform", "ro.hardware", "ro.mediatek.platform"]
+        .iter()
+        .filter_map(|key| utils::get_system_property(key))
+        .map(|v| v.trim().to_lowercase())
+        .find(|v| v.starts_with("mt"))
+}
+
+/// Any of the MediaTek-only driver nodes, so detection still works where
+/// the properties are unreadable.
+fn has_mtk_nodes(sys: &str, proc: &str) -> bool {
+    [
+        format!("{}/gpufreqv2", proc),
+        format!("{}/gpufreq", proc),
+        format!("{}/ppm", proc),
+        format!("{}/kernel/ged/hal", sys),
+    ]
+    .iter()
+    .any(|path| utils::file_exists(path))
+}
+
+/// True on MediaTek SoCs. Cached, the answer never changes at runtime.
+pub fn is_mtk_platform() -> bool {
+    static DETECTED: OnceCell<bool> = OnceCell::new();
+    *DETECTED.get_or_init(|| platform_property().is_some() || has_mtk_nodes(SYS_ROOT, PROC_ROOT))
+}
+
+pub fn read_mtk_platform() -> MtkPlatform {
+    if !is_mtk_platform() {
+        return MtkPlatform {
+            detected: false,
+            platform: None,
+            gpu: None,
+            cpu: None,
+        };
+    }
+    MtkPlatform {
+        detected: true,
+        platform: platform_property(),
+        gpu: gpufreq::read_mtk_gpu_state(),
+        cpu: ppm::read_mtk_cpu_state(),
+    }
+}
+
+fn parse_int(value: &str) -> Result<i64, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value: {}", value))
+}
+
+/// Applies one MediaTek parameter. Writes that touch several nodes (PPM
+/// limits) return one result per node.
+pub fn set_mtk_param(
+    param: MtkParam,
+    target: i32,
+    value: &str,
+) -> Result<Vec<WriteResult>, String> {
+    if !is_mtk_platform() {
+        return Err("Not a MediaTek platform".to_string());
+    }
+    let value = parse_int(value)?;
+
+    match param {
+        MtkParam::GpuFixedFreq => {
+            let mhz = (value > 0).then_some(value as i32);
+            gpufreq::set_mtk_gpu_fixed_freq(mhz).map(|r| vec![r])
+        }
+        MtkParam::PpmEnabled => Ok(vec![ppm::set_ppm_enabled(value != 0)]),
+        MtkParam::PpmPolicy => ppm::set_ppm_policy(target, value != 0).map(|r| vec![r]),
+        MtkParam::PpmMinFreq | MtkParam::PpmMaxFreq => {
+            let khz = (value > 0).then_some(value);
+            let current = ppm::read_mtk_cpu_state()
+                .and_then(|s| s.limits.into_iter().find(|l| l.cluster == target));
+            let (min, max) = match param {
+                MtkParam::PpmMinFreq => (khz, current.and_then(|l| l.max_khz)),
+                _ => (current.and_then(|l| l.min_khz), khz),
+            };
+            ppm::set_ppm_cpu_limit(target, min, max)
+        }
+        MtkParam::CpuFixedFreq => {
+            let domain = ppm::read_mtk_cpu_state()
+                .and_then(|s| s.domains.into_iter().nth(target.max(0) as usize))
+                .ok_or_else(|| format!("Unknown cpufreq domain: {}", target))?;
+            ppm::set_mtk_cpu_fixed_freq(&domain.name, value.max(0) as u64).map(|r| vec![r])
+        }
+        MtkParam::CpuPowerMode => Ok(vec![ppm::set_mtk_cpu_power_mode(value as i32)]),
+    }
+}
@@ -0,0 +1,400 @@
+use super::gpufreq::value_after;
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PpmPolicy {
+    pub index: i32,
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// PPM user limits in kHz; `None` when the cluster is unrestricted (-1).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PpmClusterLimit {
+    pub cluster: i32,
+    pub min_khz: Option<i64>,
+    pub max_khz: Option<i64>,
+}
+
+/// One `/proc/cpufreq/MT_CPU_DVFS_*` domain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtkCpuDvfs {
+    pub name: String,
+    /// Fastest first, as indexed by `cpufreq_oppidx`.
+    pub opps_khz: Vec<u64>,
+    pub cur_opp: Option<i32>,
+    pub cur_freq_khz: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MtkCpuState {
+    pub ppm_enabled: Option<bool>,
+    pub policies: Vec<PpmPolicy>,
+    pub limits: Vec<PpmClusterLimit>,
+    pub domains: Vec<MtkCpuDvfs>,
+    pub power_mode: Option<i32>,
+}
+
+fn ppm_dir(proc: &str) -> String {
+    format!("{}/ppm", proc)
+}
+
+fn cpufreq_dir(proc: &str) -> String {
+    format!("{}/cpufreq", proc)
+}
+
+/// `ppm is enabled` / `ppm is disabled`, or a bare 0/1.
+fn parse_enabled(text: &str) -> Option<bool> {
+    let text = text.trim().to_lowercase();
+    if text.contains("disable") || text == "0" {
+        Some(false)
+    } else if text.contains("enable") || text == "1" {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// `[0] PPM_POLICY_PTPOD: enabled`.
+fn parse_policy_status(text: &str) -> Vec<PpmPolicy> {
+    text.lines()
+        .filter_map(|line| {
+            let (index, rest) = line.trim().strip_prefix('[')?.split_once(']')?;
+            let (name, state) = rest.split_once(':')?;
+            Some(PpmPolicy {
+                index: index.trim().parse().ok()?,
+                name: name.trim().to_string(),
+                enabled: parse_enabled(state)?,
+            })
+        })
+        .collect()
+}
+
+/// `cluster 0: 1800000` rows of the hard user limit nodes.
+fn parse_cluster_values(text: &str) -> Vec<(i32, i64)> {
+    text.lines()
+        .filter_map(|line| {
+            let (head, tail) = line.split_once(':')?;
+            Some((value_after(head, "cluster")? as i32, value_after(tail, "")?))
+        })
+        .collect()
+}
+
+/// Indexed by PPM cluster, which follows the CPU topology order.
+pub(crate) fn read_ppm_limits_at(proc: &str) -> Vec<PpmClusterLimit> {
+    let read = |node: &str| {
+        utils::read_sysfs_full(&format!("{}/policy/{}", ppm_dir(proc), node))
+            .map(|text| parse_cluster_values(&text))
+            .unwrap_or_default()
+    };
+    let mins = read("hard_userlimit_min_cpu_freq");
+    let maxs = read("hard_userlimit_max_cpu_freq");
+    let lookup = |rows: &[(i32, i64)], cluster: i32| {
+        rows.iter()
+            .find(|(c, _)| *c == cluster)
+            .map(|(_, v)| *v)
+            .filter(|v| *v > 0)
+    };
+
+    let mut clusters: Vec<i32> = mins.iter().chain(&maxs).map(|(c, _)| *c).collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    clusters
+        .into_iter()
+        .map(|cluster| PpmClusterLimit {
+            cluster,
+            min_khz: lookup(&mins, cluster),
+            max_khz: lookup(&maxs, cluster),
+        })
+        .collect()
+}
+
+/// `cpufreq_oppidx = 3` followed by one `OP(2000000, 118750),` row per OPP.
+fn parse_oppidx(text: &str) -> (Vec<u64>, Option<i32>) {
+    let mut cur = None;
+    let mut opps = Vec::new();
+    for line in text.lines() {
+        if let Some(freq) = value_after(line, "OP(") {
+            opps.push(freq as u64);
+        } else if let Some(index) = value_after(line, "cpufreq_oppidx") {
+            cur = Some(index as i32);
+        }
+    }
+    (opps, cur)
+}
+
+fn read_domains_at(proc: &str) -> Vec<MtkCpuDvfs> {
+    let root = cpufreq_dir(proc);
+    utils::list_dir(&root)
+        .into_iter()
+        .filter(|name| name.starts_with("MT_CPU_DVFS_"))
+        .map(|name| {
+            let dir = format!("{}/{}", root, name);
+            let (opps_khz, cur_opp) = utils::read_sysfs_full(&format!("{}/cpufreq_oppidx", dir))
+                .map(|text| parse_oppidx(&text))
+                .unwrap_or_default();
+            let cur_freq_khz = utils::read_sysfs_full(&format!("{}/cpufreq_freq", dir))
+                .and_then(|text| value_after(&text, ""))
+                .map(|v| v as u64)
+                .or_else(|| opps_khz.get(cur_opp? as usize).copied());
+            MtkCpuDvfs {
+                name,
+                opps_khz,
+                cur_opp,
+                cur_freq_khz,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn read_mtk_cpu_state_at(proc: &str) -> Option<MtkCpuState> {
+    let ppm = ppm_dir(proc);
+    let cpufreq = cpufreq_dir(proc);
+    if !utils::file_exists(&ppm) && !utils::file_exists(&cpufreq) {
+        return None;
+    }
+
+    Some(MtkCpuState {
+        ppm_enabled: utils::read_sysfs_full(&format!("{}/enabled", ppm))
+            .and_then(|text| parse_enabled(&text)),
+        policies: utils::read_sysfs_full(&format!("{}/policy_status", ppm))
+            .map(|text| parse_policy_status(&text))
+            .unwrap_or_default(),
+        limits: read_ppm_limits_at(proc),
+        domains: read_domains_at(proc),
+        power_mode: utils::read_sysfs_full(&format!("{}/cpufreq_power_mode", cpufreq))
+            .and_then(|text| value_after(&text, ""))
+            .map(|v| v as i32),
+    })
+}
+
+/// Writes `value` and reports the state `check` reads back, since these
+/// proc nodes print a summary rather than echoing the input.
+fn write_checked(
+    path: String,
+    value: String,
+    check: impl FnOnce() -> Option<String>,
+    expected: &str,
+) -> WriteResult {
+    let written = utils::write_sysfs(&path, &value);
+    let actual = check().unwrap_or_default();
+    WriteResult {
+        success: written && actual == expected,
+        path,
+        requested: value,
+        actual,
+    }
+}
+
+fn set_ppm_enabled_at(proc: &str, enabled: bool) -> WriteResult {
+    let value = (enabled as i32).to_string();
+    write_checked(
+        format!("{}/enabled", ppm_dir(proc)),
+        value.clone(),
+        || {
+            let state = read_mtk_cpu_state_at(proc)?.ppm_enabled?;
+            Some((state as i32).to_string())
+        },
+        &value,
+    )
+}
+
+fn set_ppm_policy_at(proc: &str, index: i32, enabled: bool) -> Result<WriteResult, String> {
+    let state = read_mtk_cpu_state_at(proc).ok_or("MediaTek PPM is not available")?;
+    if !state.policies.iter().any(|p| p.index == index) {
+        return Err(format!("Unknown PPM policy: {}", index));
+    }
+    let expected = (enabled as i32).to_string();
+    Ok(write_checked(
+        format!("{}/policy_status", ppm_dir(proc)),
+        format!("{} {}", index, expected),
+        || {
+            let policy = read_mtk_cpu_state_at(proc)?
+                .policies
+                .into_iter()
+                .find(|p| p.index == index)?;
+            Some((policy.enabled as i32).to_string())
+        },
+        &expected,
+    ))
+}
+
+/// Applies a cluster's PPM hard user limits; `None` lifts a bound. The
+/// bound that would cross the other one is written second.
+fn set_ppm_cpu_limit_at(
+    proc: &str,
+    cluster: i32,
+    min_khz: Option<i64>,
+    max_khz: Option<i64>,
+) -> Result<Vec<WriteResult>, String> {
+    let state = read_mtk_cpu_state_at(proc).ok_or("MediaTek PPM is not available")?;
+    if let (Some(min), Some(max)) = (min_khz, max_khz)
+        && min > max
+    {
+        return Err(format!("Minimum {} kHz is above maximum {} kHz", min, max));
+    }
+    let current = state.limits.iter().find(|l| l.cluster == cluster);
+    let current_max = current.and_then(|l| l.max_khz);
+
+    let write = |node: &str, khz: Option<i64>, pick: fn(&PpmClusterLimit) -> Option<i64>| {
+        let khz = khz.unwrap_or(-1);
+        let expected = if khz > 0 {
+            khz.to_string()
+        } else {
+            "-1".to_string()
+        };
+        write_checked(
+            format!("{}/policy/{}", ppm_dir(proc), node),
+            format!("{} {}", cluster, khz),
+            || {
+                let limits = read_ppm_limits_at(proc);
+                let limit = limits.iter().find(|l| l.cluster == cluster);
+                Some(limit.and_then(pick).unwrap_or(-1).to_string())
+            },
+            &expected,
+        )
+    };
+    let min = || write("hard_userlimit_min_cpu_freq", min_khz, |l| l.min_khz);
+    let max = || write("hard_userlimit_max_cpu_freq", max_khz, |l| l.max_khz);
+
+    let raising = min_khz.zip(current_max).is_some_and(|(min, cur)| min > cur);
+    Ok(if raising {
+        vec![max(), min()]
+    } else {
+        vec![min(), max()]
+    })
+}
+
+/// Pins a DVFS domain to the fastest OPP not above `khz`; 0 releases it.
+fn set_cpu_fixed_freq_at(proc: &str, domain: &str, khz: u64) -> Result<WriteResult, String> {
+    let state = read_mtk_cpu_state_at(proc).ok_or("MediaTek cpufreq is not available")?;
+    let dvfs = state
+        .domains
+        .iter()
+        .find(|d| d.name == domain)
+        .ok_or_else(|| format!("Unknown cpufreq domain: {}", domain))?;
+
+    let target = if khz == 0 {
+        0
+    } else {
+        dvfs.opps_khz
+            .iter()
+            .copied()
+            .filter(|f| *f <= khz)
+            .max()
+            .or_else(|| dvfs.opps_khz.iter().copied().min())
+            .unwrap_or(khz)
+    };
+    let path = format!("{}/{}/cpufreq_freq", cpufreq_dir(proc), domain);
+    let written = utils::write_sysfs(&path, &target.to_string());
+    let actual = read_domains_at(proc)
+        .into_iter()
+        .find(|d| d.name == domain)
+        .and_then(|d| d.cur_freq_khz);
+
+    Ok(WriteResult {
+        path,
+        requested: target.to_string(),
+        actual: actual.map(|f| f.to_string()).unwrap_or_default(),
+        success: written && (target == 0 || actual == Some(target)),
+    })
+}
+
+fn set_power_mode_at(proc: &str, mode: i32) -> WriteResult {
+    let value = mode.to_string();
+    write_checked(
+        format!("{}/cpufreq_power_mode", cpufreq_dir(proc)),
+        value.clone(),
+        || Some(read_mtk_cpu_state_at(proc)?.power_mode?.to_string()),
+        &value,
+    )
+}
+
+pub fn read_mtk_cpu_state() -> Option<MtkCpuState> {
+    read_mtk_cpu_state_at(PROC_ROOT)
+}
+
+pub fn set_ppm_enabled(enabled: bool) -> WriteResult {
+    set_ppm_enabled_at(PROC_ROOT, enabled)
+}
+
+pub fn set_ppm_policy(index: i32, enabled: bool) -> Result<WriteResult, String> {
+    set_ppm_policy_at(PROC_ROOT, index, enabled)
+}
+
+pub fn set_ppm_cpu_limit(
+    cluster: i32,
+    min_khz: Option<i64>,
+    max_khz: Option<i64>,
+) -> Result<Vec<WriteResult>, String> {
+    set_ppm_cpu_limit_at(PROC_ROOT, cluster, min_khz, max_khz)
+}
+
+pub fn set_mtk_cpu_fixed_freq(domain: &str, khz: u64) -> Result<WriteResult, String> {
+    set_cpu_fixed_freq_at(PROC_ROOT, domain, khz)
+}
+
+pub fn set_mtk_cpu_power_mode(mode: i32) -> WriteResult {
+    set_power_mode_at(PROC_ROOT, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_ppm_and_cpufreq() {
+        let proc = fixture::build(
+            "mtk_ppm",
+            &[
+                ("ppm/enabled", "ppm is enabled\n"),
+                (
+                    "ppm/policy_status",
+                    "[0] PPM_POLICY_PTPOD: enabled\n\
+                     [1] PPM_POLICY_UT: disabled\n\
+                     [2] PPM_POLICY_HARD_USER_LIMIT: enabled\n",
+                ),
+                (
+                    "ppm/policy/hard_userlimit_min_cpu_freq",
+                    "cluster 0: -1\ncluster 1: 1000000\n",
+                ),
+                (
+                    "ppm/policy/hard_userlimit_max_cpu_freq",
+                    "cluster 0: 1800000\ncluster 1: -1\n",
+                ),
+                (
+                    "cpufreq/MT_CPU_DVFS_LL/cpufreq_oppidx",
+                    "[MT_CPU_DVFS_LL/0]\ncpufreq_oppidx = 1\n\
+                     \tOP(2000000, 118750),\n\tOP(1800000, 112500),\n\tOP(500000, 60000),\n",
+                ),
+                ("cpufreq/MT_CPU_DVFS_LL/cpufreq_freq", "1800000 KHz\n"),
+                (
+                    "cpufreq/cpufreq_power_mode",
+                    "cpufreq_power_mode = 0 (Default)\n",
+                ),
+            ],
+        );
+
+        let state = read_mtk_cpu_state_at(&proc).unwrap();
+        assert_eq!(state.ppm_enabled, Some(true));
+        assert_eq!(state.policies.len(), 3);
+        assert!(!state.policies[1].enabled);
+        assert_eq!(state.limits[0].min_khz, None);
+        assert_eq!(state.limits[0].max_khz, Some(1_800_000));
+        assert_eq!(state.limits[1].min_khz, Some(1_000_000));
+        assert_eq!(state.domains[0].name, "MT_CPU_DVFS_LL");
+        assert_eq!(state.domains[0].cur_freq_khz, Some(1_800_000));
+        assert_eq!(state.power_mode, Some(0));
+
+        assert!(set_ppm_policy_at(&proc, 9, true).is_err());
+        assert!(set_ppm_cpu_limit_at(&proc, 0, Some(2_000_000), Some(1_000_000)).is_err());
+
+        let result = set_cpu_fixed_freq_at(&proc, "MT_CPU_DVFS_LL", 1_900_000).unwrap();
+        assert_eq!(result.requested, "1800000");
+        assert!(result.success);
+    }
+}
@@ -1,4 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod power;
 pub mod thermal;
+pub mod uid_cpu;
 pub use power::*;
 pub use thermal::*;
+pub use uid_cpu::*;
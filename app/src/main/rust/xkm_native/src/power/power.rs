@@ -16,19 +16,19 @@ static INIT: Once = Once::new();
 fn ensure_init() {
     INIT.call_once(|| unsafe {
         CAPACITY_FD = libc::open(
-            b"/sys/class/power_supply/battery/capacity\0".as_ptr() as *const libc::c_char,
+            c"/sys/class/power_supply/battery/capacity".as_ptr(),
             libc::O_RDONLY,
         );
         TEMP_FD = libc::open(
-            b"/sys/class/power_supply/battery/temp\0".as_ptr() as *const libc::c_char,
+            c"/sys/class/power_supply/battery/temp".as_ptr(),
             libc::O_RDONLY,
         );
         VOLTAGE_FD = libc::open(
-            b"/sys/class/power_supply/battery/voltage_now\0".as_ptr() as *const libc::c_char,
+            c"/sys/class/power_supply/battery/voltage_now".as_ptr(),
             libc::O_RDONLY,
         );
         CURRENT_FD = libc::open(
-            b"/sys/class/power_supply/battery/current_now\0".as_ptr() as *const libc::c_char,
+            c"/sys/class/power_supply/battery/current_now".as_ptr(),
             libc::O_RDONLY,
         );
     });
@@ -82,7 +82,7 @@ pub fn read_battery_level() -> i32 {
         }
     }
 
-    utils::read_sysfs_int("/sys/class/power_supply/battery/capacity", 500).unwrap_or(0) as i32
+    utils::read_sysfs_int("/sys/class/power_supply/battery/capacity", 500).unwrap_or(0)
 }
 
 #[inline(always)]
@@ -95,7 +95,7 @@ pub fn read_battery_temp() -> i32 {
         }
     }
 
-    utils::read_sysfs_int("/sys/class/power_supply/battery/temp", 500).unwrap_or(0) as i32
+    utils::read_sysfs_int("/sys/class/power_supply/battery/temp", 500).unwrap_or(0)
 }
 
 #[inline(always)]
@@ -110,7 +110,7 @@ pub fn read_battery_voltage_mv() -> i32 {
 
     let voltage_uv =
         utils::read_sysfs_int("/sys/class/power_supply/battery/voltage_now", 500).unwrap_or(0);
-    (voltage_uv / 1000) as i32
+    voltage_uv / 1000
 }
 
 #[inline(always)]
@@ -144,11 +144,11 @@ pub fn is_charging() -> bool {
 }
 
 pub fn read_wakeup_count() -> i32 {
-    utils::read_sysfs_int("/sys/power/wakeup_count", 1000).unwrap_or(0) as i32
+    utils::read_sysfs_int("/sys/power/wakeup_count", 1000).unwrap_or(0)
 }
 
 pub fn read_suspend_count() -> i32 {
-    utils::read_sysfs_int("/sys/kernel/debug/suspend_stats/success", 1000).unwrap_or(0) as i32
+    utils::read_sysfs_int("/sys/kernel/debug/suspend_stats/success", 1000).unwrap_or(0)
 }
 
 pub fn read_cycle_count() -> i32 {
@@ -160,7 +160,7 @@ pub fn read_cycle_count() -> i32 {
 
     for path in &paths {
         if let Some(cycles) = utils::read_sysfs_int(path, 1000) {
-            return cycles as i32;
+            return cycles;
         }
     }
 
@@ -181,7 +181,7 @@ pub fn read_battery_capacity_level() -> f32 {
 
     if design > 0.0 && current > 0.0 {
         let capacity = (current / design) * 100.0;
-        if capacity >= 50.0 && capacity <= 100.0 {
+        if (50.0..=100.0).contains(&capacity) {
             return capacity;
         }
     }
@@ -0,0 +1,734 @@
+use crate::cpu::hotplug::{cluster_topology, parse_cpu_list};
+use crate::devicetree::{devicetree as dt, opp};
+use crate::utils;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const PROC_ROOT: &str = "/proc";
+const SYS_ROOT: &str = "/sys";
+
+/// Number of slots the window is divided into; retained samples are at least
+/// one slot apart, so the history stays bounded however often the caller
+/// polls.
+const HISTORY_SLOTS: u32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerModelSource {
+    /// `/sys/kernel/debug/energy_model` performance states.
+    EnergyModel,
+    /// `dynamic-power-coefficient` of the CPU node and OPP voltages.
+    DeviceTree,
+    /// Capacity and frequency only. Energy is in relative units.
+    Estimated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyPower {
+    pub policy: i32,
+    pub cpus: Vec<i32>,
+    /// Frequency columns of `uid_time_in_state` belonging to this policy.
+    pub freqs_khz: Vec<u64>,
+    /// Active power of one core at each of `freqs_khz`.
+    pub power_mw: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UidSources {
+    pub cputime: bool,
+    pub time_in_state: bool,
+    pub concurrent_active: bool,
+    pub concurrent_policy: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UidCpuUsage {
+    pub uid: u32,
+    pub user_ms: u64,
+    pub system_ms: u64,
+    /// Per policy, aligned with `PolicyPower::freqs_khz`.
+    pub time_in_state_ms: Vec<Vec<u64>>,
+    /// Entry `i` is time spent running while `i + 1` CPUs were busy.
+    pub concurrent_active_ms: Vec<u64>,
+    /// Per policy, entry `i` is time spent running while `i + 1` CPUs of
+    /// that policy were busy.
+    pub concurrent_policy_ms: Vec<Vec<u64>>,
+    pub energy_mj: f64,
+    /// Share of the attributed energy, or of CPU time when the kernel has no
+    /// `uid_time_in_state`.
+    pub energy_share: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UidCpuReport {
+    /// No earlier sample to diff against, so the figures are totals since
+    /// boot.
+    pub since_boot: bool,
+    pub interval_ms: u64,
+    pub sources: UidSources,
+    pub power_model: PowerModelSource,
+    pub policies: Vec<PolicyPower>,
+    pub uids: Vec<UidCpuUsage>,
+}
+
+type UidRows = HashMap<u32, Vec<u64>>;
+
+/// `(kHz, mW)` per performance state, ascending.
+type PowerStates = Vec<(u64, f64)>;
+
+struct RawSample {
+    taken: Instant,
+    cputime: UidRows,
+    time_in_state: UidRows,
+    concurrent_active: UidRows,
+    concurrent_policy: UidRows,
+}
+
+#[derive(Default)]
+struct UidHistory {
+    samples: Vec<RawSample>,
+    /// Per-policy cost of each `uid_time_in_state` column; fixed after boot.
+    model: Option<(PowerModelSource, Vec<Vec<f64>>)>,
+}
+
+static UID_HISTORY: Lazy<Mutex<UidHistory>> = Lazy::new(|| Mutex::new(UidHistory::default()));
+
+fn tick_ms() -> f64 {
+    let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if hz > 0 { 1000.0 / hz as f64 } else { 10.0 }
+}
+
+/// `<uid>: <value> <value> ...` rows. Header rows do not start with a number
+/// and are skipped.
+fn parse_uid_rows(text: &str) -> UidRows {
+    text.lines()
+        .filter_map(|line| {
+            let (uid, values) = line.split_once(':')?;
+            let uid = uid.trim().parse().ok()?;
+            Some((
+                uid,
+                values
+                    .split_whitespace()
+                    .filter_map(|v| v.parse().ok())
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+/// `uid_time_in_state` prints every policy's frequency table in one header
+/// row. The per-policy table sizes split it when they add up; otherwise each
+/// drop in frequency starts the next policy.
+fn split_freq_header(freqs: &[u64], counts: &[usize]) -> Vec<Vec<u64>> {
+    if !counts.is_empty() && counts.iter().sum::<usize>() == freqs.len() {
+        let mut rest = freqs;
+        return counts
+            .iter()
+            .map(|&count| {
+                let (head, tail) = rest.split_at(count);
+                rest = tail;
+                head.to_vec()
+            })
+            .collect();
+    }
+
+    let mut policies: Vec<Vec<u64>> = Vec::new();
+    for &freq in freqs {
+        match policies.last_mut() {
+            Some(last) if last.last().is_some_and(|&prev| freq > prev) => last.push(freq),
+            _ => policies.push(vec![freq]),
+        }
+    }
+    policies
+}
+
+/// `policy0: 4 policy4: 3 policy7: 1` header of `uid_concurrent_policy_time`.
+fn parse_policy_header(line: &str) -> Vec<(i32, usize)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    tokens
+        .chunks(2)
+        .filter_map(|pair| {
+            let policy = pair.first()?.strip_prefix("policy")?.trim_end_matches(':');
+            Some((policy.parse().ok()?, pair.get(1)?.parse().ok()?))
+        })
+        .collect()
+}
+
+fn split_by(values: &[u64], sizes: &[usize]) -> Vec<Vec<u64>> {
+    let mut rest = values;
+    sizes
+        .iter()
+        .map(|&size| {
+            let (head, tail) = rest.split_at(size.min(rest.len()));
+            rest = tail;
+            head.to_vec()
+        })
+        .collect()
+}
+
+/// Cost of running at `khz`: the first state at or above it, as the kernel
+/// would pick.
+fn cost_at(states: &[(u64, f64)], khz: u64) -> f64 {
+    states
+        .iter()
+        .find(|(freq, _)| *freq >= khz)
+        .or(states.last())
+        .map(|(_, power)| *power)
+        .unwrap_or(0.0)
+}
+
+fn energy_model_states(sys: &str, policies: &[Vec<i32>]) -> Option<Vec<PowerStates>> {
+    let dir = format!("{}/kernel/debug/energy_model", sys);
+    let mut domains: Vec<(Vec<i32>, PowerStates)> = Vec::new();
+
+    for name in utils::list_dir(&dir) {
+        let path = format!("{}/{}", dir, name);
+        let cpus = match utils::read_sysfs_full(&format!("{}/cpus", path)) {
+            Some(list) => parse_cpu_list(&list),
+            None => name
+                .strip_prefix("cpu")
+                .and_then(|n| n.parse().ok())
+                .into_iter()
+                .collect(),
+        };
+
+        let mut states: PowerStates = utils::list_dir(&path)
+            .into_iter()
+            .filter(|state| state.starts_with("ps:") || state.starts_with("cs:"))
+            .filter_map(|state| {
+                let state = format!("{}/{}", path, state);
+                let freq = utils::read_sysfs_num::<u64>(&format!("{}/frequency", state))?;
+                Some((
+                    freq,
+                    utils::read_sysfs_num::<u64>(&format!("{}/power", state))? as f64,
+                ))
+            })
+            .collect();
+        states.sort_by_key(|(freq, _)| *freq);
+
+        if !cpus.is_empty() && !states.is_empty() {
+            domains.push((cpus, states));
+        }
+    }
+
+    // Kernels since 5.19 report micro-Watts, older ones milli-Watts. No CPU
+    // state draws 20 W, so anything above that is micro-Watts.
+    let scale = if domains
+        .iter()
+        .flat_map(|(_, s)| s)
+        .any(|(_, p)| *p > 20_000.0)
+    {
+        0.001
+    } else {
+        1.0
+    };
+
+    policies
+        .iter()
+        .map(|cpus| {
+            let (_, states) = domains
+                .iter()
+                .find(|(d, _)| d.iter().any(|c| cpus.contains(c)))?;
+            Some(
+                states
+                    .iter()
+                    .map(|(freq, power)| (*freq, power * scale))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// `P[mW] = C * V[mV]^2 * f[MHz] / 10^9`, as the kernel builds its own
+/// energy model from the device tree.
+fn devicetree_states(
+    dt_root: &str,
+    cpu_root: &str,
+    policies: &[Vec<i32>],
+) -> Option<Vec<PowerStates>> {
+    let nodes = opp::cpu_nodes(dt_root, cpu_root);
+    let tables = opp::read_opp_tables_at(dt_root, cpu_root);
+
+    policies
+        .iter()
+        .map(|cpus| {
+            let (_, node) = nodes.iter().find(|(cpu, _)| cpus.contains(cpu))?;
+            let coefficient = *dt::read_cells(node, "dynamic-power-coefficient").first()? as f64;
+            let table = tables
+                .iter()
+                .find(|t| t.cpus.iter().any(|c| cpus.contains(c)))?;
+
+            let states: PowerStates = table
+                .entries
+                .iter()
+                .filter_map(|entry| {
+                    let mv = *entry.microvolt.first()? as f64 / 1000.0;
+                    let mhz = entry.freq_hz as f64 / 1_000_000.0;
+                    Some((entry.freq_hz / 1000, coefficient * mv * mv * mhz / 1e9))
+                })
+                .collect();
+            (!states.is_empty()).then_some(states)
+        })
+        .collect()
+}
+
+/// Without a power model, cost scales with capacity and the cube of the
+/// relative frequency (voltage rising roughly with frequency).
+fn estimated_states(cpu_root: &str, policies: &[Vec<i32>], freqs: &[Vec<u64>]) -> Vec<PowerStates> {
+    policies
+        .iter()
+        .enumerate()
+        .map(|(index, cpus)| {
+            let capacity = cpus
+                .first()
+                .and_then(|cpu| {
+                    utils::read_sysfs_num::<u64>(&format!("{}/cpu{}/cpu_capacity", cpu_root, cpu))
+                })
+                .unwrap_or(1024) as f64;
+            let columns = freqs.get(index).map(Vec::as_slice).unwrap_or_default();
+            let max = columns.iter().max().copied().unwrap_or(1).max(1) as f64;
+
+            columns
+                .iter()
+                .map(|&freq| (freq, capacity * (freq as f64 / max).powi(3)))
+                .collect()
+        })
+        .collect()
+}
+
+fn power_model_at(
+    sys: &str,
+    dt_root: Option<&str>,
+    policies: &[Vec<i32>],
+    freqs: &[Vec<u64>],
+) -> (PowerModelSource, Vec<Vec<f64>>) {
+    let cpu_root = format!("{}/devices/system/cpu", sys);
+
+    let (source, states) = if let Some(states) = energy_model_states(sys, policies) {
+        (PowerModelSource::EnergyModel, states)
+    } else if let Some(states) =
+        dt_root.and_then(|root| devicetree_states(root, &cpu_root, policies))
+    {
+        (PowerModelSource::DeviceTree, states)
+    } else {
+        (
+            PowerModelSource::Estimated,
+            estimated_states(&cpu_root, policies, freqs),
+        )
+    };
+
+    let costs = freqs
+        .iter()
+        .enumerate()
+        .map(|(index, columns)| {
+            let policy_states = states.get(index).map(Vec::as_slice).unwrap_or_default();
+            columns
+                .iter()
+                .map(|&freq| cost_at(policy_states, freq))
+                .collect()
+        })
+        .collect();
+
+    (source, costs)
+}
+
+/// The four counters, plus the header rows that lay out their columns.
+fn read_raw(proc_root: &str, taken: Instant) -> (RawSample, Option<String>, Option<String>) {
+    let read =
+        |name: &str| std::fs::read_to_string(format!("{}/{}", proc_root, name)).unwrap_or_default();
+    let header = |text: &str, prefix: &str| {
+        text.lines()
+            .find(|l| l.starts_with(prefix))
+            .map(str::to_string)
+    };
+
+    let time_in_state = read("uid_time_in_state");
+    let concurrent_policy = read("uid_concurrent_policy_time");
+
+    let mut cputime = parse_uid_rows(&read("uid_cputime/show_uid_stat"));
+    // Some kernels append a power estimate after user and system time.
+    for values in cputime.values_mut() {
+        values.truncate(2);
+    }
+
+    let sample = RawSample {
+        taken,
+        cputime,
+        time_in_state: parse_uid_rows(&time_in_state),
+        concurrent_active: parse_uid_rows(&read("uid_concurrent_active_time")),
+        concurrent_policy: parse_uid_rows(&concurrent_policy),
+    };
+    (
+        sample,
+        header(&time_in_state, "uid:"),
+        header(&concurrent_policy, "policy"),
+    )
+}
+
+fn delta(current: &UidRows, baseline: Option<&UidRows>, uid: u32) -> Vec<u64> {
+    let now = current.get(&uid).cloned().unwrap_or_default();
+    match baseline.and_then(|rows| rows.get(&uid)) {
+        Some(before) if before.len() == now.len() => now
+            .iter()
+            .zip(before)
+            .map(|(n, b)| n.saturating_sub(*b))
+            .collect(),
+        _ => now,
+    }
+}
+
+fn sample_uid_cpu_at(
+    proc_root: &str,
+    sys: &str,
+    dt_root: Option<&str>,
+    history: &mut UidHistory,
+    now: Instant,
+    window: Duration,
+) -> UidCpuReport {
+    let cpu_root = format!("{}/devices/system/cpu", sys);
+    let (current, freq_header, policy_header) = read_raw(proc_root, now);
+
+    let topology = cluster_topology(&cpu_root);
+    let header: Vec<u64> = freq_header
+        .map(|line| {
+            line[4..]
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let counts: Vec<usize> = topology
+        .iter()
+        .filter_map(|cpus| {
+            let path = format!(
+                "{}/cpufreq/policy{}/scaling_available_frequencies",
+                cpu_root,
+                cpus.first()?
+            );
+            Some(utils::read_sysfs_full(&path)?.split_whitespace().count())
+        })
+        .collect();
+    let counts = if counts.len() == topology.len() {
+        counts
+    } else {
+        Vec::new()
+    };
+    let freqs = split_freq_header(&header, &counts);
+
+    let policy_sizes: Vec<usize> = policy_header
+        .as_deref()
+        .map(parse_policy_header)
+        .filter(|header| !header.is_empty())
+        .map(|header| header.iter().map(|(_, cpus)| *cpus).collect())
+        .unwrap_or_else(|| topology.iter().map(Vec::len).collect());
+
+    let (source, costs) = history
+        .model
+        .get_or_insert_with(|| power_model_at(sys, dt_root, &topology, &freqs))
+        .clone();
+
+    // The newest sample at least a window old, or the oldest one there is.
+    let baseline_index = history
+        .samples
+        .iter()
+        .rposition(|s| now.saturating_duration_since(s.taken) >= window)
+        .or(if history.samples.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    let baseline = baseline_index.map(|index| &history.samples[index]);
+
+    let tick = tick_ms();
+    let to_ms = |ticks: u64| (ticks as f64 * tick).round() as u64;
+
+    let mut uids: Vec<u32> = current
+        .cputime
+        .keys()
+        .chain(current.time_in_state.keys())
+        .chain(current.concurrent_active.keys())
+        .chain(current.concurrent_policy.keys())
+        .copied()
+        .collect();
+    uids.sort_unstable();
+    uids.dedup();
+
+    let mut usage: Vec<UidCpuUsage> = uids
+        .into_iter()
+        .filter_map(|uid| {
+            let cpu = delta(&current.cputime, baseline.map(|b| &b.cputime), uid);
+            let in_state = delta(
+                &current.time_in_state,
+                baseline.map(|b| &b.time_in_state),
+                uid,
+            );
+            let active = delta(
+                &current.concurrent_active,
+                baseline.map(|b| &b.concurrent_active),
+                uid,
+            );
+            let policy = delta(
+                &current.concurrent_policy,
+                baseline.map(|b| &b.concurrent_policy),
+                uid,
+            );
+
+            if [&cpu, &in_state, &active, &policy]
+                .iter()
+                .all(|v| v.iter().all(|t| *t == 0))
+            {
+                return None;
+            }
+
+            let sizes: Vec<usize> = freqs.iter().map(Vec::len).collect();
+            let time_in_state_ms: Vec<Vec<u64>> = split_by(&in_state, &sizes)
+                .into_iter()
+                .map(|ticks| ticks.into_iter().map(to_ms).collect())
+                .collect();
+
+            let energy_mj = time_in_state_ms
+                .iter()
+                .zip(&costs)
+                .flat_map(|(times, powers)| times.iter().zip(powers))
+                .map(|(ms, mw)| *ms as f64 * mw / 1000.0)
+                .sum();
+
+            Some(UidCpuUsage {
+                uid,
+                user_ms: cpu.first().copied().unwrap_or(0) / 1000,
+                system_ms: cpu.get(1).copied().unwrap_or(0) / 1000,
+                time_in_state_ms,
+                concurrent_active_ms: active.into_iter().map(to_ms).collect(),
+                concurrent_policy_ms: split_by(&policy, &policy_sizes)
+                    .into_iter()
+                    .map(|ticks| ticks.into_iter().map(to_ms).collect())
+                    .collect(),
+                energy_mj,
+                energy_share: 0.0,
+            })
+        })
+        .collect();
+
+    let use_energy = usage.iter().any(|u| u.energy_mj > 0.0);
+    let weight = |u: &UidCpuUsage| {
+        if use_energy {
+            u.energy_mj
+        } else {
+            (u.user_ms + u.system_ms) as f64
+        }
+    };
+    let total: f64 = usage.iter().map(weight).sum();
+    for entry in usage.iter_mut() {
+        entry.energy_share = if total > 0.0 {
+            (weight(entry) / total) as f32
+        } else {
+            0.0
+        };
+    }
+    usage.sort_by(|a, b| {
+        b.energy_share
+            .total_cmp(&a.energy_share)
+            .then(a.uid.cmp(&b.uid))
+    });
+
+    let report = UidCpuReport {
+        since_boot: baseline.is_none(),
+        interval_ms: baseline
+            .map(|b| now.saturating_duration_since(b.taken).as_millis() as u64)
+            .unwrap_or(0),
+        sources: UidSources {
+            cputime: utils::file_exists(&format!("{}/uid_cputime/show_uid_stat", proc_root)),
+            time_in_state: utils::file_exists(&format!("{}/uid_time_in_state", proc_root)),
+            concurrent_active: utils::file_exists(&format!(
+                "{}/uid_concurrent_active_time",
+                proc_root
+            )),
+            concurrent_policy: utils::file_exists(&format!(
+                "{}/uid_concurrent_policy_time",
+                proc_root
+            )),
+        },
+        power_model: source,
+        policies: topology
+            .iter()
+            .enumerate()
+            .map(|(index, cpus)| PolicyPower {
+                policy: cpus.first().copied().unwrap_or(0),
+                cpus: cpus.clone(),
+                freqs_khz: freqs.get(index).cloned().unwrap_or_default(),
+                power_mw: costs.get(index).cloned().unwrap_or_default(),
+            })
+            .collect(),
+        uids: usage,
+    };
+
+    // Keep the baseline and everything newer, recording the current sample
+    // only if it is far enough from the last one.
+    if let Some(index) = baseline_index {
+        history.samples.drain(..index);
+    }
+    let spacing = window / HISTORY_SLOTS;
+    if history
+        .samples
+        .last()
+        .is_none_or(|last| now.saturating_duration_since(last.taken) >= spacing)
+    {
+        history.samples.push(current);
+    }
+
+    report
+}
+
+/// Per-UID CPU time and energy over the last `window`, from the kernel's
+/// `/proc/uid_*` accounting. The first call has nothing to diff against and
+/// reports totals since boot.
+pub fn sample_uid_cpu(window: Duration) -> UidCpuReport {
+    let mut history = UID_HISTORY.lock().unwrap();
+    sample_uid_cpu_at(
+        PROC_ROOT,
+        SYS_ROOT,
+        dt::dt_root(),
+        &mut history,
+        Instant::now(),
+        window,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_split_freq_header() {
+        let freqs = [300, 600, 900, 500, 1000, 1500];
+        assert_eq!(
+            split_freq_header(&freqs, &[3, 3]),
+            vec![vec![300, 600, 900], vec![500, 1000, 1500]]
+        );
+        // Table sizes that do not add up fall back to the frequency drops.
+        assert_eq!(
+            split_freq_header(&freqs, &[2, 2]),
+            vec![vec![300, 600, 900], vec![500, 1000, 1500]]
+        );
+        assert_eq!(
+            parse_policy_header("policy0: 4 policy4: 3 policy7: 1"),
+            vec![(0, 4), (4, 3), (7, 1)]
+        );
+    }
+
+    fn build(cputime: &str, in_state: &str, active: &str, policy: &str) -> String {
+        fixture::build(
+            "uid_cpu",
+            &[
+                ("proc/uid_cputime/show_uid_stat", cputime),
+                ("proc/uid_time_in_state", in_state),
+                ("proc/uid_concurrent_active_time", active),
+                ("proc/uid_concurrent_policy_time", policy),
+                ("sys/devices/system/cpu/cpufreq/policy0/related_cpus", "0 1"),
+                (
+                    "sys/devices/system/cpu/cpufreq/policy0/scaling_available_frequencies",
+                    "300000 600000",
+                ),
+                ("sys/devices/system/cpu/cpufreq/policy2/related_cpus", "2"),
+                (
+                    "sys/devices/system/cpu/cpufreq/policy2/scaling_available_frequencies",
+                    "1000000 2000000",
+                ),
+                ("sys/kernel/debug/energy_model/cpu0/cpus", "0-1"),
+                (
+                    "sys/kernel/debug/energy_model/cpu0/ps:300000/frequency",
+                    "300000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu0/ps:300000/power",
+                    "30000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu0/ps:600000/frequency",
+                    "600000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu0/ps:600000/power",
+                    "80000",
+                ),
+                ("sys/kernel/debug/energy_model/cpu2/cpus", "2"),
+                (
+                    "sys/kernel/debug/energy_model/cpu2/ps:1000000/frequency",
+                    "1000000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu2/ps:1000000/power",
+                    "200000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu2/ps:2000000/frequency",
+                    "2000000",
+                ),
+                (
+                    "sys/kernel/debug/energy_model/cpu2/ps:2000000/power",
+                    "900000",
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_sample_uid_cpu() {
+        let header = "uid: 300000 600000 1000000 2000000\n";
+        let root = build(
+            "0: 1000 1000\n10100: 1000000 500000\n",
+            &format!("{}0: 10 0 0 0\n10100: 100 50 0 20\n", header),
+            "cpus: 3\n0: 1 0 0\n10100: 5 5 0\n",
+            "policy0: 2 policy2: 1\n0: 1 0 0\n10100: 5 0 3\n",
+        );
+        let proc_root = format!("{}/proc", root);
+        let sys = format!("{}/sys", root);
+        let mut history = UidHistory::default();
+        let start = Instant::now();
+
+        let first = sample_uid_cpu_at(&proc_root, &sys, None, &mut history, start, Duration::ZERO);
+        assert!(first.since_boot);
+        assert_eq!(first.power_model, PowerModelSource::EnergyModel);
+        assert_eq!(first.policies[0].power_mw, vec![30.0, 80.0]);
+        assert_eq!(first.policies[1].freqs_khz, vec![1_000_000, 2_000_000]);
+        assert_eq!(first.uids.len(), 2);
+
+        build(
+            "0: 1000 1000\n10100: 3000000 500000\n",
+            &format!("{}0: 10 0 0 0\n10100: 200 50 0 120\n", header),
+            "cpus: 3\n0: 1 0 0\n10100: 10 5 0\n",
+            "policy0: 2 policy2: 1\n0: 1 0 0\n10100: 8 0 3\n",
+        );
+        let second = sample_uid_cpu_at(
+            &proc_root,
+            &sys,
+            None,
+            &mut history,
+            start + Duration::from_secs(1),
+            Duration::ZERO,
+        );
+        assert!(!second.since_boot);
+        assert_eq!(second.interval_ms, 1000);
+        // uid 0 did not run in between.
+        assert_eq!(second.uids.len(), 1);
+
+        let tick = tick_ms();
+        let ms = |ticks: u64| (ticks as f64 * tick).round() as u64;
+        let app = &second.uids[0];
+        assert_eq!(app.uid, 10100);
+        assert_eq!((app.user_ms, app.system_ms), (2000, 0));
+        assert_eq!(
+            app.time_in_state_ms,
+            vec![vec![ms(100), 0], vec![0, ms(100)]]
+        );
+        assert_eq!(app.concurrent_active_ms, vec![ms(5), 0, 0]);
+        assert_eq!(app.concurrent_policy_ms, vec![vec![ms(3), 0], vec![0]]);
+        let expected = ms(100) as f64 * (30.0 + 900.0) / 1000.0;
+        assert!((app.energy_mj - expected).abs() < 1e-6);
+        assert_eq!(app.energy_share, 1.0);
+    }
+}
@@ -0,0 +1,396 @@
+use crate::utils;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub ppid: i32,
+    pub name: String,
+    pub cmdline: String,
+    pub state: String,
+    pub uid: u32,
+    /// Share of total CPU capacity (all cores) over the sample interval.
+    pub cpu_percent: f32,
+    pub last_cpu: i32,
+    pub priority: i64,
+    pub nice: i64,
+    pub threads: i64,
+    pub rss_kb: u64,
+    pub oom_score_adj: i32,
+    pub cgroup: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadInfo {
+    pub tid: i32,
+    pub name: String,
+    pub state: String,
+    pub cpu_percent: f32,
+    pub last_cpu: i32,
+    pub priority: i64,
+    pub nice: i64,
+}
+
+struct StatFields {
+    pid: i32,
+    comm: String,
+    state: String,
+    ppid: i32,
+    ticks: u64,
+    priority: i64,
+    nice: i64,
+    threads: i64,
+    rss_pages: u64,
+    processor: i32,
+}
+
+/// Jiffies per task from the previous call, and the system total then.
+#[derive(Default)]
+struct Sample {
+    total: u64,
+    ticks: HashMap<i32, u64>,
+}
+
+static PROCESS_SAMPLE: Lazy<Mutex<Option<Sample>>> = Lazy::new(|| Mutex::new(None));
+static THREAD_SAMPLE: Lazy<Mutex<Option<(i32, Sample)>>> = Lazy::new(|| Mutex::new(None));
+
+/// `comm` may contain spaces and parentheses, so fields are counted from the
+/// last `)`.
+fn parse_stat(content: &str) -> Option<StatFields> {
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let rest: Vec<&str> = content.get(close + 1..)?.split_whitespace().collect();
+    // rest[0] is field 3 of proc(5).
+    let field = |n: usize| rest.get(n - 3).copied();
+    let num = |n: usize| field(n).and_then(|v| v.parse::<i64>().ok()).unwrap_or(0);
+
+    Some(StatFields {
+        pid: content[..open].trim().parse().ok()?,
+        comm: content[open + 1..close].to_string(),
+        state: field(3)?.to_string(),
+        ppid: num(4) as i32,
+        ticks: (num(14) + num(15)) as u64,
+        priority: num(18),
+        nice: num(19),
+        threads: num(20),
+        rss_pages: num(24) as u64,
+        processor: num(39) as i32,
+    })
+}
+
+fn total_jiffies(root: &str) -> u64 {
+    std::fs::read_to_string(format!("{}/stat", root))
+        .ok()
+        .and_then(|stat| {
+            let line = stat.lines().next()?;
+            Some(
+                line.split_whitespace()
+                    .skip(1)
+                    .take(8)
+                    .filter_map(|v| v.parse::<u64>().ok())
+                    .sum(),
+            )
+        })
+        .unwrap_or(0)
+}
+
+fn read_stats(dir: &str) -> Vec<StatFields> {
+    utils::list_dir(dir)
+        .into_iter()
+        .filter(|name| name.bytes().all(|b| b.is_ascii_digit()))
+        .filter_map(|name| {
+            let content = std::fs::read_to_string(format!("{}/{}/stat", dir, name)).ok()?;
+            parse_stat(&content)
+        })
+        .collect()
+}
+
+fn percent(prev: Option<&Sample>, pid: i32, ticks: u64, total: u64) -> f32 {
+    let Some(prev) = prev else { return 0.0 };
+    let Some(&before) = prev.ticks.get(&pid) else {
+        return 0.0;
+    };
+    let elapsed = total.saturating_sub(prev.total);
+    if elapsed == 0 {
+        return 0.0;
+    }
+    ticks.saturating_sub(before) as f32 / elapsed as f32 * 100.0
+}
+
+/// The cpuset path from `/proc/<pid>/cgroup` (`top-app`, `background`...),
+/// falling back to the unified hierarchy.
+fn parse_cgroup(content: &str) -> String {
+    let path_of = |controller: &str| {
+        content.lines().find_map(|line| {
+            let mut parts = line.splitn(3, ':');
+            let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+            (controllers.split(',').any(|c| c == controller))
+                .then(|| path.trim_start_matches('/').to_string())
+        })
+    };
+
+    let group = path_of("cpuset")
+        .or_else(|| path_of(""))
+        .unwrap_or_default();
+    if group.is_empty() {
+        "root".to_string()
+    } else {
+        group
+    }
+}
+
+fn status_field<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(|v| v.trim())
+}
+
+fn read_cmdline(dir: &str) -> String {
+    std::fs::read(format!("{}/cmdline", dir))
+        .map(|bytes| {
+            bytes
+                .split(|&b| b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+/// argv[0] without its directory, which is the package name for apps;
+/// kernel threads have no cmdline.
+fn display_name(cmdline: &str, comm: &str) -> String {
+    cmdline
+        .split(' ')
+        .next()
+        .and_then(|arg0| arg0.rsplit('/').next())
+        .filter(|n| !n.is_empty())
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| comm.to_string())
+}
+
+/// Name and UID of `pid`, for joining other per-PID counters with the
+/// process list. `None` once the process has exited.
+pub(crate) fn process_name_at(root: &str, pid: i32) -> Option<(String, u32)> {
+    let dir = format!("{}/{}", root, pid);
+    let status = std::fs::read_to_string(format!("{}/status", dir)).ok()?;
+    let comm = status_field(&status, "Name").unwrap_or_default();
+    let uid = status_field(&status, "Uid")
+        .and_then(|v| v.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0);
+    Some((display_name(&read_cmdline(&dir), comm), uid))
+}
+
+fn process_details(root: &str, stat: StatFields, cpu_percent: f32) -> ProcessInfo {
+    let dir = format!("{}/{}", root, stat.pid);
+    let status = std::fs::read_to_string(format!("{}/status", dir)).unwrap_or_default();
+
+    let cmdline = read_cmdline(&dir);
+
+    let rss_kb = status_field(&status, "VmRSS")
+        .and_then(|v| v.split_whitespace().next()?.parse().ok())
+        .unwrap_or(stat.rss_pages * utils::page_size() / 1024);
+
+    let name = display_name(&cmdline, &stat.comm);
+
+    ProcessInfo {
+        pid: stat.pid,
+        ppid: stat.ppid,
+        name,
+        cmdline,
+        state: stat.state,
+        uid: status_field(&status, "Uid")
+            .and_then(|v| v.split_whitespace().next()?.parse().ok())
+            .unwrap_or(0),
+        cpu_percent,
+        last_cpu: stat.processor,
+        priority: stat.priority,
+        nice: stat.nice,
+        threads: stat.threads,
+        rss_kb,
+        oom_score_adj: utils::read_sysfs_full(&format!("{}/oom_score_adj", dir))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        cgroup: std::fs::read_to_string(format!("{}/cgroup", dir))
+            .map(|c| parse_cgroup(&c))
+            .unwrap_or_default(),
+    }
+}
+
+/// Only `stat` is read for every process; `status`, `cmdline` and the rest
+/// are read for the `limit` processes that are returned.
+fn sample_processes_at(root: &str, prev: &mut Option<Sample>, limit: usize) -> Vec<ProcessInfo> {
+    let total = total_jiffies(root);
+    let stats = read_stats(root);
+
+    let mut ranked: Vec<(f32, StatFields)> = stats
+        .into_iter()
+        .map(|stat| (percent(prev.as_ref(), stat.pid, stat.ticks, total), stat))
+        .collect();
+
+    *prev = Some(Sample {
+        total,
+        ticks: ranked.iter().map(|(_, s)| (s.pid, s.ticks)).collect(),
+    });
+
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.rss_pages.cmp(&a.1.rss_pages)));
+
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(pct, stat)| process_details(root, stat, pct))
+        .collect()
+}
+
+fn sample_threads_at(
+    root: &str,
+    prev: &mut Option<(i32, Sample)>,
+    pid: i32,
+    limit: usize,
+) -> Vec<ThreadInfo> {
+    let total = total_jiffies(root);
+    let stats = read_stats(&format!("{}/{}/task", root, pid));
+
+    // A sample taken for another process does not apply.
+    let previous = prev.as_ref().filter(|(p, _)| *p == pid).map(|(_, s)| s);
+    let mut threads: Vec<ThreadInfo> = stats
+        .iter()
+        .map(|stat| ThreadInfo {
+            tid: stat.pid,
+            name: stat.comm.clone(),
+            state: stat.state.clone(),
+            cpu_percent: percent(previous, stat.pid, stat.ticks, total),
+            last_cpu: stat.processor,
+            priority: stat.priority,
+            nice: stat.nice,
+        })
+        .collect();
+
+    *prev = Some((
+        pid,
+        Sample {
+            total,
+            ticks: stats.iter().map(|s| (s.pid, s.ticks)).collect(),
+        },
+    ));
+
+    threads.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+    threads.truncate(limit);
+    threads
+}
+
+/// Top `limit` processes by CPU% since the previous call. The first call
+/// only primes the sample and reports 0%.
+pub fn sample_processes(limit: usize) -> Vec<ProcessInfo> {
+    let mut prev = PROCESS_SAMPLE.lock().unwrap();
+    sample_processes_at(PROC_ROOT, &mut prev, limit)
+}
+
+/// Top `limit` threads of `pid` by CPU% since the previous call for the
+/// same process.
+pub fn sample_threads(pid: i32, limit: usize) -> Vec<ThreadInfo> {
+    let mut prev = THREAD_SAMPLE.lock().unwrap();
+    sample_threads_at(PROC_ROOT, &mut prev, pid, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn stat(pid: i32, comm: &str, utime: u64, stime: u64, cpu: i32) -> String {
+        let mut fields = vec!["0".to_string(); 37];
+        fields[0] = "S".into();
+        fields[1] = "1".into();
+        fields[11] = utime.to_string();
+        fields[12] = stime.to_string();
+        fields[15] = "20".into();
+        fields[16] = "0".into();
+        fields[17] = "12".into();
+        fields[21] = "1000".into();
+        fields[36] = cpu.to_string();
+        format!("{} ({}) {}\n", pid, comm, fields.join(" "))
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        let parsed = parse_stat(&stat(42, "Binder:1 (x)", 100, 50, 6)).unwrap();
+        assert_eq!(parsed.pid, 42);
+        assert_eq!(parsed.comm, "Binder:1 (x)");
+        assert_eq!(parsed.ticks, 150);
+        assert_eq!(parsed.threads, 12);
+        assert_eq!(parsed.processor, 6);
+    }
+
+    #[test]
+    fn test_parse_cgroup() {
+        assert_eq!(
+            parse_cgroup("3:cpuset:/top-app\n2:cpu:/\n0::/uid_10123/pid_99\n"),
+            "top-app"
+        );
+        assert_eq!(parse_cgroup("0::/uid_1000/pid_5\n"), "uid_1000/pid_5");
+        assert_eq!(parse_cgroup("3:cpuset:/\n"), "root");
+    }
+
+    #[test]
+    fn test_sample_processes() {
+        let before = [
+            ("stat", "cpu  1000 0 0 1000 0 0 0 0 0 0\n".to_string()),
+            ("100/stat", stat(100, "surfaceflinger", 10, 10, 0)),
+            ("200/stat", stat(200, "app", 100, 0, 4)),
+            (
+                "200/status",
+                "Name:\tapp\nUid:\t10123\t10123\t10123\t10123\nVmRSS:\t  204800 kB\n".to_string(),
+            ),
+            ("200/cmdline", "com.example.app\0--flag\0".to_string()),
+            ("200/oom_score_adj", "0\n".to_string()),
+            ("200/cgroup", "3:cpuset:/top-app\n".to_string()),
+            ("200/task/200/stat", stat(200, "app", 60, 0, 4)),
+            ("200/task/201/stat", stat(201, "RenderThread", 40, 0, 7)),
+        ];
+        let files: Vec<(&str, &str)> = before.iter().map(|(p, v)| (*p, v.as_str())).collect();
+        let root = fixture::build("process_sample", &files);
+
+        let mut prev = None;
+        let primed = sample_processes_at(&root, &mut prev, 10);
+        assert!(primed.iter().all(|p| p.cpu_percent == 0.0));
+
+        std::fs::write(format!("{}/stat", root), "cpu  1800 0 0 1200 0 0 0 0 0 0\n").unwrap();
+        std::fs::write(
+            format!("{}/100/stat", root),
+            stat(100, "surfaceflinger", 60, 20, 1),
+        )
+        .unwrap();
+        std::fs::write(format!("{}/200/stat", root), stat(200, "app", 400, 100, 5)).unwrap();
+
+        let top = sample_processes_at(&root, &mut prev, 1);
+        assert_eq!(top.len(), 1);
+        let app = &top[0];
+        assert_eq!(app.pid, 200);
+        assert_eq!(app.cpu_percent, 40.0);
+        assert_eq!(app.name, "com.example.app");
+        assert_eq!(app.uid, 10123);
+        assert_eq!(app.rss_kb, 204800);
+        assert_eq!(app.cgroup, "top-app");
+        assert_eq!(app.last_cpu, 5);
+
+        let mut thread_prev = None;
+        sample_threads_at(&root, &mut thread_prev, 200, 10);
+        std::fs::write(format!("{}/stat", root), "cpu  2800 0 0 1200 0 0 0 0 0 0\n").unwrap();
+        std::fs::write(
+            format!("{}/200/task/201/stat", root),
+            stat(201, "RenderThread", 240, 0, 7),
+        )
+        .unwrap();
+        let threads = sample_threads_at(&root, &mut thread_prev, 200, 10);
+        assert_eq!(threads[0].name, "RenderThread");
+        assert_eq!(threads[0].cpu_percent, 20.0);
+    }
+}
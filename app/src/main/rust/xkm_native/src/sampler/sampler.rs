@@ -0,0 +1,90 @@
+use crate::cpu::{self, CoreIdleResidency, CpuLoadInfo, CpuStats, IdleSample};
+use crate::disk::{self, DiskState, DiskStats};
+use crate::gpu::{self, GpuBusyStats};
+use std::sync::Mutex;
+
+/// One consumer's previous readings. The dashboard, the overlay and the
+/// quick-settings tile each own a sampler, so their polling intervals do not
+/// eat into each other's deltas.
+#[derive(Default)]
+pub struct Sampler {
+    cpu: CpuStats,
+    gpu: Option<GpuBusyStats>,
+    disk: DiskState,
+    cpuidle: Option<IdleSample>,
+}
+
+impl Sampler {
+    pub fn cpu_load(&mut self) -> CpuLoadInfo {
+        cpu::read_cpu_load_with(&mut self.cpu)
+    }
+
+    pub fn gpu_busy(&mut self) -> i32 {
+        gpu::read_gpu_busy_with(&mut self.gpu)
+    }
+
+    pub fn disk_stats(&mut self) -> DiskStats {
+        disk::read_disk_stats_with(&mut self.disk)
+    }
+
+    pub fn cpuidle_residency(&mut self) -> Vec<CoreIdleResidency> {
+        cpu::read_cpuidle_residency_with(&mut self.cpuidle)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Sampler::default();
+    }
+}
+
+/// Handles are leaked `Box<Mutex<Sampler>>` pointers; a consumer may poll
+/// from more than one thread.
+pub fn create_sampler_handle() -> i64 {
+    Box::into_raw(Box::new(Mutex::new(Sampler::default()))) as i64
+}
+
+/// # Safety
+///
+/// `handle` must be 0 or come from [`create_sampler_handle`] and not have
+/// been released.
+pub unsafe fn with_sampler<T>(handle: i64, f: impl FnOnce(&mut Sampler) -> T) -> Option<T> {
+    if handle == 0 {
+        return None;
+    }
+    let sampler = unsafe { &*(handle as *const Mutex<Sampler>) };
+    let mut sampler = sampler.lock().unwrap_or_else(|e| e.into_inner());
+    Some(f(&mut sampler))
+}
+
+/// # Safety
+///
+/// `handle` must be 0 or come from [`create_sampler_handle`], and is invalid
+/// afterwards.
+pub unsafe fn release_sampler_handle(handle: i64) {
+    if handle != 0 {
+        drop(unsafe { Box::from_raw(handle as *mut Mutex<Sampler>) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_are_independent() {
+        let first = create_sampler_handle();
+        let second = create_sampler_handle();
+        assert_ne!(first, second);
+
+        let a = unsafe { with_sampler(first, |s| s.cpu_load()) }.unwrap();
+        let b = unsafe { with_sampler(second, |s| s.cpu_load()) }.unwrap();
+        assert_eq!(a.per_core_load.len(), b.per_core_load.len());
+        assert!((0.0..=100.0).contains(&a.total_load));
+
+        unsafe {
+            with_sampler(first, |s| s.reset());
+            release_sampler_handle(first);
+            release_sampler_handle(second);
+            assert!(with_sampler(0, |s| s.cpu_load()).is_none());
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::cpu;
+use crate::utils::{self, WriteResult};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const DEV_ROOT: &str = "/dev";
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+pub const CPUSET_GROUPS: [&str; 5] = [
+    "top-app",
+    "foreground",
+    "background",
+    "system-background",
+    "restricted",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpusetGroup {
+    pub group: String,
+    pub cpus: String,
+    pub cpu_list: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuctlGroup {
+    pub group: String,
+    pub uclamp_min: Option<String>,
+    pub uclamp_max: Option<String>,
+    pub uclamp_latency_sensitive: Option<bool>,
+    pub shares: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedtuneGroup {
+    pub group: String,
+    pub boost: Option<i32>,
+    pub prefer_idle: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupState {
+    pub cpuset: Vec<CpusetGroup>,
+    pub cpuctl: Vec<CpuctlGroup>,
+    pub schedtune: Vec<SchedtuneGroup>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedProfileReport {
+    pub mode: String,
+    pub results: Vec<WriteResult>,
+    /// Values rejected before reaching the kernel, as
+    /// `controller/group/param: reason`.
+    pub errors: Vec<String>,
+    /// `balance` wrote back the values from before the first profile.
+    pub restored: bool,
+}
+
+/// (path, raw value) of every node a profile touches, read before the first
+/// profile so `balance` can hand the groups back to the vendor's settings.
+type ProfileOriginals = Vec<(String, String)>;
+
+static PROFILE_ORIGINALS: Lazy<Mutex<Option<ProfileOriginals>>> = Lazy::new(|| Mutex::new(None));
+
+/// Android mounts cpuset with `noprefix`; stock Linux keeps `cpuset.cpus`.
+fn cpuset_file(dev: &str, group: &str) -> Option<String> {
+    [
+        format!("{}/cpuset/{}/cpus", dev, group),
+        format!("{}/cpuset/{}/cpuset.cpus", dev, group),
+    ]
+    .into_iter()
+    .find(|p| utils::file_exists(p))
+}
+
+/// Child groups of a controller, with the root group reported as `""`.
+fn groups(dev: &str, controller: &str, probe: &str) -> Vec<String> {
+    let base = format!("{}/{}", dev, controller);
+    let mut groups: Vec<String> = utils::list_dir(&base)
+        .into_iter()
+        .filter(|name| utils::file_exists(&format!("{}/{}/{}", base, name, probe)))
+        .collect();
+
+    if utils::file_exists(&format!("{}/{}", base, probe)) {
+        groups.insert(0, String::new());
+    }
+    groups
+}
+
+fn group_path(dev: &str, controller: &str, group: &str, param: &str) -> String {
+    if group.is_empty() {
+        format!("{}/{}/{}", dev, controller, param)
+    } else {
+        format!("{}/{}/{}/{}", dev, controller, group, param)
+    }
+}
+
+fn read(path: &str) -> Option<String> {
+    utils::read_sysfs_full(path)
+}
+
+fn read_cgroups_at(dev: &str) -> CgroupState {
+    let cpuset = CPUSET_GROUPS
+        .iter()
+        .filter_map(|group| {
+            let cpus = read(&cpuset_file(dev, group)?)?;
+            Some(CpusetGroup {
+                group: group.to_string(),
+                cpu_list: cpu::parse_cpu_list(&cpus),
+                cpus,
+            })
+        })
+        .collect();
+
+    let cpuctl = groups(dev, "cpuctl", "cpu.shares")
+        .into_iter()
+        .map(|group| {
+            let param = |name: &str| read(&group_path(dev, "cpuctl", &group, name));
+            CpuctlGroup {
+                uclamp_min: param("cpu.uclamp.min"),
+                uclamp_max: param("cpu.uclamp.max"),
+                uclamp_latency_sensitive: param("cpu.uclamp.latency_sensitive").map(|v| v == "1"),
+                shares: param("cpu.shares").and_then(|v| v.parse().ok()),
+                group,
+            }
+        })
+        .collect();
+
+    let schedtune = groups(dev, "stune", "schedtune.boost")
+        .into_iter()
+        .map(|group| {
+            let param = |name: &str| read(&group_path(dev, "stune", &group, name));
+            SchedtuneGroup {
+                boost: param("schedtune.boost").and_then(|v| v.parse().ok()),
+                prefer_idle: param("schedtune.prefer_idle").map(|v| v == "1"),
+                group,
+            }
+        })
+        .collect();
+
+    CgroupState {
+        cpuset,
+        cpuctl,
+        schedtune,
+    }
+}
+
+/// Rejects CPUs that are not part of the topology or are offline, and
+/// returns the mask in the kernel's own list format.
+fn validate_cpus(cpu_root: &str, value: &str) -> Result<String, String> {
+    let cpus = cpu::parse_cpu_list(value);
+    if cpus.is_empty() {
+        return Err(format!("invalid cpu list '{}'", value));
+    }
+
+    let known: Vec<i32> = cpu::cluster_topology(cpu_root).concat();
+    for &c in &cpus {
+        if !known.contains(&c) {
+            return Err(format!("cpu{} is not present", c));
+        }
+        let online = utils::read_sysfs_full(&format!("{}/cpu{}/online", cpu_root, c));
+        if online.as_deref() == Some("0") {
+            return Err(format!("cpu{} is offline", c));
+        }
+    }
+
+    Ok(cpu::format_cpu_list(&cpus))
+}
+
+fn validate_uclamp(value: &str) -> Result<String, String> {
+    if value == "max" {
+        return Ok(value.to_string());
+    }
+    let pct: f32 = value
+        .parse()
+        .map_err(|_| format!("invalid uclamp value '{}'", value))?;
+    if !(0.0..=100.0).contains(&pct) {
+        return Err("uclamp must be within 0-100".into());
+    }
+    // The kernel prints 100% back as "max".
+    Ok(if pct >= 100.0 {
+        "max".to_string()
+    } else {
+        format!("{:.2}", pct)
+    })
+}
+
+fn validate_int(value: &str, min: i64, max: i64) -> Result<String, String> {
+    let v: i64 = value
+        .parse()
+        .map_err(|_| format!("invalid integer '{}'", value))?;
+    if !(min..=max).contains(&v) {
+        return Err(format!("value must be within {}-{}", min, max));
+    }
+    Ok(v.to_string())
+}
+
+fn write_cgroup_at(
+    dev: &str,
+    cpu_root: &str,
+    controller: &str,
+    group: &str,
+    param: &str,
+    value: &str,
+) -> Result<WriteResult, String> {
+    let value = value.trim();
+
+    let (path, value) = match (controller, param) {
+        ("cpuset", "cpus") => {
+            let path =
+                cpuset_file(dev, group).ok_or_else(|| format!("no cpuset group '{}'", group))?;
+            (path, validate_cpus(cpu_root, value)?)
+        }
+        ("cpuctl", "cpu.uclamp.min" | "cpu.uclamp.max") => {
+            let value = validate_uclamp(value)?;
+            let other = if param.ends_with("min") { "max" } else { "min" };
+            let other = read(&group_path(
+                dev,
+                controller,
+                group,
+                &format!("cpu.uclamp.{}", other),
+            ));
+            let as_pct = |v: &str| {
+                if v == "max" {
+                    100.0
+                } else {
+                    v.parse::<f32>().unwrap_or(0.0)
+                }
+            };
+            if let Some(other) = other {
+                let (min, max) = if param.ends_with("min") {
+                    (as_pct(&value), as_pct(&other))
+                } else {
+                    (as_pct(&other), as_pct(&value))
+                };
+                if min > max {
+                    return Err("uclamp.min must not exceed uclamp.max".into());
+                }
+            }
+            (group_path(dev, controller, group, param), value)
+        }
+        ("cpuctl", "cpu.uclamp.latency_sensitive") | ("stune", "schedtune.prefer_idle") => (
+            group_path(dev, controller, group, param),
+            validate_int(value, 0, 1)?,
+        ),
+        ("cpuctl", "cpu.shares") => (
+            group_path(dev, controller, group, param),
+            validate_int(value, 2, 262_144)?,
+        ),
+        ("stune", "schedtune.boost") => (
+            group_path(dev, controller, group, param),
+            validate_int(value, -100, 100)?,
+        ),
+        _ => return Err(format!("unsupported parameter {}/{}", controller, param)),
+    };
+
+    if !utils::file_exists(&path) {
+        return Err(format!("{} does not exist", path));
+    }
+    Ok(utils::write_sysfs_verified(&path, &value))
+}
+
+/// (controller, group, parameter, value)
+type ProfileWrite = (&'static str, &'static str, &'static str, String);
+
+/// Cpuset masks and uclamp/schedtune values for a perf mode. Little is the
+/// first cluster, prime the last one when there are three or more. `balance`
+/// has no profile of its own: it restores the vendor's values.
+fn profile(mode: &str, topology: &[Vec<i32>]) -> Option<Vec<ProfileWrite>> {
+    let all = cpu::format_cpu_list(&topology.concat());
+    let little = cpu::format_cpu_list(topology.first()?);
+    let no_prime = if topology.len() >= 3 {
+        cpu::format_cpu_list(&topology[..topology.len() - 1].concat())
+    } else {
+        all.clone()
+    };
+
+    let (top_app, foreground, uclamp_min, uclamp_max, latency, boost, prefer_idle) = match mode {
+        "performance" => (all.clone(), all, "20", "max", "1", "10", "1"),
+        "battery" => (no_prime, little.clone(), "0", "80", "0", "0", "0"),
+        _ => return None,
+    };
+
+    Some(vec![
+        ("cpuset", "top-app", "cpus", top_app),
+        ("cpuset", "foreground", "cpus", foreground),
+        ("cpuset", "background", "cpus", little.clone()),
+        ("cpuset", "system-background", "cpus", little.clone()),
+        ("cpuset", "restricted", "cpus", little),
+        (
+            "cpuctl",
+            "top-app",
+            "cpu.uclamp.max",
+            uclamp_max.to_string(),
+        ),
+        (
+            "cpuctl",
+            "top-app",
+            "cpu.uclamp.min",
+            uclamp_min.to_string(),
+        ),
+        (
+            "cpuctl",
+            "top-app",
+            "cpu.uclamp.latency_sensitive",
+            latency.to_string(),
+        ),
+        ("stune", "top-app", "schedtune.boost", boost.to_string()),
+        (
+            "stune",
+            "top-app",
+            "schedtune.prefer_idle",
+            prefer_idle.to_string(),
+        ),
+    ])
+}
+
+/// Node a profile write lands on, when the kernel has it.
+fn profile_path(dev: &str, controller: &str, group: &str, param: &str) -> Option<String> {
+    if controller == "cpuset" {
+        return cpuset_file(dev, group);
+    }
+    let path = group_path(dev, controller, group, param);
+    utils::file_exists(&path).then_some(path)
+}
+
+fn apply_profile_at(
+    dev: &str,
+    cpu_root: &str,
+    mode: &str,
+    originals: &mut Option<ProfileOriginals>,
+) -> Result<SchedProfileReport, String> {
+    let mut report = SchedProfileReport {
+        mode: mode.to_string(),
+        results: Vec::new(),
+        errors: Vec::new(),
+        restored: false,
+    };
+
+    if mode == "balance" {
+        if let Some(saved) = originals.take() {
+            report.results = saved
+                .iter()
+                .map(|(path, value)| utils::write_sysfs_verified(path, value))
+                .collect();
+            report.restored = true;
+        }
+        return Ok(report);
+    }
+
+    let online = |c: &i32| {
+        utils::read_sysfs_full(&format!("{}/cpu{}/online", cpu_root, c)).as_deref() != Some("0")
+    };
+    let topology: Vec<Vec<i32>> = cpu::cluster_topology(cpu_root)
+        .into_iter()
+        .map(|cluster| cluster.into_iter().filter(online).collect::<Vec<_>>())
+        .filter(|cluster| !cluster.is_empty())
+        .collect();
+    let writes = profile(mode, &topology).ok_or_else(|| format!("unknown perf mode '{}'", mode))?;
+
+    // Nodes the kernel does not have (no uclamp, no schedtune) are skipped.
+    let writes: Vec<(String, ProfileWrite)> = writes
+        .into_iter()
+        .filter_map(|w| Some((profile_path(dev, w.0, w.1, w.2)?, w)))
+        .collect();
+
+    // Switching between profiles keeps the values from before the first one.
+    if originals.is_none() {
+        *originals = Some(
+            writes
+                .iter()
+                .filter_map(|(path, _)| Some((path.clone(), read(path)?)))
+                .collect(),
+        );
+    }
+
+    for (_, (controller, group, param, value)) in writes {
+        match write_cgroup_at(dev, cpu_root, controller, group, param, &value) {
+            Ok(result) => report.results.push(result),
+            Err(error) => report
+                .errors
+                .push(format!("{}/{}/{}: {}", controller, group, param, error)),
+        }
+    }
+    Ok(report)
+}
+
+pub fn read_cgroups() -> CgroupState {
+    read_cgroups_at(DEV_ROOT)
+}
+
+/// Validated write of one cgroup parameter: `cpus` under cpuset,
+/// `cpu.uclamp.*`/`cpu.shares` under cpuctl, `schedtune.*` under stune.
+pub fn write_cgroup(
+    controller: &str,
+    group: &str,
+    param: &str,
+    value: &str,
+) -> Result<WriteResult, String> {
+    write_cgroup_at(DEV_ROOT, CPU_ROOT, controller, group, param, value)
+}
+
+/// Maps the app's perf mode to cpusets, uclamp and schedtune. `battery` and
+/// `performance` record the current values first; `balance` restores them.
+pub fn apply_sched_profile(mode: &str) -> Result<SchedProfileReport, String> {
+    apply_profile_at(
+        DEV_ROOT,
+        CPU_ROOT,
+        mode,
+        &mut PROFILE_ORIGINALS.lock().unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn tree(name: &str) -> (String, String) {
+        let root = fixture::build(
+            name,
+            &[
+                ("cpu/cpufreq/policy0/related_cpus", "0 1 2 3"),
+                ("cpu/cpufreq/policy4/related_cpus", "4 5 6"),
+                ("cpu/cpufreq/policy7/related_cpus", "7"),
+                ("cpu/cpu6/online", "0"),
+                ("dev/cpuset/top-app/cpus", "0-7"),
+                ("dev/cpuset/background/cpus", "0-3"),
+                ("dev/cpuset/restricted/cpus", "0-3"),
+                ("dev/cpuctl/cpu.shares", "1024"),
+                ("dev/cpuctl/top-app/cpu.shares", "1024"),
+                ("dev/cpuctl/top-app/cpu.uclamp.min", "0.00"),
+                ("dev/cpuctl/top-app/cpu.uclamp.max", "max"),
+                ("dev/cpuctl/top-app/cpu.uclamp.latency_sensitive", "0"),
+                ("dev/cpuctl/background/cpu.shares", "52"),
+            ],
+        );
+        (format!("{}/dev", root), format!("{}/cpu", root))
+    }
+
+    #[test]
+    fn test_read_cgroups() {
+        let (dev, _) = tree("cgroup_read");
+        let state = read_cgroups_at(&dev);
+
+        assert_eq!(state.cpuset.len(), 3);
+        assert_eq!(state.cpuset[0].cpu_list, (0..8).collect::<Vec<_>>());
+        assert_eq!(state.cpuctl.len(), 3);
+        assert_eq!(state.cpuctl[0].group, "");
+        let top = state.cpuctl.iter().find(|g| g.group == "top-app").unwrap();
+        assert_eq!(top.uclamp_max.as_deref(), Some("max"));
+        assert_eq!(top.uclamp_latency_sensitive, Some(false));
+        assert!(state.schedtune.is_empty());
+    }
+
+    #[test]
+    fn test_write_validation() {
+        let (dev, cpu_root) = tree("cgroup_write");
+        let write = |c, g, p, v| write_cgroup_at(&dev, &cpu_root, c, g, p, v);
+
+        assert!(write("cpuset", "background", "cpus", "0-9").is_err());
+        assert!(write("cpuset", "background", "cpus", "4-6").is_err());
+        let ok = write("cpuset", "background", "cpus", "0,1,2").unwrap();
+        assert!(ok.success);
+        assert_eq!(ok.actual, "0-2");
+
+        assert_eq!(
+            write("cpuctl", "top-app", "cpu.uclamp.min", "30")
+                .unwrap()
+                .actual,
+            "30.00"
+        );
+        assert!(write("cpuctl", "top-app", "cpu.uclamp.max", "20").is_err());
+        assert!(write("cpuctl", "background", "cpu.shares", "1").is_err());
+        assert!(write("stune", "top-app", "schedtune.boost", "10").is_err());
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let (dev, cpu_root) = tree("cgroup_profile");
+        let mut originals = None;
+
+        let report = apply_profile_at(&dev, &cpu_root, "battery", &mut originals).unwrap();
+        assert!(report.results.iter().all(|r| r.success));
+        assert!(report.errors.is_empty());
+        let state = read_cgroups_at(&dev);
+        // Offline cpu6 is left out of the masks.
+        assert_eq!(state.cpuset[0].cpus, "0-5");
+        assert_eq!(state.cpuset[1].cpus, "0-3");
+        let top = state.cpuctl.iter().find(|g| g.group == "top-app").unwrap();
+        assert_eq!(top.uclamp_max.as_deref(), Some("80.00"));
+
+        apply_profile_at(&dev, &cpu_root, "performance", &mut originals).unwrap();
+        assert!(apply_profile_at(&dev, &cpu_root, "turbo", &mut originals).is_err());
+
+        let report = apply_profile_at(&dev, &cpu_root, "balance", &mut originals).unwrap();
+        assert!(report.restored);
+        assert!(report.results.iter().all(|r| r.success));
+        let state = read_cgroups_at(&dev);
+        assert_eq!(state.cpuset[0].cpus, "0-7");
+        assert_eq!(state.cpuset[1].cpus, "0-3");
+        let top = state.cpuctl.iter().find(|g| g.group == "top-app").unwrap();
+        assert_eq!(top.uclamp_min.as_deref(), Some("0.00"));
+        assert_eq!(top.uclamp_max.as_deref(), Some("max"));
+        assert!(originals.is_none());
+    }
+
+    #[test]
+    fn test_profile_reports_rejected_values() {
+        let (dev, cpu_root) = tree("cgroup_profile_errors");
+        // A uclamp.min above the profile's uclamp.max is refused, not dropped.
+        std::fs::write(format!("{}/cpuctl/top-app/cpu.uclamp.min", dev), "90.00").unwrap();
+
+        let report = apply_profile_at(&dev, &cpu_root, "battery", &mut None).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].starts_with("cpuctl/top-app/cpu.uclamp.max"));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cgroup;
+pub mod sysctl;
+pub use cgroup::*;
+pub use sysctl::*;
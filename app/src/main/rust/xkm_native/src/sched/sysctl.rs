@@ -0,0 +1,303 @@
+use crate::utils::{self, WriteResult};
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::PermissionsExt;
+
+const PROC_SYS: &str = "/proc/sys";
+
+/// Directories under `/proc/sys` that hold scheduler sysctls, with the file
+/// prefix to enumerate.
+const SOURCES: [(&str, &str, &str); 2] = [("kernel", "kernel", "sched_"), ("walt", "walt", "")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SysctlKind {
+    Integer,
+    /// One value per cluster, e.g. `sched_upmigrate = 95 95`.
+    IntegerList,
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedSysctl {
+    pub name: String,
+    pub source: String,
+    pub path: String,
+    pub value: String,
+    pub kind: SysctlKind,
+    pub known: bool,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub description: Option<String>,
+    pub writable: bool,
+}
+
+use SysctlKind::{Integer as Int, IntegerList as List};
+
+/// (name, kind, min, max, description). WALT exposes many of the same names
+/// under `/proc/sys/walt`, so entries apply to both locations.
+#[rustfmt::skip]
+const CATALOG: &[(&str, SysctlKind, i64, i64, &str)] = &[
+    ("sched_upmigrate", List, 0, 100, "Task load % of the current cluster's capacity above which it moves to a bigger cluster"),
+    ("sched_downmigrate", List, 0, 100, "Task load % below which it moves back to a smaller cluster; must not exceed upmigrate"),
+    ("sched_group_upmigrate", Int, 0, 1000, "Colocated group load % that moves the group to a bigger cluster"),
+    ("sched_group_downmigrate", Int, 0, 1000, "Colocated group load % that moves the group back; must not exceed group_upmigrate"),
+    ("sched_boost", Int, -3, 3, "WALT boost: 0 off, 1 full throttle, 2 conservative, 3 restrained; negative values undo"),
+    ("sched_util_clamp_min", Int, 0, 1024, "System-wide cap on the uclamp.min any task may request"),
+    ("sched_util_clamp_max", Int, 0, 1024, "System-wide cap on the uclamp.max any task may request"),
+    ("sched_util_clamp_min_rt_default", Int, 0, 1024, "Default uclamp.min for RT tasks; 1024 runs them at max frequency"),
+    ("sched_child_runs_first", Int, 0, 1, "Run the child before the parent after fork"),
+    ("sched_latency_ns", Int, 100_000, 1_000_000_000, "CFS targeted preemption latency"),
+    ("sched_min_granularity_ns", Int, 100_000, 1_000_000_000, "Minimum CFS timeslice"),
+    ("sched_wakeup_granularity_ns", Int, 0, 1_000_000_000, "How much vruntime advantage a waking task needs to preempt"),
+    ("sched_migration_cost_ns", Int, 0, 1_000_000_000, "Time after which a task is considered cache-cold for migration"),
+    ("sched_nr_migrate", Int, 1, 1024, "Tasks moved per load-balance pass"),
+    ("sched_rt_period_us", Int, 1, 2_147_483_647, "Period over which RT bandwidth is measured"),
+    ("sched_rt_runtime_us", Int, -1, 2_147_483_647, "RT runtime allowed per period; -1 disables throttling"),
+    ("sched_rr_timeslice_ms", Int, 1, 1000, "SCHED_RR timeslice"),
+    ("sched_schedstats", Int, 0, 1, "Collect scheduler statistics"),
+    ("sched_autogroup_enabled", Int, 0, 1, "Group tasks by session for CFS fairness"),
+    ("sched_tunable_scaling", Int, 0, 2, "Scale CFS tunables with CPU count: 0 none, 1 log, 2 linear"),
+    ("sched_energy_aware", Int, 0, 1, "Energy-aware scheduling"),
+    ("sched_walt_rotate_big_tasks", Int, 0, 1, "Rotate big tasks across the big cluster"),
+    ("sched_ravg_window_nr_ticks", Int, 1, 20, "WALT window size in scheduler ticks"),
+    ("sched_min_task_util_for_boost", Int, 0, 1024, "Minimum task util for sched_boost to place it on big cores"),
+    ("sched_min_task_util_for_colocation", Int, 0, 1024, "Minimum task util to count toward colocation"),
+    ("sched_conservative_pl", Int, 0, 1, "Conservative predicted load"),
+    ("sched_busy_hyst_ns", Int, 0, 1_000_000_000, "How long a CPU stays busy after its load drops"),
+    ("sched_coloc_downmigrate_ns", Int, 0, 1_000_000_000, "Delay before a colocated group migrates down"),
+    ("sched_idle_enough", Int, 0, 100, "Util % below which a CPU is idle enough to pull tasks"),
+    ("sched_cluster_util_thres_pct", Int, 0, 100, "Cluster util % that enables task spreading"),
+    ("sched_force_lb_enable", Int, 0, 1, "Force load balancing on newly idle CPUs"),
+    ("sched_sync_hint_enable", Int, 0, 1, "Honour the sync wakeup hint"),
+    ("sched_prefer_spread", Int, 0, 4, "Spread tasks instead of packing: 0 off, higher spreads more"),
+    ("sched_many_wakeup_threshold", Int, 0, 10_000, "Wakeups per window that mark a task as a waker of many"),
+];
+
+/// Pairs whose first value must not exceed the second, element-wise.
+const ORDERED_PAIRS: [(&str, &str); 3] = [
+    ("sched_downmigrate", "sched_upmigrate"),
+    ("sched_group_downmigrate", "sched_group_upmigrate"),
+    ("sched_util_clamp_min", "sched_util_clamp_max"),
+];
+
+fn catalog(name: &str) -> Option<&'static (&'static str, SysctlKind, i64, i64, &'static str)> {
+    CATALOG.iter().find(|entry| entry.0 == name)
+}
+
+fn parse_ints(value: &str) -> Option<Vec<i64>> {
+    value.split_whitespace().map(|v| v.parse().ok()).collect()
+}
+
+fn is_writable(path: &str) -> bool {
+    std::fs::metadata(path)
+        .map(|m| m.permissions().mode() & 0o222 != 0)
+        .unwrap_or(false)
+}
+
+fn source_dir(root: &str, source: &str) -> Option<String> {
+    SOURCES
+        .iter()
+        .find(|(name, _, _)| *name == source)
+        .map(|(_, dir, _)| format!("{}/{}", root, dir))
+}
+
+fn read_sysctls_at(root: &str) -> Vec<SchedSysctl> {
+    let mut sysctls = Vec::new();
+
+    for (source, dir, prefix) in SOURCES {
+        let dir = format!("{}/{}", root, dir);
+        for name in utils::list_dir(&dir) {
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            let path = format!("{}/{}", dir, name);
+            // Directories such as kernel/sched_domain are not sysctls.
+            if std::fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(true) {
+                continue;
+            }
+            let value = utils::read_sysfs_full(&path).unwrap_or_default();
+
+            let (kind, min, max, description) = match catalog(&name) {
+                Some(&(_, kind, min, max, desc)) => {
+                    (kind, Some(min), Some(max), Some(desc.to_string()))
+                }
+                None => {
+                    let kind = match parse_ints(&value) {
+                        Some(v) if v.len() == 1 => SysctlKind::Integer,
+                        Some(v) if v.len() > 1 => SysctlKind::IntegerList,
+                        _ => SysctlKind::Text,
+                    };
+                    (kind, None, None, None)
+                }
+            };
+
+            sysctls.push(SchedSysctl {
+                known: description.is_some(),
+                writable: is_writable(&path),
+                name,
+                source: source.to_string(),
+                path,
+                value,
+                kind,
+                min,
+                max,
+                description,
+            });
+        }
+    }
+
+    sysctls
+}
+
+fn validate(dir: &str, name: &str, current: &str, value: &str) -> Result<String, String> {
+    let entry = catalog(name);
+    let kind = entry.map(|e| e.1);
+
+    if kind.is_none() && parse_ints(current).is_none() {
+        return Err(format!("{} is not a numeric sysctl", name));
+    }
+
+    let values =
+        parse_ints(value).ok_or_else(|| format!("'{}' is not a list of integers", value))?;
+    let expected = match kind {
+        Some(SysctlKind::Integer) => 1,
+        _ => parse_ints(current).map(|c| c.len()).unwrap_or(1).max(1),
+    };
+    if values.len() != expected {
+        return Err(format!("{} expects {} value(s)", name, expected));
+    }
+
+    if let Some(&(_, _, min, max, _)) = entry
+        && let Some(v) = values.iter().find(|v| !(min..=max).contains(*v))
+    {
+        return Err(format!("{} out of range {}..={} for {}", v, min, max, name));
+    }
+
+    for (low, high) in ORDERED_PAIRS {
+        let (lows, highs) = if name == low {
+            let other =
+                utils::read_sysfs_full(&format!("{}/{}", dir, high)).and_then(|v| parse_ints(&v));
+            (values.clone(), other)
+        } else if name == high {
+            let other =
+                utils::read_sysfs_full(&format!("{}/{}", dir, low)).and_then(|v| parse_ints(&v));
+            match other {
+                Some(other) => (other, Some(values.clone())),
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+
+        if let Some(highs) = highs
+            && lows.iter().zip(&highs).any(|(l, h)| l > h)
+        {
+            return Err(format!("{} must not exceed {}", low, high));
+        }
+    }
+
+    Ok(values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+fn write_sysctl_at(
+    root: &str,
+    source: &str,
+    name: &str,
+    value: &str,
+) -> Result<WriteResult, String> {
+    if name.contains('/') || name.contains("..") {
+        return Err(format!("invalid sysctl name '{}'", name));
+    }
+    let dir = source_dir(root, source).ok_or_else(|| format!("unknown source '{}'", source))?;
+    let path = format!("{}/{}", dir, name);
+    let current =
+        utils::read_sysfs_full(&path).ok_or_else(|| format!("{} does not exist", path))?;
+
+    let value = validate(&dir, name, &current, value)?;
+    let mut result = utils::write_sysfs_verified(&path, &value);
+    // procfs prints lists tab-separated on some kernels.
+    if !result.success && parse_ints(&result.actual) == parse_ints(&value) {
+        result.success = true;
+    }
+    Ok(result)
+}
+
+/// Scheduler sysctls from `/proc/sys/kernel/sched_*` and `/proc/sys/walt`,
+/// annotated from the embedded catalog where the name is known.
+pub fn read_sched_sysctls() -> Vec<SchedSysctl> {
+    read_sysctls_at(PROC_SYS)
+}
+
+/// `source` is `kernel` or `walt`. Known sysctls are range-checked; unknown
+/// ones must keep the current value count.
+pub fn write_sched_sysctl(source: &str, name: &str, value: &str) -> Result<WriteResult, String> {
+    write_sysctl_at(PROC_SYS, source, name, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    fn tree(name: &str) -> String {
+        fixture::build(
+            name,
+            &[
+                ("kernel/sched_child_runs_first", "0\n"),
+                ("kernel/sched_util_clamp_min", "1024\n"),
+                ("kernel/sched_util_clamp_max", "1024\n"),
+                ("kernel/sched_domain/cpu0/flags", "x\n"),
+                ("kernel/printk", "4 4 1 7\n"),
+                ("walt/sched_upmigrate", "95\t95\n"),
+                ("walt/sched_downmigrate", "85\t85\n"),
+                ("walt/sched_boost", "0\n"),
+                ("walt/sched_lib_name", "com.example\n"),
+                ("walt/sched_custom_knob", "5 6\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_read_sysctls() {
+        let root = tree("sysctl_read");
+        let sysctls = read_sysctls_at(&root);
+
+        assert_eq!(sysctls.len(), 8);
+        let up = sysctls
+            .iter()
+            .find(|s| s.name == "sched_upmigrate")
+            .unwrap();
+        assert_eq!(up.source, "walt");
+        assert_eq!(up.kind, SysctlKind::IntegerList);
+        assert_eq!(up.max, Some(100));
+        let custom = sysctls
+            .iter()
+            .find(|s| s.name == "sched_custom_knob")
+            .unwrap();
+        assert!(!custom.known);
+        assert_eq!(custom.kind, SysctlKind::IntegerList);
+        let lib = sysctls.iter().find(|s| s.name == "sched_lib_name").unwrap();
+        assert_eq!(lib.kind, SysctlKind::Text);
+    }
+
+    #[test]
+    fn test_write_validation() {
+        let root = tree("sysctl_write");
+        let write = |source, name, value| write_sysctl_at(&root, source, name, value);
+
+        assert!(write("walt", "sched_boost", "5").is_err());
+        assert!(write("walt", "sched_boost", "1").unwrap().success);
+        assert!(write("walt", "sched_upmigrate", "95").is_err());
+        assert!(write("walt", "sched_downmigrate", "96 80").is_err());
+        assert!(write("walt", "sched_upmigrate", "80 90").is_err());
+        assert!(write("walt", "sched_upmigrate", "90 99").unwrap().success);
+        assert!(write("kernel", "sched_util_clamp_max", "512").is_err());
+        assert!(write("walt", "sched_lib_name", "1").is_err());
+        assert!(write("walt", "sched_custom_knob", "1 2").unwrap().success);
+        assert!(write("kernel", "../kernel/printk", "1").is_err());
+    }
+}
@@ -0,0 +1,466 @@
+use crate::utils;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const SYS_ROOT: &str = "/sys";
+
+const MAX_RATE_HZ: u32 = 100;
+/// Per-second buckets kept per channel; longer sessions keep the histogram
+/// and totals only.
+const MAX_SECONDS: usize = 4 * 3600;
+/// Throttle events listed per domain; the count keeps going.
+const MAX_EVENTS: usize = 64;
+
+/// GPU clock nodes, first readable wins. Units vary and are normalised by
+/// magnitude.
+const GPU_CLOCKS: [&str; 4] = [
+    "class/kgsl/kgsl-3d0/gpuclk",
+    "class/kgsl/kgsl-3d0/devfreq/cur_freq",
+    "class/misc/mali0/device/clock",
+    "devices/platform/mali/clock",
+];
+const GPU_LIMITS: [&str; 2] = [
+    "class/kgsl/kgsl-3d0/max_gpuclk",
+    "class/kgsl/kgsl-3d0/devfreq/max_freq",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSummary {
+    /// `cpu0`..`cpuN` or `gpu`.
+    pub name: String,
+    pub min_mhz: u32,
+    pub avg_mhz: f32,
+    pub max_mhz: u32,
+    /// `[mhz, samples]`, ascending.
+    pub histogram: Vec<(u32, u32)>,
+    /// `[second, min, avg, max]` per second of the session.
+    pub seconds: Vec<(u32, u32, u32, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainThrottles {
+    /// `policyN` or `gpu`.
+    pub domain: String,
+    /// Times the domain's max frequency dropped.
+    pub count: u32,
+    /// `[t_ms, from_mhz, to_mhz]` for the first events.
+    pub events: Vec<(u64, u32, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreqTraceSummary {
+    pub running: bool,
+    pub rate_hz: u32,
+    pub duration_ms: u64,
+    pub samples: u64,
+    /// Ticks that started late because the previous one overran the period.
+    pub overruns: u64,
+    pub channels: Vec<ChannelSummary>,
+    pub throttles: Vec<DomainThrottles>,
+}
+
+#[derive(Default)]
+struct Bucket {
+    second: u32,
+    min: u32,
+    max: u32,
+    sum: u64,
+    count: u32,
+}
+
+struct Channel {
+    name: String,
+    file: File,
+    unit: Unit,
+    min: u32,
+    max: u32,
+    sum: u64,
+    count: u64,
+    histogram: BTreeMap<u32, u32>,
+    seconds: Vec<(u32, u32, u32, u32)>,
+    bucket: Bucket,
+}
+
+struct Domain {
+    file: File,
+    unit: Unit,
+    last: Option<u32>,
+    throttles: DomainThrottles,
+}
+
+struct TraceState {
+    rate_hz: u32,
+    elapsed_ms: u64,
+    samples: u64,
+    overruns: u64,
+    channels: Vec<Channel>,
+    domains: Vec<Domain>,
+}
+
+struct Tracer {
+    state: Arc<Mutex<TraceState>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// The running tracer, or the last one until the next start.
+static TRACER: Lazy<Mutex<Option<Tracer>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Copy)]
+enum Unit {
+    KHz,
+    /// GPU nodes report Hz, kHz or MHz depending on the driver.
+    Guess,
+}
+
+/// Reads a pre-opened sysfs node from offset 0 and converts it to MHz.
+fn read_mhz(file: &File, unit: Unit) -> Option<u32> {
+    let mut buf = [0u8; 32];
+    let len = file.read_at(&mut buf, 0).ok()?;
+    let raw: u64 = std::str::from_utf8(&buf[..len]).ok()?.trim().parse().ok()?;
+
+    Some(match unit {
+        Unit::KHz => (raw / 1000) as u32,
+        Unit::Guess => utils::to_mhz(raw) as u32,
+    })
+}
+
+impl Bucket {
+    fn flush(&self) -> Option<(u32, u32, u32, u32)> {
+        (self.count > 0).then(|| {
+            (
+                self.second,
+                self.min,
+                (self.sum / self.count as u64) as u32,
+                self.max,
+            )
+        })
+    }
+}
+
+impl Channel {
+    fn new(name: String, file: File, unit: Unit) -> Self {
+        Channel {
+            name,
+            file,
+            unit,
+            min: u32::MAX,
+            max: 0,
+            sum: 0,
+            count: 0,
+            histogram: BTreeMap::new(),
+            seconds: Vec::new(),
+            bucket: Bucket::default(),
+        }
+    }
+
+    fn record(&mut self, t_ms: u64, mhz: u32) {
+        self.min = self.min.min(mhz);
+        self.max = self.max.max(mhz);
+        self.sum += mhz as u64;
+        self.count += 1;
+        *self.histogram.entry(mhz).or_default() += 1;
+
+        let second = (t_ms / 1000) as u32;
+        if self.bucket.count > 0 && self.bucket.second != second {
+            if let Some(done) = self.bucket.flush()
+                && self.seconds.len() < MAX_SECONDS
+            {
+                self.seconds.push(done);
+            }
+            self.bucket = Bucket::default();
+        }
+        if self.bucket.count == 0 {
+            self.bucket = Bucket {
+                second,
+                min: mhz,
+                max: mhz,
+                sum: 0,
+                count: 0,
+            };
+        }
+        self.bucket.min = self.bucket.min.min(mhz);
+        self.bucket.max = self.bucket.max.max(mhz);
+        self.bucket.sum += mhz as u64;
+        self.bucket.count += 1;
+    }
+
+    fn summary(&self) -> ChannelSummary {
+        let mut seconds = self.seconds.clone();
+        if let Some(open) = self.bucket.flush()
+            && seconds.len() < MAX_SECONDS
+        {
+            seconds.push(open);
+        }
+
+        ChannelSummary {
+            name: self.name.clone(),
+            min_mhz: if self.count > 0 { self.min } else { 0 },
+            avg_mhz: if self.count > 0 {
+                self.sum as f32 / self.count as f32
+            } else {
+                0.0
+            },
+            max_mhz: self.max,
+            histogram: self.histogram.iter().map(|(&mhz, &n)| (mhz, n)).collect(),
+            seconds,
+        }
+    }
+}
+
+impl Domain {
+    fn record(&mut self, t_ms: u64, mhz: u32) {
+        if let Some(last) = self.last
+            && mhz < last
+        {
+            self.throttles.count += 1;
+            if self.throttles.events.len() < MAX_EVENTS {
+                self.throttles.events.push((t_ms, last, mhz));
+            }
+        }
+        self.last = Some(mhz);
+    }
+}
+
+impl TraceState {
+    fn sample(&mut self, t_ms: u64) {
+        for channel in &mut self.channels {
+            if let Some(mhz) = read_mhz(&channel.file, channel.unit) {
+                channel.record(t_ms, mhz);
+            }
+        }
+        for domain in &mut self.domains {
+            if let Some(mhz) = read_mhz(&domain.file, domain.unit) {
+                domain.record(t_ms, mhz);
+            }
+        }
+        self.samples += 1;
+        self.elapsed_ms = t_ms;
+    }
+
+    fn summary(&self, running: bool) -> FreqTraceSummary {
+        FreqTraceSummary {
+            running,
+            rate_hz: self.rate_hz,
+            duration_ms: self.elapsed_ms,
+            samples: self.samples,
+            overruns: self.overruns,
+            channels: self.channels.iter().map(Channel::summary).collect(),
+            throttles: self.domains.iter().map(|d| d.throttles.clone()).collect(),
+        }
+    }
+}
+
+/// Opens every core's `scaling_cur_freq`, each policy's `scaling_max_freq`
+/// and the GPU clock once, so the sampling loop only issues `pread`s.
+fn open_state(sys: &str, rate_hz: u32) -> TraceState {
+    let cpu_root = format!("{}/devices/system/cpu", sys);
+    let open = |path: &str| File::open(path).ok();
+
+    let mut channels = Vec::new();
+    for cpu in 0..16 {
+        if !std::path::Path::new(&format!("{}/cpu{}", cpu_root, cpu)).exists() {
+            break;
+        }
+        if let Some(file) = open(&format!("{}/cpu{}/cpufreq/scaling_cur_freq", cpu_root, cpu)) {
+            channels.push(Channel::new(format!("cpu{}", cpu), file, Unit::KHz));
+        }
+    }
+    if let Some(file) = GPU_CLOCKS
+        .iter()
+        .find_map(|p| open(&format!("{}/{}", sys, p)))
+    {
+        channels.push(Channel::new("gpu".to_string(), file, Unit::Guess));
+    }
+
+    let domain = |name: String, file: File, unit: Unit| Domain {
+        throttles: DomainThrottles {
+            domain: name,
+            count: 0,
+            events: Vec::new(),
+        },
+        file,
+        unit,
+        last: None,
+    };
+    let mut domains: Vec<Domain> = crate::cpu::cluster_topology(&cpu_root)
+        .iter()
+        .filter_map(|cpus| {
+            let policy = cpus.first()?;
+            let file = open(&format!(
+                "{}/cpufreq/policy{}/scaling_max_freq",
+                cpu_root, policy
+            ))?;
+            Some(domain(format!("policy{}", policy), file, Unit::KHz))
+        })
+        .collect();
+    if let Some(file) = GPU_LIMITS
+        .iter()
+        .find_map(|p| open(&format!("{}/{}", sys, p)))
+    {
+        domains.push(domain("gpu".to_string(), file, Unit::Guess));
+    }
+
+    TraceState {
+        rate_hz,
+        elapsed_ms: 0,
+        samples: 0,
+        overruns: 0,
+        channels,
+        domains,
+    }
+}
+
+fn run(state: Arc<Mutex<TraceState>>, stop: Arc<AtomicBool>, period: Duration) {
+    let start = Instant::now();
+    let mut next = start;
+
+    while !stop.load(Ordering::SeqCst) {
+        state
+            .lock()
+            .unwrap()
+            .sample(start.elapsed().as_millis() as u64);
+
+        next += period;
+        let now = Instant::now();
+        if next > now {
+            std::thread::sleep(next - now);
+        } else {
+            state.lock().unwrap().overruns += 1;
+            next = now;
+        }
+    }
+}
+
+/// Starts sampling at `rate_hz` (1-100) on a dedicated thread. Fails if a
+/// trace is already running or no frequency node could be opened.
+pub fn start_freq_trace(rate_hz: u32) -> Result<FreqTraceSummary, String> {
+    let mut tracer = TRACER.lock().unwrap();
+    if tracer.as_ref().is_some_and(|t| t.handle.is_some()) {
+        return Err("A frequency trace is already running".to_string());
+    }
+
+    let rate_hz = rate_hz.clamp(1, MAX_RATE_HZ);
+    let state = open_state(SYS_ROOT, rate_hz);
+    if state.channels.is_empty() {
+        return Err("No readable CPU or GPU frequency nodes".to_string());
+    }
+    let summary = state.summary(true);
+
+    let state = Arc::new(Mutex::new(state));
+    let stop = Arc::new(AtomicBool::new(false));
+    let period = Duration::from_secs(1) / rate_hz;
+    let handle = {
+        let (state, stop) = (state.clone(), stop.clone());
+        std::thread::Builder::new()
+            .name("xkm-freq-trace".to_string())
+            .spawn(move || run(state, stop, period))
+            .map_err(|e| e.to_string())?
+    };
+
+    *tracer = Some(Tracer {
+        state,
+        stop,
+        handle: Some(handle),
+    });
+    Ok(summary)
+}
+
+/// Stops the running trace and returns its summary. Without a running trace
+/// the last session's summary is returned, if any.
+pub fn stop_freq_trace() -> Option<FreqTraceSummary> {
+    let mut tracer = TRACER.lock().unwrap();
+    let tracer = tracer.as_mut()?;
+
+    if let Some(handle) = tracer.handle.take() {
+        tracer.stop.store(true, Ordering::SeqCst);
+        let _ = handle.join();
+    }
+    Some(tracer.state.lock().unwrap().summary(false))
+}
+
+/// Summary so far of the running trace, or of the last one.
+pub fn get_freq_trace_summary() -> Option<FreqTraceSummary> {
+    let tracer = TRACER.lock().unwrap();
+    let tracer = tracer.as_ref()?;
+    Some(
+        tracer
+            .state
+            .lock()
+            .unwrap()
+            .summary(tracer.handle.is_some()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixture;
+
+    #[test]
+    fn test_trace_sampling() {
+        let root = fixture::build(
+            "freq_trace",
+            &[
+                ("devices/system/cpu/cpu0/cpufreq/scaling_cur_freq", "300000"),
+                ("devices/system/cpu/cpu1/cpufreq/scaling_cur_freq", "300000"),
+                ("devices/system/cpu/cpufreq/policy0/related_cpus", "0 1"),
+                (
+                    "devices/system/cpu/cpufreq/policy0/scaling_max_freq",
+                    "1800000",
+                ),
+                ("class/kgsl/kgsl-3d0/gpuclk", "585000000"),
+                ("class/kgsl/kgsl-3d0/max_gpuclk", "680000000"),
+            ],
+        );
+        let write =
+            |rel: &str, value: &str| std::fs::write(format!("{}/{}", root, rel), value).unwrap();
+
+        let mut state = open_state(&root, 100);
+        assert_eq!(state.channels.len(), 3);
+        assert_eq!(state.domains.len(), 2);
+
+        state.sample(0);
+        write(
+            "devices/system/cpu/cpu0/cpufreq/scaling_cur_freq",
+            "1800000",
+        );
+        state.sample(500);
+        write(
+            "devices/system/cpu/cpu0/cpufreq/scaling_cur_freq",
+            "1200000",
+        );
+        write(
+            "devices/system/cpu/cpufreq/policy0/scaling_max_freq",
+            "1200000",
+        );
+        write("class/kgsl/kgsl-3d0/gpuclk", "315000000");
+        state.sample(1200);
+
+        let summary = state.summary(false);
+        let cpu0 = &summary.channels[0];
+        assert_eq!((cpu0.min_mhz, cpu0.max_mhz), (300, 1800));
+        assert_eq!(cpu0.histogram, vec![(300, 1), (1200, 1), (1800, 1)]);
+        assert_eq!(
+            cpu0.seconds,
+            vec![(0, 300, 1050, 1800), (1, 1200, 1200, 1200)]
+        );
+
+        let gpu = &summary.channels[2];
+        assert_eq!(gpu.name, "gpu");
+        assert_eq!((gpu.min_mhz, gpu.max_mhz), (315, 585));
+
+        assert_eq!(summary.throttles[0].domain, "policy0");
+        assert_eq!(summary.throttles[0].count, 1);
+        assert_eq!(summary.throttles[0].events, vec![(1200, 1800, 1200)]);
+        assert_eq!(summary.throttles[1].count, 0);
+        assert_eq!(summary.samples, 3);
+    }
+}
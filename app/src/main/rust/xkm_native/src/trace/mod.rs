@@ -0,0 +1,2 @@
+pub mod freq;
+pub use freq::*;
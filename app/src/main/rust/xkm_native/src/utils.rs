@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 use std::ffi::CString;
-use std::time::{Duration, Instant};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 
 #[cfg(windows)]
 #[inline]
@@ -20,7 +22,7 @@ unsafe fn libc_read_safe(fd: i32, buf: *mut libc::c_void, len: usize) -> isize {
 #[cfg(unix)]
 #[inline]
 pub fn read_file_rustix(path: &str) -> Option<String> {
-    use rustix::fs::{openat, CWD, OFlags, Mode};
+    use rustix::fs::{CWD, Mode, OFlags, openat};
     use rustix::io::read as rustix_read;
 
     let fd = openat(CWD, path, OFlags::RDONLY, Mode::empty()).ok()?;
@@ -49,11 +51,7 @@ pub fn read_file_libc(path: &str) -> Option<String> {
         }
 
         let mut buffer = [0u8; 512];
-        let bytes_read = libc_read_safe(
-            fd,
-            buffer.as_mut_ptr() as *mut libc::c_void,
-            buffer.len(),
-        );
+        let bytes_read = libc_read_safe(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len());
 
         libc::close(fd);
 
@@ -71,7 +69,7 @@ pub fn get_system_property(_key: &str) -> Option<String> {
     {
         android_properties::getprop(_key).value()
     }
-    
+
     #[cfg(not(target_os = "android"))]
     {
         None
@@ -88,11 +86,7 @@ pub fn read_file_libc_buf(path: &str, buf: &mut [u8]) -> Option<usize> {
             return None;
         }
 
-        let bytes_read = libc_read_safe(
-            fd, 
-            buf.as_mut_ptr() as *mut libc::c_void, 
-            buf.len()
-        );
+        let bytes_read = libc_read_safe(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len());
         libc::close(fd);
 
         if bytes_read <= 0 {
@@ -119,19 +113,22 @@ static VALUE_CACHE: Lazy<RwLock<HashMap<String, CachedValue>>> =
 pub fn read_sysfs_cached(path: &str, ttl_ms: u64) -> Option<String> {
     {
         let cache = VALUE_CACHE.read();
-        if let Some(cached) = cache.get(path) {
-            if cached.timestamp.elapsed() < Duration::from_millis(ttl_ms) {
-                return Some(cached.value.clone());
-            }
+        if let Some(cached) = cache.get(path)
+            && cached.timestamp.elapsed() < Duration::from_millis(ttl_ms)
+        {
+            return Some(cached.value.clone());
         }
     }
     let value = read_sysfs(path)?;
     {
         let mut cache = VALUE_CACHE.write();
-        cache.insert(path.to_string(), CachedValue {
-            value: value.clone(),
-            timestamp: Instant::now(),
-        });
+        cache.insert(
+            path.to_string(),
+            CachedValue {
+                value: value.clone(),
+                timestamp: Instant::now(),
+            },
+        );
     }
 
     Some(value)
@@ -147,15 +144,126 @@ pub fn read_sysfs_float(path: &str, ttl_ms: u64) -> Option<f32> {
     read_sysfs_cached(path, ttl_ms)?.parse().ok()
 }
 
+/// Reads a whole file, for tables that do not fit the 512-byte buffer of
+/// `read_sysfs` (`trans_stat`, `time_in_state`, long governor maps).
+pub fn read_sysfs_full(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// First whitespace-separated field of a node as a number, so `1804800`,
+/// `1804800\n` and `512 kB` rows all read. `None` when the node is missing or
+/// the field does not parse as `T`.
+pub fn read_sysfs_num<T: FromStr>(path: &str) -> Option<T> {
+    read_sysfs_full(path)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Bytes per kernel page, 16 KiB on some arm64 builds. Page counts in
+/// `/proc` and the Mali `gpu_memory` table use it.
+pub fn page_size() -> u64 {
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if size > 0 { size as u64 } else { 4096 }
+}
+
+/// Milliseconds since the Unix epoch, for event timestamps.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Clock nodes report Hz (devfreq), kHz (cpufreq, Pixel's kbase) or MHz
+/// (Exynos) depending on the driver. No clock in use sits below 10 MHz or
+/// above 10 GHz, so the magnitude tells the unit apart.
+pub fn to_mhz(value: u64) -> i32 {
+    let mhz = if value >= 10_000_000 {
+        value / 1_000_000
+    } else if value >= 10_000 {
+        value / 1000
+    } else {
+        value
+    };
+    mhz as i32
+}
+
+/// Scales `mhz` back to the unit `sample`, a value from the same node, is in.
+pub fn from_mhz(mhz: i32, sample: u64) -> u64 {
+    let mhz = mhz as u64;
+    if sample >= 10_000_000 {
+        mhz * 1_000_000
+    } else if sample >= 10_000 {
+        mhz * 1000
+    } else {
+        mhz
+    }
+}
+
+/// Lists the entries of a directory sorted by name. Missing directories yield
+/// an empty list.
+pub fn list_dir(path: &str) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+pub fn write_sysfs(path: &str, value: &str) -> bool {
+    use std::io::Write;
+
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(value.as_bytes()))
+        .is_ok();
+
+    VALUE_CACHE.write().remove(path);
+    written
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteResult {
+    pub path: String,
+    pub requested: String,
+    pub actual: String,
+    pub success: bool,
+}
+
+/// Writes `value` and reads the node back. `success` is only set when the
+/// kernel kept the exact value; callers that expect rounding compare `actual`
+/// themselves.
+pub fn write_sysfs_verified(path: &str, value: &str) -> WriteResult {
+    let written = write_sysfs(path, value);
+    let actual = read_sysfs_full(path).unwrap_or_default();
+
+    WriteResult {
+        path: path.to_string(),
+        requested: value.to_string(),
+        success: written && actual == value.trim(),
+        actual,
+    }
+}
+
 #[inline]
 pub fn file_exists(path: &str) -> bool {
     #[cfg(unix)]
     {
-        use rustix::fs::{accessat, Access, AtFlags, CWD};
-        
-        match accessat(CWD, path, Access::EXISTS, AtFlags::empty()) {
-            Ok(_) => return true,
-            Err(_) => {}
+        use rustix::fs::{Access, AtFlags, CWD, accessat};
+
+        if accessat(CWD, path, Access::EXISTS, AtFlags::empty()).is_ok() {
+            return true;
         }
     }
 
@@ -163,7 +271,7 @@ pub fn file_exists(path: &str) -> bool {
         unsafe {
             #[cfg(unix)]
             return libc::access(c_path.as_ptr(), libc::F_OK) == 0;
-            
+
             #[cfg(windows)]
             return libc::access(c_path.as_ptr(), 0) == 0;
         }
@@ -172,6 +280,36 @@ pub fn file_exists(path: &str) -> bool {
     false
 }
 
+#[cfg(test)]
+pub(crate) mod fixture {
+    /// Builds a throwaway sysfs-like tree under the temp dir and returns its
+    /// root. Paths in `files` are relative to that root.
+    pub fn build(name: &str, files: &[(&str, &str)]) -> String {
+        let files: Vec<(&str, Vec<u8>)> = files
+            .iter()
+            .map(|(rel, content)| (*rel, content.as_bytes().to_vec()))
+            .collect();
+        build_bytes(name, &files)
+    }
+
+    /// Same as `build` for binary content such as device-tree properties.
+    pub fn build_bytes(name: &str, files: &[(&str, Vec<u8>)]) -> String {
+        let root = std::env::temp_dir().join(format!("xkm_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        for (rel, content) in files {
+            let path = root.join(rel);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            std::fs::write(&path, content).unwrap();
+        }
+
+        std::fs::create_dir_all(&root).unwrap();
+        root.to_string_lossy().into_owned()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,7 +343,24 @@ mod tests {
     #[test]
     fn test_typed_readers() {
         if let Some(capacity) = read_sysfs_int("/sys/class/power_supply/battery/capacity", 100) {
-            assert!(capacity >= 0 && capacity <= 100);
+            assert!((0..=100).contains(&capacity));
+        }
+
+        let root = fixture::build("utils_num", &[("freq", "1804800\n"), ("mem", "512 kB")]);
+        assert_eq!(
+            read_sysfs_num::<u64>(&format!("{}/freq", root)),
+            Some(1804800)
+        );
+        assert_eq!(read_sysfs_num::<i64>(&format!("{}/mem", root)), Some(512));
+        assert_eq!(read_sysfs_num::<u64>(&format!("{}/missing", root)), None);
+    }
+
+    #[test]
+    fn test_mhz_units() {
+        for raw in [585_000_000, 585_000, 585] {
+            assert_eq!(to_mhz(raw), 585);
+            assert_eq!(from_mhz(585, raw), raw);
         }
+        assert_eq!(to_mhz(1_500_000), 1500);
     }
 }
@@ -124,32 +124,31 @@ fn test_memory() {
         println!("  Disk size: {} MB", disksize / 1024 / 1024);
     }
 
-    if let Some(algo) = read_string("/sys/block/zram0/comp_algorithm") {
-        if let Some(start) = algo.find('[') {
-            if let Some(end) = algo.find(']') {
-                println!("  Algorithm: {}", &algo[start + 1..end]);
-            }
-        }
+    if let Some(algo) = read_string("/sys/block/zram0/comp_algorithm")
+        && let Some(start) = algo.find('[')
+        && let Some(end) = algo.find(']')
+    {
+        println!("  Algorithm: {}", &algo[start + 1..end]);
     }
 
     if let Some(mm_stat) = read_string("/sys/block/zram0/mm_stat") {
         let parts: Vec<&str> = mm_stat.split_whitespace().collect();
-        if parts.len() >= 3 {
-            if let (Ok(orig), Ok(compr), Ok(mem_used)) = (
+        if parts.len() >= 3
+            && let (Ok(orig), Ok(compr), Ok(mem_used)) = (
                 parts[0].parse::<i64>(),
                 parts[1].parse::<i64>(),
                 parts[2].parse::<i64>(),
-            ) {
-                let ratio = if compr > 0 {
-                    orig as f64 / compr as f64
-                } else {
-                    1.0
-                };
-                println!("  Original data: {} MB", orig / 1024 / 1024);
-                println!("  Compressed: {} MB", compr / 1024 / 1024);
-                println!("  Memory used: {} MB", mem_used / 1024 / 1024);
-                println!("  Compression ratio: {:.2}x", ratio);
-            }
+            )
+        {
+            let ratio = if compr > 0 {
+                orig as f64 / compr as f64
+            } else {
+                1.0
+            };
+            println!("  Original data: {} MB", orig / 1024 / 1024);
+            println!("  Compressed: {} MB", compr / 1024 / 1024);
+            println!("  Memory used: {} MB", mem_used / 1024 / 1024);
+            println!("  Compression ratio: {:.2}x", ratio);
         }
     }
 
@@ -224,7 +223,7 @@ fn test_gpu() {
         if let Some(content) = read_string(path) {
             println!(
                 "GPU busy ({}): {}",
-                path.split('/').last().unwrap(),
+                path.split('/').next_back().unwrap(),
                 content
             );
         }
@@ -263,15 +262,15 @@ fn test_gpu_advanced() {
     // GPU temperature
     for zone in 0..20 {
         let type_path = format!("/sys/class/thermal/thermal_zone{}/type", zone);
-        if let Some(zone_type) = read_string(&type_path) {
-            if zone_type.to_lowercase().contains("gpu") {
-                let temp_path = format!("/sys/class/thermal/thermal_zone{}/temp", zone);
-                if let Some(temp) = read_int(&temp_path) {
-                    let temp_c = temp as f32 / 1000.0;
-                    if temp_c > 0.0 && temp_c < 150.0 {
-                        println!("GPU Temperature: {:.1}°C", temp_c);
-                        break;
-                    }
+        if let Some(zone_type) = read_string(&type_path)
+            && zone_type.to_lowercase().contains("gpu")
+        {
+            let temp_path = format!("/sys/class/thermal/thermal_zone{}/temp", zone);
+            if let Some(temp) = read_int(&temp_path) {
+                let temp_c = temp as f32 / 1000.0;
+                if temp_c > 0.0 && temp_c < 150.0 {
+                    println!("GPU Temperature: {:.1}°C", temp_c);
+                    break;
                 }
             }
         }
@@ -300,12 +299,10 @@ fn test_cpu() {
             read_int(&online_path).unwrap_or(0) == 1
         };
 
-        if is_online {
-            if let Some(freq) = read_int(&freq_path) {
-                println!("CPU{}: {}MHz", cpu, freq / 1000);
-                online_count += 1;
-                total_freq += freq as i64;
-            }
+        if is_online && let Some(freq) = read_int(&freq_path) {
+            println!("CPU{}: {}MHz", cpu, freq / 1000);
+            online_count += 1;
+            total_freq += freq as i64;
         }
     }
 
@@ -591,10 +588,10 @@ fn test_available_options() {
     println!("\nZRAM Devices:");
     for i in 0..4 {
         let path = format!("/sys/block/zram{}/disksize", i);
-        if let Some(size) = read_long(&path) {
-            if size > 0 {
-                println!("  zram{}: {} MB", i, size / 1024 / 1024);
-            }
+        if let Some(size) = read_long(&path)
+            && size > 0
+        {
+            println!("  zram{}: {} MB", i, size / 1024 / 1024);
         }
     }
 